@@ -0,0 +1,91 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use oca_rs::data_storage::StorageError;
+use oca_rs::facade::build::{self, ValidationError};
+use oca_rs::facade::{FetchError, SearchError};
+use serde::ser::SerializeStruct;
+use serde::Serialize;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Validation error")]
+    Validation(Vec<ValidationError>),
+    #[error("Deprecated")]
+    Deprecated,
+    #[error("Invalid SAID {0}")]
+    InvalidSaid(String),
+    #[error("Unknown language {0}")]
+    InvalidLanguage(String),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error(transparent)]
+    Search(#[from] SearchError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("Request handling failed: {0}")]
+    Internal(String),
+}
+
+impl Error {
+    /// Stable identifier of the error kind, the same as of the facade error
+    /// it wraps.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Validation(_) => "validation",
+            Self::Deprecated => "deprecated",
+            Self::InvalidSaid(_) => "invalid_said",
+            Self::InvalidLanguage(_) => "invalid_language",
+            Self::Fetch(e) => e.code(),
+            Self::Search(e) => e.code(),
+            Self::Storage(e) => e.code(),
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self.code() {
+            "validation" => StatusCode::UNPROCESSABLE_ENTITY,
+            "not_found" => StatusCode::NOT_FOUND,
+            "deprecated" => StatusCode::GONE,
+            "invalid_said" | "invalid_language" | "invalid_page" | "invalid_query" => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<build::Error> for Error {
+    fn from(error: build::Error) -> Self {
+        match error {
+            build::Error::ValidationError(errors) => Self::Validation(errors),
+            build::Error::Deprecated => Self::Deprecated,
+            build::Error::Storage(e) => Self::Storage(e),
+        }
+    }
+}
+
+/// Serialized as `code` and `message`, like errors of the facade. Validation
+/// errors are listed under `errors` in their own serialized form.
+impl Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let errors = match self {
+            Self::Validation(errors) => Some(errors),
+            _ => None,
+        };
+        let mut state = serializer.serialize_struct("Error", 2 + errors.is_some() as usize)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let Some(errors) = errors {
+            state.serialize_field("errors", errors)?;
+        }
+        state.end()
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        (self.status(), Json(self)).into_response()
+    }
+}
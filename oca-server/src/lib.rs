@@ -0,0 +1,467 @@
+//! HTTP server exposing [`Facade`] of an OCA repository as REST endpoints.
+//!
+//! Responses are JSON, except for OCAfiles served as plain text. Failures
+//! are [`Error`] serialized as `{"code": ..., "message": ...}`, validation
+//! failures additionally list serialized `ValidationError`s under `errors`.
+//!
+//! Requests share a single facade behind a mutex, so they are handled one at
+//! a time, on the blocking thread pool as the facade does blocking I/O.
+
+mod error;
+
+pub use error::Error;
+
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+#[cfg(feature = "local-references")]
+use oca_rs::data_storage::StorageError;
+#[cfg(feature = "local-references")]
+use oca_rs::facade::build::References;
+use oca_rs::Facade;
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "local-references")]
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+type SharedFacade = Arc<Mutex<Facade>>;
+
+/// Router serving the endpoints with the facade:
+///
+/// - `POST /oca-bundles` builds bundle from OCAfile in the request body,
+/// - `POST /oca-bundles/validate` validates OCAfile without storing it or
+///   binding its `-- name=` reference,
+/// - `GET /oca-bundles?limit=&page=` lists bundles,
+/// - `GET /oca-bundles/search?q=&lang=&limit=&page=` searches bundles,
+/// - `GET /oca-bundles/{said}?dependencies=true` fetches bundle,
+/// - `GET /oca-bundles/{said}/ocafile?dereference=true` fetches OCAfile,
+/// - `GET /oca-bundles/{said}/steps` fetches build steps,
+/// - `GET /oca-bundles/{said}/relations` explores related objects,
+/// - `GET /capture-bases?limit=&page=` lists capture bases,
+/// - `GET /objects?saids=` fetches comma separated capture bases and overlays.
+pub fn router(facade: Facade) -> Router {
+    Router::new()
+        .route("/oca-bundles", get(all_oca_bundles).post(build_oca_bundle))
+        .route("/oca-bundles/validate", post(validate_ocafile))
+        .route("/oca-bundles/search", get(search_oca_bundles))
+        .route("/oca-bundles/:said", get(oca_bundle))
+        .route("/oca-bundles/:said/ocafile", get(oca_bundle_ocafile))
+        .route("/oca-bundles/:said/steps", get(oca_bundle_steps))
+        .route("/oca-bundles/:said/relations", get(oca_bundle_relations))
+        .route("/capture-bases", get(all_capture_bases))
+        .route("/objects", get(oca_objects))
+        .with_state(Arc::new(Mutex::new(facade)))
+}
+
+/// Serves [`router`] on the listener until the server fails.
+pub async fn serve(listener: TcpListener, facade: Facade) -> std::io::Result<()> {
+    axum::serve(listener, router(facade)).await
+}
+
+/// Runs `f` with the locked facade on the blocking thread pool, keeping
+/// async workers free while the facade does I/O.
+async fn blocking<T, F>(facade: SharedFacade, f: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Facade) -> Result<T, Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut facade = facade
+            .lock()
+            .map_err(oca_rs::data_storage::StorageError::from)?;
+        f(&mut facade)
+    })
+    .await
+    .map_err(|e| Error::Internal(e.to_string()))?
+}
+
+/// References of the repository for validation. Names bound by validated
+/// OCAfile are kept only here, so validation never rebinds stored names.
+#[cfg(feature = "local-references")]
+struct ValidationReferences(HashMap<String, String>);
+
+#[cfg(feature = "local-references")]
+impl References for ValidationReferences {
+    fn find(&self, refn: &str) -> Result<Option<String>, StorageError> {
+        Ok(self.0.get(refn).cloned())
+    }
+
+    fn save(&mut self, refn: &str, value: String) -> Result<(), StorageError> {
+        self.0.insert(refn.to_string(), value);
+        Ok(())
+    }
+}
+
+fn parse_said(said: &str) -> Result<SelfAddressingIdentifier, Error> {
+    said.parse()
+        .map_err(|_| Error::InvalidSaid(said.to_string()))
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+fn default_page() -> usize {
+    1
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_page")]
+    page: usize,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    lang: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default = "default_page")]
+    page: usize,
+}
+
+#[derive(Deserialize)]
+struct BundleQuery {
+    #[serde(default)]
+    dependencies: bool,
+}
+
+#[derive(Deserialize)]
+struct OCAfileQuery {
+    #[serde(default)]
+    dereference: bool,
+}
+
+#[derive(Deserialize)]
+struct ObjectsQuery {
+    saids: String,
+}
+
+#[derive(Serialize)]
+struct Validated {
+    said: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Step<C, B> {
+    parent_said: Option<String>,
+    command: C,
+    result: B,
+}
+
+#[derive(Serialize)]
+struct Relations<O> {
+    base_object: O,
+    relations: Vec<O>,
+}
+
+async fn build_oca_bundle(
+    State(facade): State<SharedFacade>,
+    ocafile: String,
+) -> Result<Json<impl Serialize>, Error> {
+    let bundle = blocking(
+        facade,
+        move |facade| Ok(facade.build_from_ocafile(ocafile)?),
+    )
+    .await?;
+    Ok(Json(bundle))
+}
+
+async fn validate_ocafile(
+    State(facade): State<SharedFacade>,
+    ocafile: String,
+) -> Result<Json<Validated>, Error> {
+    let oca_build = blocking(facade, move |facade| {
+        #[cfg(feature = "local-references")]
+        let oca_build = {
+            let mut references = ValidationReferences(facade.fetch_all_refs()?);
+            facade.validate_ocafile_with_external_references(ocafile, &mut references)
+        };
+        #[cfg(not(feature = "local-references"))]
+        let oca_build = facade.validate_ocafile(ocafile);
+        oca_build.map_err(Error::Validation)
+    })
+    .await?;
+    Ok(Json(Validated {
+        said: oca_build.oca_bundle.said.map(|said| said.to_string()),
+    }))
+}
+
+async fn all_oca_bundles(
+    State(facade): State<SharedFacade>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<impl Serialize>, Error> {
+    let bundles = blocking(facade, move |facade| {
+        Ok(facade.fetch_all_oca_bundle(pagination.limit, pagination.page)?)
+    })
+    .await?;
+    Ok(Json(bundles))
+}
+
+async fn search_oca_bundles(
+    State(facade): State<SharedFacade>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<impl Serialize>, Error> {
+    let language = query
+        .lang
+        .map(|lang| lang.parse().map_err(|_| Error::InvalidLanguage(lang)))
+        .transpose()?;
+    let found = blocking(facade, move |facade| {
+        Ok(facade.search_oca_bundle(language, query.q, query.limit, query.page)?)
+    })
+    .await?;
+    Ok(Json(found))
+}
+
+async fn oca_bundle(
+    State(facade): State<SharedFacade>,
+    Path(said): Path<String>,
+    Query(query): Query<BundleQuery>,
+) -> Result<Json<impl Serialize>, Error> {
+    let said = parse_said(&said)?;
+    let bundle = blocking(facade, move |facade| {
+        Ok(facade.get_oca_bundle(said, query.dependencies)?)
+    })
+    .await?;
+    Ok(Json(bundle))
+}
+
+async fn oca_bundle_ocafile(
+    State(facade): State<SharedFacade>,
+    Path(said): Path<String>,
+    Query(query): Query<OCAfileQuery>,
+) -> Result<String, Error> {
+    let said = parse_said(&said)?;
+    blocking(facade, move |facade| {
+        Ok(facade.get_oca_bundle_ocafile(said, query.dereference)?)
+    })
+    .await
+}
+
+async fn oca_bundle_steps(
+    State(facade): State<SharedFacade>,
+    Path(said): Path<String>,
+) -> Result<Json<impl Serialize>, Error> {
+    let said = parse_said(&said)?;
+    let steps = blocking(facade, move |facade| Ok(facade.get_oca_bundle_steps(said)?)).await?;
+    Ok(Json(
+        steps
+            .into_iter()
+            .map(|step| Step {
+                parent_said: step.parent_said.map(|said| said.to_string()),
+                command: step.command,
+                result: step.result,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+async fn oca_bundle_relations(
+    State(facade): State<SharedFacade>,
+    Path(said): Path<String>,
+) -> Result<Json<impl Serialize>, Error> {
+    let said = parse_said(&said)?;
+    let relationship =
+        blocking(facade, move |facade| Ok(facade.explore(said.to_string())?)).await?;
+    let mut relations: Vec<_> = relationship.relations.into_iter().collect();
+    relations.sort_by(|a, b| a.said.cmp(&b.said));
+    Ok(Json(Relations {
+        base_object: relationship.base_object,
+        relations,
+    }))
+}
+
+async fn all_capture_bases(
+    State(facade): State<SharedFacade>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<impl Serialize>, Error> {
+    let capture_bases = blocking(facade, move |facade| {
+        Ok(facade.fetch_all_capture_base(pagination.limit, pagination.page)?)
+    })
+    .await?;
+    Ok(Json(capture_bases))
+}
+
+async fn oca_objects(
+    State(facade): State<SharedFacade>,
+    Query(query): Query<ObjectsQuery>,
+) -> Result<Json<impl Serialize>, Error> {
+    let saids = query
+        .saids
+        .split(',')
+        .filter(|said| !said.is_empty())
+        .map(|said| parse_said(said).map(|said| said.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let objects = blocking(facade, move |facade| Ok(facade.get_oca_objects(saids)?)).await?;
+    Ok(Json(objects))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode};
+    use oca_rs::data_storage::{DataStorage, InMemoryDataStorage};
+    use oca_rs::repositories::SQLiteConfig;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        router(Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap())
+    }
+
+    async fn call(app: &Router, method: Method, uri: &str, body: &str) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, body.to_vec())
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, Value) {
+        let (status, body) = call(app, Method::GET, uri, "").await;
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn serve_oca_bundles() {
+        let app = app();
+        let ocafile = "ADD ATTRIBUTE name=Text\nADD META en PROPS name=\"Person\"\n";
+        let (status, body) = call(&app, Method::POST, "/oca-bundles/validate", ocafile).await;
+        assert_eq!(status, StatusCode::OK);
+        let validated: Value = serde_json::from_slice(&body).unwrap();
+
+        let (status, body) = call(&app, Method::POST, "/oca-bundles", ocafile).await;
+        assert_eq!(status, StatusCode::OK);
+        let bundle: Value = serde_json::from_slice(&body).unwrap();
+        let said = bundle["d"].as_str().unwrap().to_string();
+        assert_eq!(validated["said"], said);
+        let capture_base = bundle["capture_base"]["d"].as_str().unwrap().to_string();
+
+        let (status, fetched) = get(&app, &format!("/oca-bundles/{said}?dependencies=true")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(fetched["bundle"], bundle);
+        assert_eq!(fetched["dependencies"], Value::Array(vec![]));
+
+        let (status, ocafile) = call(
+            &app,
+            Method::GET,
+            &format!("/oca-bundles/{said}/ocafile"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8(ocafile)
+            .unwrap()
+            .contains("ADD ATTRIBUTE name=Text"));
+
+        let (_, steps) = get(&app, &format!("/oca-bundles/{said}/steps")).await;
+        assert_eq!(steps.as_array().unwrap().len(), 2);
+        assert_eq!(steps[1]["result"], bundle);
+        assert_eq!(steps[1]["parent_said"], steps[0]["result"]["d"]);
+
+        let (_, relations) = get(&app, &format!("/oca-bundles/{said}/relations")).await;
+        assert_eq!(relations["base_object"]["said"], said);
+        assert!(relations["relations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|object| object["said"] == capture_base));
+
+        let (_, all) = get(&app, "/oca-bundles?limit=10&page=1").await;
+        assert_eq!(all["metadata"]["total"], 1);
+        assert_eq!(all["records"][0], bundle);
+        let (_, all) = get(&app, "/capture-bases").await;
+        assert_eq!(all["records"][0]["d"], capture_base);
+
+        let (_, found) = get(&app, "/oca-bundles/search?q=Person&lang=en").await;
+        assert_eq!(found["r"][0]["oca_bundle"], bundle);
+
+        let (status, objects) = get(&app, &format!("/objects?saids={capture_base}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(objects[0]["d"], capture_base);
+    }
+
+    #[cfg(feature = "local-references")]
+    #[tokio::test]
+    async fn validate_without_binding_references() {
+        let app = app();
+        let (status, body) = call(
+            &app,
+            Method::POST,
+            "/oca-bundles",
+            "-- name=person\nADD ATTRIBUTE name=Text\n",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let person: Value = serde_json::from_slice(&body).unwrap();
+
+        for ocafile in [
+            "-- name=person\nADD ATTRIBUTE surname=Text\n",
+            "ADD ATTRIBUTE owner=refn:person\n",
+        ] {
+            let (status, _) = call(&app, Method::POST, "/oca-bundles/validate", ocafile).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let (_, body) = call(
+            &app,
+            Method::POST,
+            "/oca-bundles",
+            "ADD ATTRIBUTE owner=refn:person\n",
+        )
+        .await;
+        let owned: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            owned["capture_base"]["attributes"]["owner"],
+            format!("refs:{}", person["d"].as_str().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn serve_errors() {
+        let app = app();
+        let (status, body) = call(
+            &app,
+            Method::POST,
+            "/oca-bundles",
+            "ADD ATTRIBUTE name=Text\nADD UNKNOWN\n",
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["code"], "validation");
+        assert!(!error["errors"].as_array().unwrap().is_empty());
+
+        let missing = "EBQMQm_tXSC8tnNICl7paGUeGg0SyF1tceHhTUutn1PN";
+        let (status, error) = get(&app, &format!("/oca-bundles/{missing}")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["code"], "not_found");
+
+        let (status, error) = get(&app, "/oca-bundles/not-a-said/steps").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_said");
+
+        let (status, error) = get(&app, "/oca-bundles?page=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_page");
+
+        let (status, error) = get(&app, "/oca-bundles/search?q=name&lang=xx").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_language");
+    }
+}
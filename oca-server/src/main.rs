@@ -0,0 +1,41 @@
+use clap::Parser;
+use oca_rs::data_storage::{DataStorage, SledDataStorage, SledDataStorageConfig};
+use oca_rs::repositories::SQLiteConfig;
+use oca_rs::Facade;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Serves OCA repository over HTTP
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8000")]
+    address: SocketAddr,
+    /// Directory of the repository storage and search index
+    #[arg(long, default_value = "oca-repository")]
+    data_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let storage = |name: &str| -> Result<SledDataStorage, String> {
+        let config = SledDataStorageConfig::build()
+            .path(args.data_dir.join(name))
+            .finalize()?;
+        Ok(SledDataStorage::new().config(config))
+    };
+    let facade = Facade::new(
+        Box::new(storage("db")?),
+        Box::new(storage("db_cache")?),
+        SQLiteConfig::build()
+            .path(args.data_dir.clone())
+            .finalize()?,
+    )?;
+
+    let listener = tokio::net::TcpListener::bind(args.address).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    oca_server::serve(listener, facade).await?;
+    Ok(())
+}
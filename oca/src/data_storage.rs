@@ -1,6 +1,12 @@
 use dyn_clonable::*;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Namespace {
     OCA,
     OCABundlesJSON,
@@ -8,6 +14,7 @@ pub enum Namespace {
     CoreModel,
     OCARelations,
     OCAReferences,
+    OCATransformationsJSON,
 }
 
 impl Namespace {
@@ -19,15 +26,185 @@ impl Namespace {
             Self::CoreModel => "core_model",
             Self::OCARelations => "oca_relations",
             Self::OCAReferences => "oca_refs",
+            Self::OCATransformationsJSON => "oca_transformations_json",
         }
     }
 }
 
+/// Failure of a [`DataStorage`] backend or of the search index database.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    #[error("Data storage is not configured")]
+    NotConfigured,
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Failed to encode record: {0}")]
+    Encoding(String),
+    #[error("Corrupt record: {0}")]
+    Corrupt(String),
+}
+
+impl StorageError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotConfigured => "not_configured",
+            Self::Io(_) => "io",
+            Self::Database(_) => "database",
+            Self::Encoding(_) => "encoding",
+            Self::Corrupt(_) => "corrupt",
+        }
+    }
+}
+
+impl serde::Serialize for StorageError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::error::serialize(self.code(), self, serializer)
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<sled::Error> for StorageError {
+    fn from(e: sled::Error) -> Self {
+        match e {
+            sled::Error::Io(e) => e.into(),
+            sled::Error::Corruption { .. } => Self::Corrupt(e.to_string()),
+            e => Self::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<sled::transaction::TransactionError<()>> for StorageError {
+    fn from(e: sled::transaction::TransactionError<()>) -> Self {
+        match e {
+            sled::transaction::TransactionError::Storage(e) => e.into(),
+            sled::transaction::TransactionError::Abort(()) => {
+                Self::Database("Transaction aborted".to_string())
+            }
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for StorageError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOperation {
+    Insert {
+        namespace: Namespace,
+        key: String,
+        value: Vec<u8>,
+    },
+    Delete {
+        namespace: Namespace,
+        key: String,
+    },
+}
+
+/// Changes written together by [`DataStorage::write_batch`], in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Batch {
+    operations: Vec<BatchOperation>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, namespace: Namespace, key: &str, value: &[u8]) {
+        self.operations.push(BatchOperation::Insert {
+            namespace,
+            key: key.to_string(),
+            value: value.to_vec(),
+        });
+    }
+
+    pub fn delete(&mut self, namespace: Namespace, key: &str) {
+        self.operations.push(BatchOperation::Delete {
+            namespace,
+            key: key.to_string(),
+        });
+    }
+
+    pub fn operations(&self) -> &[BatchOperation] {
+        &self.operations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Namespaces touched by the batch, in order of first use.
+    fn namespaces(&self) -> Vec<Namespace> {
+        let mut namespaces = vec![];
+        for operation in &self.operations {
+            let namespace = match operation {
+                BatchOperation::Insert { namespace, .. }
+                | BatchOperation::Delete { namespace, .. } => *namespace,
+            };
+            if !namespaces.contains(&namespace) {
+                namespaces.push(namespace);
+            }
+        }
+        namespaces
+    }
+}
+
+/// Sorted keys starting with `prefix`, page of `limit` keys after skipping
+/// `offset` ones.
+fn page_of_keys(
+    keys: impl Iterator<Item = String>,
+    prefix: &str,
+    offset: usize,
+    limit: Option<usize>,
+) -> Vec<String> {
+    let mut keys = keys
+        .filter(|key| key.starts_with(prefix))
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
 #[clonable]
 pub trait DataStorage: Clone + Send {
-    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String>;
-    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, String>;
-    fn insert(&mut self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String>;
+    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, StorageError>;
+    fn insert(&mut self, namespace: Namespace, key: &str, value: &[u8])
+        -> Result<(), StorageError>;
+    /// Removes the key. Removing missing key is not an error.
+    fn delete(&mut self, namespace: Namespace, key: &str) -> Result<(), StorageError>;
+    /// Keys starting with `prefix` in lexicographical order, `limit` of them
+    /// after skipping `offset` ones.
+    fn list_keys(
+        &self,
+        namespace: Namespace,
+        prefix: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, StorageError>;
+    /// Writes all changes of the batch, or none of them when it fails.
+    fn write_batch(&mut self, batch: Batch) -> Result<(), StorageError>;
     fn new() -> Self
     where
         Self: Sized;
@@ -104,42 +281,122 @@ impl DataStorage for SledDataStorage {
         self.clone()
     }
 
-    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String> {
+    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
         if let Some(ref db) = self.db {
-            let tree = db.open_tree(namespace.as_str().as_bytes()).unwrap();
-            match tree.get(key.as_bytes()).unwrap() {
+            let tree = db.open_tree(namespace.as_str().as_bytes())?;
+            match tree.get(key.as_bytes())? {
                 Some(value) => Ok(Some(value.to_vec())),
                 None => Ok(None),
             }
         } else {
-            Err("Data Storage must be opened first".to_string())
+            Err(StorageError::NotConfigured)
         }
     }
 
-    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, String> {
+    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, StorageError> {
         if let Some(ref db) = self.db {
             let mut all = HashMap::new();
-            let tree = db.open_tree(namespace.as_str().as_bytes()).unwrap();
-            let mut iter = tree.iter();
-            while let Some(Ok((key, value))) = iter.next() {
-                all.insert(String::from_utf8(key.to_vec()).unwrap(), value.to_vec());
+            let tree = db.open_tree(namespace.as_str().as_bytes())?;
+            for entry in tree.iter() {
+                let (key, value) = entry?;
+                let key = String::from_utf8(key.to_vec())
+                    .map_err(|e| StorageError::Corrupt(e.to_string()))?;
+                all.insert(key, value.to_vec());
             }
 
             Ok(all)
         } else {
-            Err("Data Storage must be opened first".to_string())
+            Err(StorageError::NotConfigured)
         }
     }
 
-    fn insert(&mut self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String> {
+    fn insert(
+        &mut self,
+        namespace: Namespace,
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), StorageError> {
         if let Some(ref db) = self.db {
-            let tree = db.open_tree(namespace.as_str().as_bytes()).unwrap();
-            match tree.insert(key.as_bytes(), value) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(e.to_string()),
-            }
+            let tree = db.open_tree(namespace.as_str().as_bytes())?;
+            tree.insert(key.as_bytes(), value)?;
+            Ok(())
         } else {
-            Err("Data Storage must be opened first".to_string())
+            Err(StorageError::NotConfigured)
+        }
+    }
+
+    fn delete(&mut self, namespace: Namespace, key: &str) -> Result<(), StorageError> {
+        if let Some(ref db) = self.db {
+            let tree = db.open_tree(namespace.as_str().as_bytes())?;
+            tree.remove(key.as_bytes())?;
+            Ok(())
+        } else {
+            Err(StorageError::NotConfigured)
+        }
+    }
+
+    fn list_keys(
+        &self,
+        namespace: Namespace,
+        prefix: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, StorageError> {
+        if let Some(ref db) = self.db {
+            let tree = db.open_tree(namespace.as_str().as_bytes())?;
+            // Keys of the tree are ordered, so no need to collect all of them
+            tree.scan_prefix(prefix.as_bytes())
+                .keys()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|key| {
+                    key.map(|key| String::from_utf8_lossy(&key).to_string())
+                        .map_err(StorageError::from)
+                })
+                .collect()
+        } else {
+            Err(StorageError::NotConfigured)
+        }
+    }
+
+    fn write_batch(&mut self, batch: Batch) -> Result<(), StorageError> {
+        use sled::transaction::{ConflictableTransactionError, Transactional};
+
+        if let Some(ref db) = self.db {
+            let namespaces = batch.namespaces();
+            let trees = namespaces
+                .iter()
+                .map(|namespace| db.open_tree(namespace.as_str().as_bytes()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let tree_of = |namespace: &Namespace| {
+                namespaces
+                    .iter()
+                    .position(|other| other == namespace)
+                    .unwrap()
+            };
+            trees
+                .as_slice()
+                .transaction(|trees| {
+                    for operation in batch.operations() {
+                        match operation {
+                            BatchOperation::Insert {
+                                namespace,
+                                key,
+                                value,
+                            } => {
+                                trees[tree_of(namespace)]
+                                    .insert(key.as_bytes(), value.as_slice())?;
+                            }
+                            BatchOperation::Delete { namespace, key } => {
+                                trees[tree_of(namespace)].remove(key.as_bytes())?;
+                            }
+                        }
+                    }
+                    Ok::<_, ConflictableTransactionError<()>>(())
+                })
+                .map_err(StorageError::from)
+        } else {
+            Err(StorageError::NotConfigured)
         }
     }
 }
@@ -158,7 +415,7 @@ impl DataStorage for InMemoryDataStorage {
         self.clone()
     }
 
-    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String> {
+    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
         let namespace_storage = match self.db.get(namespace.as_str()) {
             Some(namespace_storage) => namespace_storage,
             None => return Ok(None),
@@ -169,14 +426,19 @@ impl DataStorage for InMemoryDataStorage {
         }
     }
 
-    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, String> {
+    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, StorageError> {
         match self.db.get(namespace.as_str()) {
             Some(namespace_storage) => Ok(namespace_storage.clone()),
             None => Ok(HashMap::new()),
         }
     }
 
-    fn insert(&mut self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String> {
+    fn insert(
+        &mut self,
+        namespace: Namespace,
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), StorageError> {
         let mut namespace_storage = match self.db.get(namespace.as_str()) {
             Some(namespace_storage) => namespace_storage.clone(),
             None => HashMap::new(),
@@ -187,6 +449,42 @@ impl DataStorage for InMemoryDataStorage {
 
         Ok(())
     }
+
+    fn delete(&mut self, namespace: Namespace, key: &str) -> Result<(), StorageError> {
+        if let Some(namespace_storage) = self.db.get_mut(namespace.as_str()) {
+            namespace_storage.remove(key);
+        }
+        Ok(())
+    }
+
+    fn list_keys(
+        &self,
+        namespace: Namespace,
+        prefix: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, StorageError> {
+        let keys = self
+            .db
+            .get(namespace.as_str())
+            .into_iter()
+            .flat_map(|namespace_storage| namespace_storage.keys().cloned());
+        Ok(page_of_keys(keys, prefix, offset, limit))
+    }
+
+    fn write_batch(&mut self, batch: Batch) -> Result<(), StorageError> {
+        for operation in batch.operations {
+            match operation {
+                BatchOperation::Insert {
+                    namespace,
+                    key,
+                    value,
+                } => self.insert(namespace, &key, &value)?,
+                BatchOperation::Delete { namespace, key } => self.delete(namespace, &key)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -251,37 +549,507 @@ impl DataStorage for FileSystemStorage {
         self.clone()
     }
 
-    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String> {
+    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
         if let Some(ref dir) = self.dir {
             let mut path = dir.clone();
             path.push(namespace.as_str());
-            if path.try_exists().unwrap() {
+            if path.try_exists()? {
                 path.push(key);
-                Ok(std::fs::read(path.clone()).ok())
+                match std::fs::read(path.clone()) {
+                    Ok(value) => Ok(Some(value)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
             } else {
                 Ok(None)
             }
         } else {
-            Err("File path is required".to_string())
+            Err(StorageError::NotConfigured)
         }
     }
 
-    fn get_all(&self, _namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, String> {
-        Err("Not implemented".to_string())
+    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, StorageError> {
+        let mut all = HashMap::new();
+        for key in self.list_keys(namespace, "", 0, None)? {
+            if let Some(value) = self.get(namespace, &key)? {
+                all.insert(key, value);
+            }
+        }
+        Ok(all)
     }
 
-    fn insert(&mut self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String> {
+    fn insert(
+        &mut self,
+        namespace: Namespace,
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), StorageError> {
         if let Some(ref dir) = self.dir {
             let mut path = dir.clone();
             path.push(namespace.as_str());
-            if !path.try_exists().unwrap() {
-                std::fs::create_dir_all(path.clone()).unwrap();
+            if !path.try_exists()? {
+                std::fs::create_dir_all(path.clone())?;
             }
 
             path.push(key);
-            std::fs::write(path.clone(), value).map_err(|e| e.to_string())
+            Ok(std::fs::write(path.clone(), value)?)
         } else {
-            Err("File path is required".to_string())
+            Err(StorageError::NotConfigured)
+        }
+    }
+
+    fn delete(&mut self, namespace: Namespace, key: &str) -> Result<(), StorageError> {
+        if let Some(ref dir) = self.dir {
+            let path = dir.join(namespace.as_str()).join(key);
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        } else {
+            Err(StorageError::NotConfigured)
+        }
+    }
+
+    fn list_keys(
+        &self,
+        namespace: Namespace,
+        prefix: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, StorageError> {
+        if let Some(ref dir) = self.dir {
+            let path = dir.join(namespace.as_str());
+            if !path.try_exists()? {
+                return Ok(vec![]);
+            }
+            let mut keys = vec![];
+            for entry in std::fs::read_dir(path)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    keys.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+            Ok(page_of_keys(keys.into_iter(), prefix, offset, limit))
+        } else {
+            Err(StorageError::NotConfigured)
+        }
+    }
+
+    /// Values are first written to a staging directory, and only when all
+    /// of them are written they are moved into place. Files replaced or
+    /// deleted meanwhile are moved to the staging directory too, and moved
+    /// back when the batch fails, so a failed batch leaves the storage
+    /// untouched. Only a failure of that rollback itself, or a crash while
+    /// moving files, can leave the batch partly applied along with its
+    /// `.batch-*` directory.
+    fn write_batch(&mut self, batch: Batch) -> Result<(), StorageError> {
+        let Some(dir) = self.dir.clone() else {
+            return Err(StorageError::NotConfigured);
+        };
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let staging = dir.join(format!(".batch-{}-{}", std::process::id(), nanos));
+        let stage = || -> std::io::Result<()> {
+            std::fs::create_dir_all(&staging)?;
+            for (i, operation) in batch.operations().iter().enumerate() {
+                if let BatchOperation::Insert {
+                    namespace, value, ..
+                } = operation
+                {
+                    std::fs::create_dir_all(dir.join(namespace.as_str()))?;
+                    std::fs::write(staging.join(i.to_string()), value)?;
+                }
+            }
+            Ok(())
+        };
+        if let Err(e) = stage() {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e.into());
         }
+
+        // Operations applied so far, with backups of the files they replaced
+        let mut applied: Vec<(PathBuf, Option<PathBuf>)> = vec![];
+        let mut apply = || -> std::io::Result<()> {
+            for (i, operation) in batch.operations().iter().enumerate() {
+                let (namespace, key) = match operation {
+                    BatchOperation::Insert { namespace, key, .. } => (namespace, key),
+                    BatchOperation::Delete { namespace, key } => (namespace, key),
+                };
+                let target = dir.join(namespace.as_str()).join(key);
+                let backup = staging.join(format!("{i}.backup"));
+                let backup = match std::fs::rename(&target, &backup) {
+                    Ok(()) => Some(backup),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(e),
+                };
+                applied.push((target.clone(), backup));
+                if let BatchOperation::Insert { .. } = operation {
+                    std::fs::rename(staging.join(i.to_string()), &target)?;
+                }
+            }
+            Ok(())
+        };
+        if let Err(e) = apply() {
+            for (target, backup) in applied.into_iter().rev() {
+                let _ = std::fs::remove_file(&target);
+                if let Some(backup) = backup {
+                    std::fs::rename(backup, &target)?;
+                }
+            }
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e.into());
+        }
+        Ok(std::fs::remove_dir_all(&staging)?)
+    }
+}
+
+/// Storage keeping each namespace in its own table of SQLite database. The
+/// database file can be shared with the search index, see
+/// [`SQLiteConfig::database_path`](crate::repositories::SQLiteConfig::database_path).
+#[derive(Clone)]
+pub struct SqliteDataStorage {
+    connection: Option<Arc<Mutex<rusqlite::Connection>>>,
+}
+
+#[derive(Clone)]
+pub struct SqliteDataStorageConfig {
+    pub path: String,
+}
+
+impl SqliteDataStorageConfig {
+    pub fn build() -> SqliteDataStorageConfigBuilder {
+        SqliteDataStorageConfigBuilder { path: None }
+    }
+}
+
+pub struct SqliteDataStorageConfigBuilder {
+    path: Option<PathBuf>,
+}
+
+impl SqliteDataStorageConfigBuilder {
+    /// Path of database file, `:memory:` for in-memory database.
+    pub fn path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn finalize(&self) -> Result<HashMap<String, String>, String> {
+        let mut config = HashMap::new();
+
+        match &self.path {
+            Some(path) => config.insert(
+                "path".to_string(),
+                path.clone()
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|e| e.into_string().unwrap())?,
+            ),
+            None => return Err("path is required".to_string()),
+        };
+
+        Ok(config)
+    }
+
+    pub fn unwrap(&self) -> HashMap<String, String> {
+        self.finalize().unwrap()
+    }
+}
+
+impl SqliteDataStorage {
+    fn with_table<T>(
+        &self,
+        namespace: Namespace,
+        f: impl FnOnce(&rusqlite::Connection, &str) -> rusqlite::Result<T>,
+    ) -> Result<T, StorageError> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or(StorageError::NotConfigured)?;
+        let connection = connection.lock()?;
+        let table = namespace.as_str();
+        connection
+            .execute(&create_table_query(namespace), ())
+            .and_then(|_| f(&connection, table))
+            .map_err(StorageError::from)
+    }
+}
+
+impl DataStorage for SqliteDataStorage {
+    fn new() -> Self {
+        Self { connection: None }
+    }
+
+    fn config(&self, config: HashMap<String, String>) -> Self {
+        if let Some(path) = config.get("path") {
+            if let Ok(connection) = rusqlite::Connection::open(path) {
+                // Database file may be used by the search index at the same time
+                let _ = connection.busy_timeout(Duration::from_secs(5));
+                return Self {
+                    connection: Some(Arc::new(Mutex::new(connection))),
+                };
+            }
+        }
+        self.clone()
+    }
+
+    fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        self.with_table(namespace, |connection, table| {
+            let mut statement =
+                connection.prepare_cached(&format!("SELECT value FROM {table} WHERE key = ?1"))?;
+            let mut rows = statement.query([key])?;
+            match rows.next()? {
+                Some(row) => Ok(Some(row.get(0)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn get_all(&self, namespace: Namespace) -> Result<HashMap<String, Vec<u8>>, StorageError> {
+        self.with_table(namespace, |connection, table| {
+            let mut statement =
+                connection.prepare_cached(&format!("SELECT key, value FROM {table}"))?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        })
+    }
+
+    fn insert(
+        &mut self,
+        namespace: Namespace,
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.with_table(namespace, |connection, table| {
+            connection.execute(
+                &format!("INSERT OR REPLACE INTO {table}(key, value) VALUES (?1, ?2)"),
+                rusqlite::params![key, value],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn delete(&mut self, namespace: Namespace, key: &str) -> Result<(), StorageError> {
+        self.with_table(namespace, |connection, table| {
+            connection.execute(&format!("DELETE FROM {table} WHERE key = ?1"), [key])?;
+            Ok(())
+        })
+    }
+
+    fn list_keys(
+        &self,
+        namespace: Namespace,
+        prefix: &str,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, StorageError> {
+        self.with_table(namespace, |connection, table| {
+            let mut statement = connection.prepare_cached(&format!(
+                "SELECT key FROM {table} WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key LIMIT ?2 OFFSET ?3"
+            ))?;
+            // Negative limit means no limit
+            let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+            let rows = statement.query_map(
+                rusqlite::params![prefix, limit, offset as i64],
+                |row| row.get(0),
+            )?;
+            rows.collect()
+        })
+    }
+
+    fn write_batch(&mut self, batch: Batch) -> Result<(), StorageError> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or(StorageError::NotConfigured)?;
+        let mut connection = connection.lock()?;
+        let write = |connection: &mut rusqlite::Connection| -> rusqlite::Result<()> {
+            let transaction = connection.transaction()?;
+            for namespace in batch.namespaces() {
+                transaction.execute(&create_table_query(namespace), ())?;
+            }
+            for operation in batch.operations() {
+                match operation {
+                    BatchOperation::Insert {
+                        namespace,
+                        key,
+                        value,
+                    } => transaction.execute(
+                        &format!(
+                            "INSERT OR REPLACE INTO {}(key, value) VALUES (?1, ?2)",
+                            namespace.as_str()
+                        ),
+                        rusqlite::params![key, value],
+                    )?,
+                    BatchOperation::Delete { namespace, key } => transaction.execute(
+                        &format!("DELETE FROM {} WHERE key = ?1", namespace.as_str()),
+                        [key],
+                    )?,
+                };
+            }
+            transaction.commit()
+        };
+        Ok(write(&mut connection)?)
+    }
+}
+
+fn create_table_query(namespace: Namespace) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {}(key TEXT PRIMARY KEY, value BLOB NOT NULL)",
+        namespace.as_str()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::facade::Facade;
+    use crate::repositories::SQLiteConfig;
+
+    fn check_keys_and_batches(db: &mut dyn DataStorage) {
+        for key in ["b.2", "a.1", "b.1", "b.3"] {
+            db.insert(Namespace::OCA, key, key.as_bytes()).unwrap();
+        }
+        db.insert(Namespace::OCAReferences, "b.4", b"other")
+            .unwrap();
+        assert_eq!(
+            db.list_keys(Namespace::OCA, "b.", 0, None),
+            Ok(vec![
+                "b.1".to_string(),
+                "b.2".to_string(),
+                "b.3".to_string()
+            ])
+        );
+        assert_eq!(
+            db.list_keys(Namespace::OCA, "", 1, Some(2)),
+            Ok(vec!["b.1".to_string(), "b.2".to_string()])
+        );
+        assert_eq!(db.list_keys(Namespace::CoreModel, "", 0, None), Ok(vec![]));
+
+        db.delete(Namespace::OCA, "b.2").unwrap();
+        db.delete(Namespace::OCA, "missing").unwrap();
+        assert_eq!(db.get(Namespace::OCA, "b.2"), Ok(None));
+
+        let mut batch = Batch::new();
+        batch.insert(Namespace::OCA, "c.1", b"first");
+        batch.insert(Namespace::OCARelations, "c.1", b"relation");
+        batch.delete(Namespace::OCA, "a.1");
+        batch.insert(Namespace::OCA, "c.1", b"second");
+        db.write_batch(batch).unwrap();
+        assert_eq!(db.get(Namespace::OCA, "c.1"), Ok(Some(b"second".to_vec())));
+        assert_eq!(
+            db.get(Namespace::OCARelations, "c.1"),
+            Ok(Some(b"relation".to_vec()))
+        );
+        assert_eq!(
+            db.list_keys(Namespace::OCA, "", 0, None),
+            Ok(vec![
+                "b.1".to_string(),
+                "b.3".to_string(),
+                "c.1".to_string()
+            ])
+        );
+        assert_eq!(db.get_all(Namespace::OCA).unwrap().len(), 3);
+    }
+
+    /// Failure while moving staged files into place rolls back the files
+    /// moved before.
+    fn check_failed_batch(dir: &std::path::Path) {
+        let mut db = FileSystemStorage::new()
+            .config(FileSystemStorageConfig::build().path(dir.into()).unwrap());
+        let mut batch = Batch::new();
+        batch.insert(Namespace::OCA, "c.1", b"third");
+        batch.insert(Namespace::OCA, "d.1", b"new");
+        batch.delete(Namespace::OCA, "b.1");
+        batch.insert(Namespace::OCA, "missing/e.1", b"unreachable");
+        assert!(db.write_batch(batch).is_err());
+        assert_eq!(db.get(Namespace::OCA, "c.1"), Ok(Some(b"second".to_vec())));
+        assert_eq!(db.get(Namespace::OCA, "d.1"), Ok(None));
+        assert!(db.get(Namespace::OCA, "b.1").unwrap().is_some());
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn data_storage_keys_and_batches() {
+        let dir = std::env::temp_dir().join(format!("oca-storage-keys-{}", std::process::id()));
+
+        check_keys_and_batches(&mut InMemoryDataStorage::new());
+        check_keys_and_batches(
+            &mut SledDataStorage::new().config(
+                SledDataStorageConfig::build()
+                    .path(dir.join("sled"))
+                    .unwrap(),
+            ),
+        );
+        check_keys_and_batches(
+            &mut FileSystemStorage::new().config(
+                FileSystemStorageConfig::build()
+                    .path(dir.join("fs"))
+                    .unwrap(),
+            ),
+        );
+        // Staging directory is removed once the batch is written
+        assert_eq!(std::fs::read_dir(dir.join("fs")).unwrap().count(), 3);
+        check_failed_batch(&dir.join("fs"));
+        std::fs::create_dir_all(&dir).unwrap();
+        check_keys_and_batches(
+            &mut SqliteDataStorage::new().config(
+                SqliteDataStorageConfig::build()
+                    .path(dir.join("storage.db"))
+                    .unwrap(),
+            ),
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sqlite_data_storage() {
+        let dir = std::env::temp_dir().join(format!("oca-sqlite-storage-{}", std::process::id()));
+        let cache_storage_config = SQLiteConfig::build().path(dir.clone()).unwrap();
+        let database_path = cache_storage_config.database_path().unwrap();
+        let storage_config = SqliteDataStorageConfig::build()
+            .path(PathBuf::from(&database_path))
+            .unwrap();
+        let mut db = SqliteDataStorage::new().config(storage_config.clone());
+
+        assert_eq!(db.get(Namespace::OCA, "missing"), Ok(None));
+        db.insert(Namespace::OCA, "key", b"value").unwrap();
+        db.insert(Namespace::OCA, "key", b"new value").unwrap();
+        db.insert(Namespace::OCAReferences, "key", b"other")
+            .unwrap();
+        assert_eq!(
+            db.get(Namespace::OCA, "key"),
+            Ok(Some(b"new value".to_vec()))
+        );
+        assert_eq!(
+            db.get_all(Namespace::OCAReferences),
+            Ok(HashMap::from([("key".to_string(), b"other".to_vec())]))
+        );
+        assert_eq!(
+            SqliteDataStorage::new().get(Namespace::OCA, "key"),
+            Err(StorageError::NotConfigured)
+        );
+
+        // Storage and search index share the database file
+        let db_cache = SqliteDataStorage::new().config(storage_config);
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        let oca_bundle = facade
+            .build_from_ocafile(
+                "ADD ATTRIBUTE name=Text\nADD META en PROPS name=\"Person\"\n".to_string(),
+            )
+            .unwrap();
+        let said = oca_bundle.said.unwrap();
+        assert!(facade.get_oca_bundle(said, false).is_ok());
+        let search = facade
+            .search_oca_bundle(None, "Person".to_string(), 10, 1)
+            .unwrap();
+        assert_eq!(search.metadata.total, 1);
+        let entries = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(entries, 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,14 @@
+use serde::ser::SerializeStruct;
+
+/// Errors of the facade and storage are serialized the same way, as their
+/// stable `code` and human readable `message`.
+pub(crate) fn serialize<E, S>(code: &str, error: &E, serializer: S) -> Result<S::Ok, S::Error>
+where
+    E: std::fmt::Display,
+    S: serde::Serializer,
+{
+    let mut state = serializer.serialize_struct("Error", 2)?;
+    state.serialize_field("code", code)?;
+    state.serialize_field("message", &error.to_string())?;
+    state.end()
+}
@@ -0,0 +1,622 @@
+use super::fetch::{decode, get_oca_bundle, retrive_all_references, FetchError};
+use super::Facade;
+use crate::data_storage::{Batch, Namespace, StorageError};
+use oca_ast_semantics::ast;
+use oca_bundle_semantics::build::apply_command;
+use oca_bundle_semantics::state::oca::{OCABox, OCABundle};
+use oca_dag_semantics::{record::Record, CommandModel};
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::io::{Read, Write};
+
+/// Version of the archive layout written by [`Facade::export_bundles`].
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Describes content of an archive, stored in it as `manifest.json`. Beside
+/// the manifest, the tar archive holds `bundles/{said}.json`, build steps as
+/// `history/{said}` records and core models as `core_model/{key}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    /// Exported bundles, in requested order.
+    pub bundles: Vec<String>,
+    /// Bundles referenced by the exported ones, transitively.
+    pub dependencies: Vec<String>,
+    /// Local reference names pointing at bundles in the archive.
+    pub references: BTreeMap<String, String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArchiveError {
+    #[error("Malformed archive: {0}")]
+    Malformed(String),
+    #[error("Unsupported archive version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Archive entry {entry} is tampered: {reason}")]
+    Tampered { entry: String, reason: String },
+    #[error("Reference {name} already points at {existing}, not at {imported}")]
+    ConflictingReference {
+        name: String,
+        existing: String,
+        imported: String,
+    },
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl ArchiveError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Malformed(_) => "malformed_archive",
+            Self::UnsupportedVersion(_) => "unsupported_version",
+            Self::Tampered { .. } => "tampered",
+            Self::ConflictingReference { .. } => "conflicting_reference",
+            Self::Fetch(e) => e.code(),
+            Self::Storage(e) => e.code(),
+        }
+    }
+
+    fn tampered(entry: impl ToString, reason: impl ToString) -> Self {
+        Self::Tampered {
+            entry: entry.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Serialize for ArchiveError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::error::serialize(self.code(), self, serializer)
+    }
+}
+
+impl Facade {
+    /// Writes bundles into a tar archive, together with bundles they
+    /// reference and bundles they were built from, build steps, core models
+    /// and local reference names of all of them.
+    pub fn export_bundles<W: Write>(
+        &self,
+        saids: &[SelfAddressingIdentifier],
+        writer: W,
+    ) -> Result<ArchiveManifest, ArchiveError> {
+        let requested: Vec<String> = saids.iter().map(ToString::to_string).collect();
+        self.export_archive(&requested, &HashSet::new(), writer)
+    }
+
+    /// Writes archive as [`Facade::export_bundles`] does, leaving out the
+    /// `excluded` bundles, which the receiving side already stores, along
+    /// with everything they were built from. Requested bundles are always
+    /// written.
+    pub(crate) fn export_archive<W: Write>(
+        &self,
+        requested: &[String],
+        excluded: &HashSet<String>,
+        writer: W,
+    ) -> Result<ArchiveManifest, ArchiveError> {
+        let requested = requested.to_vec();
+        let mut bundles: BTreeMap<String, (OCABundle, Vec<u8>)> = BTreeMap::new();
+        let mut history: BTreeMap<String, Record> = BTreeMap::new();
+        let mut dependencies = BTreeSet::new();
+        let mut queue: VecDeque<(String, bool)> =
+            requested.iter().map(|said| (said.clone(), false)).collect();
+        while let Some((said, referenced)) = queue.pop_front() {
+            if referenced && !requested.contains(&said) {
+                dependencies.insert(said.clone());
+            }
+            if bundles.contains_key(&said)
+                || (excluded.contains(&said) && !requested.contains(&said))
+            {
+                continue;
+            }
+            let value = self
+                .db_cache
+                .get(Namespace::OCABundlesJSON, &said)?
+                .ok_or_else(|| FetchError::not_found("OCA bundle", &said))?;
+            let bundle: OCABundle = decode("OCA bundle", &said, &value)?;
+            queue.extend(
+                retrive_all_references(bundle.clone())
+                    .into_iter()
+                    .map(|said| (said.to_string(), true)),
+            );
+            if let Some(value) = self
+                .db
+                .get(Namespace::OCA, &format!("oca.{}.operation", said))?
+            {
+                let record = Record::read(&value, Some(1))
+                    .map_err(|e| StorageError::Corrupt(format!("History of {said}: {e}")))?;
+                let parent = record.text(0).unwrap_or_default();
+                if !parent.is_empty() {
+                    queue.push_back((parent, false));
+                }
+                history.insert(said.clone(), record);
+            }
+            bundles.insert(said, (bundle, value));
+        }
+
+        let mut core_models: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        let objects: HashSet<String> = bundles
+            .values()
+            .flat_map(|(bundle, _)| object_saids(bundle))
+            .collect();
+        let mut keys: VecDeque<String> = objects.iter().cloned().collect();
+        while let Some(key) = keys.pop_front() {
+            if core_models.contains_key(&key) {
+                continue;
+            }
+            let Some(value) = self
+                .db
+                .get(Namespace::CoreModel, &format!("core_model.{}", key))?
+            else {
+                continue;
+            };
+            let value = if value.starts_with(b"{") {
+                value
+            } else if objects.contains(&key) {
+                let record = Record::read(&value, None)
+                    .map_err(|e| StorageError::Corrupt(format!("Core model {key}: {e}")))?;
+                keys.extend(
+                    record
+                        .fields()
+                        .iter()
+                        .filter(|field| !field.is_empty())
+                        .map(|field| String::from_utf8_lossy(field).to_string()),
+                );
+                record.encode()
+            } else {
+                // Object of a bundle left out of the archive
+                continue;
+            };
+            core_models.insert(key, value);
+        }
+
+        let mut references = BTreeMap::new();
+        for (name, said) in self.db.get_all(Namespace::OCAReferences)? {
+            let said = String::from_utf8(said)
+                .map_err(|e| StorageError::Corrupt(format!("Reference {name}: {e}")))?;
+            if bundles.contains_key(&said) {
+                references.insert(name, said);
+            }
+        }
+
+        let manifest = ArchiveManifest {
+            version: ARCHIVE_VERSION,
+            bundles: requested,
+            dependencies: dependencies.into_iter().collect(),
+            references,
+        };
+        let mut builder = tar::Builder::new(writer);
+        let manifest_json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| StorageError::Encoding(e.to_string()))?;
+        append(&mut builder, MANIFEST, &manifest_json)?;
+        for (said, (_, value)) in &bundles {
+            append(&mut builder, &format!("bundles/{said}.json"), value)?;
+        }
+        for (said, record) in &history {
+            append(&mut builder, &format!("history/{said}"), &record.encode())?;
+        }
+        for (key, value) in &core_models {
+            append(&mut builder, &format!("core_model/{key}"), value)?;
+        }
+        builder
+            .into_inner()
+            .and_then(|mut writer| writer.flush())
+            .map_err(StorageError::from)?;
+        Ok(manifest)
+    }
+
+    /// Imports archive written by [`Facade::export_bundles`]. SAIDs of all
+    /// bundles, capture bases and overlays are recomputed, build steps are
+    /// replayed and command digests checked, so nothing is stored unless
+    /// the whole archive is intact. Bundles listed in the manifest may be
+    /// left out of the archive when they are already stored.
+    pub fn import_archive<R: Read>(&mut self, reader: R) -> Result<ArchiveManifest, ArchiveError> {
+        let mut entries: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        let mut archive = tar::Archive::new(reader);
+        let malformed = |e: std::io::Error| ArchiveError::Malformed(e.to_string());
+        for entry in archive.entries().map_err(malformed)? {
+            let mut entry = entry.map_err(malformed)?;
+            if entry.header().entry_type().is_dir() {
+                continue;
+            }
+            let path = entry
+                .path()
+                .map_err(malformed)?
+                .to_string_lossy()
+                .to_string();
+            let mut value = vec![];
+            entry.read_to_end(&mut value).map_err(malformed)?;
+            if entries.insert(path.clone(), value).is_some() {
+                return Err(ArchiveError::Malformed(format!("Duplicate entry {path}")));
+            }
+        }
+
+        let manifest: ArchiveManifest = entries
+            .remove(MANIFEST)
+            .ok_or_else(|| ArchiveError::Malformed(format!("Missing {MANIFEST}")))
+            .and_then(|value| {
+                serde_json::from_slice(&value)
+                    .map_err(|e| ArchiveError::Malformed(format!("{MANIFEST}: {e}")))
+            })?;
+        if manifest.version != ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(manifest.version));
+        }
+
+        let mut bundles: BTreeMap<String, OCABundle> = BTreeMap::new();
+        let mut history: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        let mut core_models: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for (path, value) in entries {
+            match path.split_once('/') {
+                Some(("bundles", name)) if name.ends_with(".json") => {
+                    let said = name.trim_end_matches(".json").to_string();
+                    let bundle: OCABundle = serde_json::from_slice(&value)
+                        .map_err(|e| ArchiveError::tampered(&path, e))?;
+                    verify_bundle(&said, &bundle).map_err(|e| ArchiveError::tampered(&path, e))?;
+                    bundles.insert(said, bundle);
+                }
+                Some(("history", said)) if !said.contains('/') => {
+                    history.insert(said.to_string(), value);
+                }
+                Some(("core_model", key)) if !key.contains('/') => {
+                    core_models.insert(key.to_string(), value);
+                }
+                _ => {
+                    return Err(ArchiveError::Malformed(format!("Unexpected entry {path}")));
+                }
+            }
+        }
+
+        for said in manifest
+            .bundles
+            .iter()
+            .chain(&manifest.dependencies)
+            .chain(manifest.references.values())
+        {
+            if !bundles.contains_key(said)
+                && self
+                    .db_cache
+                    .get(Namespace::OCABundlesJSON, said)?
+                    .is_none()
+            {
+                return Err(ArchiveError::tampered(
+                    MANIFEST,
+                    format!("Bundle {said} is missing"),
+                ));
+            }
+        }
+
+        let mut batch = Batch::new();
+        for (said, value) in &history {
+            let entry = format!("history/{said}");
+            let bundle = bundles
+                .get(said)
+                .ok_or_else(|| ArchiveError::tampered(&entry, "Bundle is missing"))?;
+            let record =
+                Record::read(value, Some(1)).map_err(|e| ArchiveError::tampered(&entry, e))?;
+            let parent_said = record.text(0).unwrap_or_default();
+            let command: ast::Command = serde_json::from_str(&record.text(1).unwrap_or_default())
+                .map_err(|e| ArchiveError::tampered(&entry, e))?;
+            let parent = match parent_said.as_str() {
+                "" => None,
+                parent_said => match bundles.get(parent_said) {
+                    Some(parent) => Some(parent.clone()),
+                    None => {
+                        let parent_said = parent_said
+                            .parse()
+                            .map_err(|_| ArchiveError::tampered(&entry, "Invalid parent SAID"))?;
+                        match get_oca_bundle(self.db_cache.as_ref(), parent_said, false) {
+                            Ok(parent) => Some(parent.bundle),
+                            Err(FetchError::NotFound { .. }) => {
+                                return Err(ArchiveError::tampered(&entry, "Unknown parent"))
+                            }
+                            Err(e) => return Err(e.into()),
+                        }
+                    }
+                },
+            };
+            let rebuilt = apply_command(parent.map(OCABox::from), command)
+                .map_err(|e| ArchiveError::tampered(&entry, e.join(", ")))?
+                .generate_bundle();
+            if rebuilt.said != bundle.said {
+                return Err(ArchiveError::tampered(
+                    &entry,
+                    "Build step does not result in the bundle",
+                ));
+            }
+            batch.insert(
+                Namespace::OCA,
+                &format!("oca.{}.operation", said),
+                &record.encode(),
+            );
+        }
+
+        let objects: HashSet<String> = bundles.values().flat_map(object_saids).collect();
+        for (key, value) in &core_models {
+            let entry = format!("core_model/{key}");
+            let value = if value.starts_with(b"{") {
+                let command: ast::Command =
+                    serde_json::from_slice(value).map_err(|e| ArchiveError::tampered(&entry, e))?;
+                if CommandModel::calculate_command_digest(&command).to_string() != *key {
+                    return Err(ArchiveError::tampered(&entry, "Command digest mismatch"));
+                }
+                value.clone()
+            } else {
+                if !objects.contains(key) {
+                    return Err(ArchiveError::tampered(&entry, "Unknown object"));
+                }
+                let record =
+                    Record::read(value, None).map_err(|e| ArchiveError::tampered(&entry, e))?;
+                for field in record.fields().iter().filter(|field| !field.is_empty()) {
+                    String::from_utf8_lossy(field)
+                        .parse::<SelfAddressingIdentifier>()
+                        .map_err(|_| ArchiveError::tampered(&entry, "Invalid SAID"))?;
+                }
+                record.encode()
+            };
+            batch.insert(Namespace::CoreModel, &format!("core_model.{}", key), &value);
+        }
+
+        // Names bound to other bundles are not rebound, only identical
+        // bindings may be imported again
+        for (name, said) in &manifest.references {
+            if let Some(existing) = self.db.get(Namespace::OCAReferences, name)? {
+                let existing = String::from_utf8_lossy(&existing);
+                if existing != said.as_str() {
+                    return Err(ArchiveError::ConflictingReference {
+                        name: name.clone(),
+                        existing: existing.to_string(),
+                        imported: said.clone(),
+                    });
+                }
+            }
+            batch.insert(Namespace::OCAReferences, name, said.as_bytes());
+        }
+
+        let mut cache_batch = Batch::new();
+        for bundle in bundles.values() {
+            Self::stage_bundle_objects(bundle, &mut cache_batch)?;
+        }
+        self.db_cache.write_batch(cache_batch)?;
+        self.db.write_batch(batch)?;
+
+        let indexed: BTreeSet<&String> = manifest
+            .bundles
+            .iter()
+            .chain(&manifest.dependencies)
+            .collect();
+        for said in indexed {
+            let bundle = match bundles.get(said) {
+                Some(bundle) => bundle.clone(),
+                None => {
+                    let value = self
+                        .db_cache
+                        .get(Namespace::OCABundlesJSON, said)?
+                        .ok_or_else(|| FetchError::not_found("OCA bundle", said))?;
+                    decode("OCA bundle", said, &value)?
+                }
+            };
+            self.build_cache(&bundle)?;
+            self.build_meta(&bundle)?;
+            self.add_relations(bundle)?;
+        }
+        Ok(manifest)
+    }
+}
+
+/// SAIDs of the bundle, its capture base and overlays.
+fn object_saids(bundle: &OCABundle) -> Vec<String> {
+    let mut saids: Vec<String> = bundle.said.iter().map(ToString::to_string).collect();
+    saids.extend(bundle.capture_base.said.iter().map(ToString::to_string));
+    saids.extend(
+        bundle
+            .overlays
+            .iter()
+            .filter_map(|overlay| overlay.said().as_ref().map(ToString::to_string)),
+    );
+    saids
+}
+
+/// Checks that bundle is stored under its SAID and SAIDs of all its objects
+/// match their content.
+pub(super) fn verify_bundle(said: &str, bundle: &OCABundle) -> Result<(), String> {
+    if bundle.said.as_ref().map(ToString::to_string).as_deref() != Some(said) {
+        return Err("Bundle SAID does not match entry name".to_string());
+    }
+    let mut capture_base = bundle.capture_base.clone();
+    capture_base.fill_said();
+    if capture_base.said != bundle.capture_base.said {
+        return Err("Malformed capture base SAID".to_string());
+    }
+    for overlay in &bundle.overlays {
+        let mut recalculated = overlay.clone();
+        recalculated.fill_said();
+        if recalculated.said() != overlay.said() {
+            return Err(format!("Malformed {} overlay SAID", overlay.overlay_type()));
+        }
+        if overlay.capture_base() != &bundle.capture_base.said {
+            return Err(format!(
+                "{} overlay belongs to other capture base",
+                overlay.overlay_type()
+            ));
+        }
+    }
+    let mut recalculated = bundle.clone();
+    recalculated.fill_said();
+    if recalculated.said != bundle.said {
+        return Err("Malformed bundle SAID".to_string());
+    }
+    Ok(())
+}
+
+fn append<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    value: &[u8],
+) -> Result<(), StorageError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(value.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    Ok(builder.append_data(&mut header, path, value)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_storage::{DataStorage, InMemoryDataStorage};
+    use crate::repositories::SQLiteConfig;
+
+    fn facade() -> Facade {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap()
+    }
+
+    fn entries(archive: &[u8]) -> BTreeMap<String, Vec<u8>> {
+        let mut archive = tar::Archive::new(archive);
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                let mut value = vec![];
+                entry.read_to_end(&mut value).unwrap();
+                (path, value)
+            })
+            .collect()
+    }
+
+    fn archive(entries: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, value) in entries {
+            append(&mut builder, path, value).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn facade_export_and_import_archive() -> Result<(), ArchiveError> {
+        let mut source = facade();
+        let address = source
+            .build_from_ocafile(
+                "ADD ATTRIBUTE street=Text\nADD LABEL en ATTRS street=\"Street\"\n".to_string(),
+            )
+            .unwrap()
+            .said
+            .unwrap();
+        let person = source
+            .build_from_ocafile(format!("ADD ATTRIBUTE name=Text address=refs:{address}\n"))
+            .unwrap()
+            .said
+            .unwrap();
+        let extended = source
+            .build_from_ocafile(format!(
+                "FROM {person}\nADD ATTRIBUTE age=Numeric\nADD META en PROPS name=\"Aged person\"\n"
+            ))
+            .unwrap()
+            .said
+            .unwrap();
+
+        source.db.insert(
+            Namespace::OCAReferences,
+            "person",
+            extended.to_string().as_bytes(),
+        )?;
+
+        let mut exported = vec![];
+        let manifest = source.export_bundles(std::slice::from_ref(&extended), &mut exported)?;
+        assert_eq!(manifest.bundles, vec![extended.to_string()]);
+        assert_eq!(manifest.dependencies, vec![address.to_string()]);
+        assert_eq!(
+            manifest.references,
+            BTreeMap::from([("person".to_string(), extended.to_string())])
+        );
+        let error = source
+            .export_bundles(&[SelfAddressingIdentifier::default()], vec![])
+            .unwrap_err();
+        assert_eq!(error.code(), "not_found");
+
+        let mut target = facade();
+        assert_eq!(target.import_archive(exported.as_slice())?, manifest);
+        assert_eq!(
+            target.get_oca_bundle_ocafile(extended.clone(), false)?,
+            source.get_oca_bundle_ocafile(extended.clone(), false)?
+        );
+        assert_eq!(
+            target
+                .get_oca_bundle(extended.clone(), true)?
+                .dependencies
+                .len(),
+            1
+        );
+        assert!(target.explore(address.to_string()).is_ok());
+        let found = target
+            .search_oca_bundle(None, "Aged".to_string(), 10, 1)
+            .unwrap();
+        assert_eq!(found.metadata.total, 1);
+        assert_eq!(target.import_archive(exported.as_slice())?, manifest);
+
+        let tamper = |path: &str, change: &dyn Fn(&[u8]) -> Vec<u8>| {
+            let mut entries = entries(&exported);
+            let value = entries.get_mut(path).unwrap();
+            *value = change(value);
+            facade().import_archive(archive(&entries).as_slice())
+        };
+        // Replaces bytes in place, keeping length prefixed records intact
+        let replace = |from: &'static str, to: &'static str| {
+            move |value: &[u8]| {
+                let mut value = value.to_vec();
+                let at = value
+                    .windows(from.len())
+                    .position(|window| window == from.as_bytes())
+                    .unwrap();
+                value.splice(at..at + from.len(), to.bytes());
+                value
+            }
+        };
+        let error = tamper(
+            &format!("bundles/{address}.json"),
+            &replace("Street", "Road"),
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), "tampered");
+        let error = tamper(&format!("history/{extended}"), &replace("Aged", "Aget")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Archive entry history/{extended} is tampered: Build step does not result in the bundle"
+            )
+        );
+        let error = tamper(MANIFEST, &replace("\"version\": 1", "\"version\": 2")).unwrap_err();
+        assert_eq!(error.code(), "unsupported_version");
+
+        // Existing binding of the name to other bundle is kept
+        let mut bound = facade();
+        bound.db.insert(
+            Namespace::OCAReferences,
+            "person",
+            address.to_string().as_bytes(),
+        )?;
+        let error = bound.import_archive(exported.as_slice()).unwrap_err();
+        assert_eq!(error.code(), "conflicting_reference");
+        assert_eq!(
+            bound.db.get(Namespace::OCAReferences, "person")?,
+            Some(address.to_string().into_bytes())
+        );
+        assert!(bound
+            .db_cache
+            .get(Namespace::OCABundlesJSON, &extended.to_string())?
+            .is_none());
+        let error = facade().import_archive(&b"not an archive"[..]).unwrap_err();
+        assert_eq!(error.code(), "malformed_archive");
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use super::Facade;
-use crate::data_storage::{DataStorage, Namespace};
-use crate::facade::fetch::get_oca_bundle;
+use crate::data_storage::{Batch, DataStorage, Namespace, StorageError};
+use crate::facade::fetch::{get_oca_bundle, FetchError};
 #[cfg(feature = "local-references")]
 use crate::local_references;
 #[cfg(feature = "local-references")]
@@ -14,10 +14,14 @@ use log::debug;
 use oca_ast_semantics::ast::{OCAAst, ObjectKind, RefValue, ReferenceAttrType};
 use oca_bundle_semantics::build::{OCABuild, OCABuildStep};
 use oca_bundle_semantics::state::oca::OCABundle;
+use oca_bundle_semantics::state::validator::Validator;
 use oca_bundle_semantics::Encode;
 use oca_dag_semantics::build_core_db_model;
+use oca_dag_semantics::record::Record;
 use said::derivation::HashFunctionCode;
 use said::sad::SerializationFormats;
+use said::SelfAddressingIdentifier;
+use transformation_file::state::Transformation;
 
 #[derive(thiserror::Error, Debug, serde::Serialize)]
 #[serde(untagged)]
@@ -26,6 +30,20 @@ pub enum Error {
     ValidationError(Vec<ValidationError>),
     #[error("Deprecated")]
     Deprecated,
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl Error {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ValidationError(_) => "validation",
+            Self::Deprecated => "deprecated",
+            Self::Storage(e) => e.code(),
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug, serde::Serialize)]
@@ -49,19 +67,48 @@ pub enum ValidationError {
     #[cfg(feature = "local-references")]
     #[error("Reference {0} not found")]
     UnknownRefn(String),
+    #[error("OCA bundle {0} not found")]
+    UnknownBundle(String),
+    #[error("Invalid OCA bundle: {0}")]
+    InvalidBundle(String),
+    #[error("OCA bundle {0} can not be rebuilt from its commands")]
+    IrreproducibleBundle(String),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl ValidationError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::OCAFileParse(_) => "parse",
+            Self::OCABundleBuild(_) => "build",
+            Self::TransformationBuild(_) => "transformation_build",
+            Self::InvalidCommand { .. } => "invalid_command",
+            #[cfg(feature = "local-references")]
+            Self::UnknownRefn(_) => "unknown_reference",
+            Self::UnknownBundle(_) => "unknown_bundle",
+            Self::InvalidBundle(_) => "invalid_bundle",
+            Self::IrreproducibleBundle(_) => "irreproducible_bundle",
+            Self::Storage(e) => e.code(),
+        }
+    }
 }
 
 #[cfg(feature = "local-references")]
 impl References for Box<dyn DataStorage> {
-    fn find(&self, refn: &str) -> Option<String> {
-        self.get(Namespace::OCAReferences, refn)
-            .unwrap()
-            .map(|said| String::from_utf8(said).unwrap())
+    fn find(&self, refn: &str) -> Result<Option<String>, StorageError> {
+        self.get(Namespace::OCAReferences, refn)?
+            .map(|said| {
+                String::from_utf8(said)
+                    .map_err(|e| StorageError::Corrupt(format!("Reference {refn}: {e}")))
+            })
+            .transpose()
     }
 
-    fn save(&mut self, refn: &str, value: String) {
+    fn save(&mut self, refn: &str, value: String) -> Result<(), StorageError> {
         self.insert(Namespace::OCAReferences, refn, value.to_string().as_bytes())
-            .unwrap()
     }
 }
 
@@ -88,6 +135,11 @@ pub fn parse_oca_bundle_to_ocafile(bundle: &OCABundle) -> String {
     oca_file_semantics::ocafile::generate_from_ast(&bundle.to_ast())
 }
 
+/// Parent SAID field of history and core model records, empty for none.
+fn parent_field(parent: &Option<SelfAddressingIdentifier>) -> String {
+    parent.as_ref().map(ToString::to_string).unwrap_or_default()
+}
+
 impl Facade {
     #[cfg(not(feature = "local-references"))]
     pub fn validate_ocafile(&self, ocafile: String) -> Result<OCABuild, Vec<ValidationError>> {
@@ -121,19 +173,97 @@ impl Facade {
         Self::oca_ast_to_oca_build_with_references(base, oca_ast, &mut self.db)
     }
 
+    /// Validate transformation file against its source and target bundles,
+    /// which are resolved from the facade database.
+    pub fn validate_transformation(
+        &self,
+        ocafile: String,
+    ) -> Result<Transformation, Vec<ValidationError>> {
+        let ast = oca_file_transformation::ocafile::parse_from_string(ocafile).map_err(|e| {
+            vec![ValidationError::OCAFileParse(
+                oca_file::ocafile::error::ParseError::TransformationError(e),
+            )]
+        })?;
+        let transformation = transformation_file::build::from_ast(&ast).map_err(|e| {
+            e.into_iter()
+                .map(ValidationError::TransformationBuild)
+                .collect::<Vec<_>>()
+        })?;
+
+        let resolve = |said: &Option<String>| -> Result<OCABundle, ValidationError> {
+            let said = said.clone().unwrap_or_default();
+            let Ok(parsed) = said.parse::<SelfAddressingIdentifier>() else {
+                return Err(ValidationError::UnknownBundle(said));
+            };
+            match get_oca_bundle(self.storage(), parsed, false) {
+                Ok(bundle) => Ok(bundle.bundle),
+                Err(FetchError::Storage(e)) => Err(ValidationError::Storage(e)),
+                Err(_) => Err(ValidationError::UnknownBundle(said)),
+            }
+        };
+        let (source, target) = match (
+            resolve(&transformation.source),
+            resolve(&transformation.target),
+        ) {
+            (Ok(source), Ok(target)) => (source, target),
+            (source, target) => {
+                return Err(source.err().into_iter().chain(target.err()).collect());
+            }
+        };
+
+        transformation_file::validator::validate(&ast, &source, &target).map_err(|e| {
+            e.into_iter()
+                .map(ValidationError::TransformationBuild)
+                .collect::<Vec<_>>()
+        })?;
+        Ok(transformation)
+    }
+
+    /// Validate transformation file and store the transformation together
+    /// with its OCAfile in a single batch of the primary storage.
+    pub fn build_transformation_from_ocafile(
+        &mut self,
+        ocafile: String,
+    ) -> Result<Transformation, Error> {
+        let transformation = self
+            .validate_transformation(ocafile.clone())
+            .map_err(Error::ValidationError)?;
+        let mut batch = Batch::new();
+        let said = Self::stage_transformation(&transformation, &mut batch)?;
+        batch.insert(
+            Namespace::OCA,
+            &format!("transformation.{}.ocafile", said),
+            ocafile.as_bytes(),
+        );
+        self.db.write_batch(batch)?;
+        Ok(transformation)
+    }
+
+    /// Stores all objects of the build in a single batch per storage, so a
+    /// failed build leaves no partial history behind. Content addressed
+    /// objects are written first to the cache storage, history and
+    /// relations referring to them next to the primary one. The two batches
+    /// are separate: when the second fails, objects of the first stay in
+    /// the cache, referred by no history, until a repeated build rewrites
+    /// them. Search index is updated only once both succeeded.
     pub fn build(&mut self, oca_build: &OCABuild) -> Result<OCABundle, Error> {
-        self.build_cache(&oca_build.oca_bundle);
+        let mut batch = Batch::new();
+        let mut cache_batch = Batch::new();
+
+        for step in &oca_build.steps {
+            Self::build_step(step, &mut batch, &mut cache_batch)?;
+        }
 
-        self.build_meta(&oca_build.oca_bundle);
+        self.stage_relations(&oca_build.oca_bundle, &mut batch)?;
 
-        oca_build
-            .steps
-            .iter()
-            .for_each(|step| self.build_step(step));
+        Self::build_models(oca_build, &mut batch);
 
-        let _ = self.add_relations(oca_build.oca_bundle.clone());
+        self.db_cache.write_batch(cache_batch)?;
+        self.db.write_batch(batch)?;
 
-        self.build_models(oca_build);
+        self.build_cache(&oca_build.oca_bundle)?;
+
+        self.build_meta(&oca_build.oca_bundle)?;
 
         Ok(oca_build.oca_bundle.clone())
     }
@@ -153,6 +283,30 @@ impl Facade {
         }
     }
 
+    /// Registers bundle built elsewhere, given as JSON. Its SAIDs are
+    /// verified and build steps are derived from its AST, then it is stored
+    /// the same way as a bundle built from OCAfile.
+    pub fn import_bundle(&mut self, json: &str) -> Result<OCABundle, Error> {
+        let invalid = |e: &dyn std::fmt::Display| ValidationError::InvalidBundle(e.to_string());
+        let bundle: OCABundle =
+            serde_json::from_str(json).map_err(|e| Error::ValidationError(vec![invalid(&e)]))?;
+        Validator::new().validate(&bundle).map_err(|errors| {
+            Error::ValidationError(errors.iter().map(|e| invalid(e)).collect())
+        })?;
+
+        let oca_build =
+            oca_bundle_semantics::build::from_ast(None, &bundle.to_ast()).map_err(|e| {
+                Error::ValidationError(e.into_iter().map(ValidationError::OCABundleBuild).collect())
+            })?;
+        if oca_build.oca_bundle.said != bundle.said {
+            let said = bundle.said.map(|said| said.to_string()).unwrap_or_default();
+            return Err(Error::ValidationError(vec![
+                ValidationError::IrreproducibleBundle(said),
+            ]));
+        }
+        self.build(&oca_build)
+    }
+
     fn parse_and_check_base(
         storage: &dyn DataStorage,
         ocafile: String,
@@ -176,42 +330,38 @@ impl Facade {
                 first_command.clone().kind,
                 first_command.clone().object_kind,
             ) {
-                match content.said {
-                    ReferenceAttrType::Reference(refs) => {
-                        match refs {
-                            RefValue::Said(said) => {
-                                match get_oca_bundle(storage, said, false) {
-                                    Ok(oca_bundle) => {
-                                        // TODO
-                                        base = Some(oca_bundle.bundle.clone());
-                                    }
-                                    Err(e) => {
-                                        let default_command_meta =
-                                            oca_ast_semantics::ast::CommandMeta {
-                                                line_number: 0,
-                                                raw_line: "unknown".to_string(),
-                                            };
-                                        let command_meta = oca_ast
-                                            .commands_meta
-                                            .get(&0)
-                                            .unwrap_or(&default_command_meta);
-                                        e.iter().for_each(|e| {
-                                            errors.push(ValidationError::InvalidCommand {
-                                                line_number: command_meta.line_number,
-                                                raw_line: command_meta.raw_line.clone(),
-                                                message: e.clone(),
-                                            })
-                                        });
-                                    }
-                                }
-                            }
-                            RefValue::Name(_) => todo!(),
+                let default_command_meta = oca_ast_semantics::ast::CommandMeta {
+                    line_number: 0,
+                    raw_line: "unknown".to_string(),
+                };
+                let command_meta = oca_ast
+                    .commands_meta
+                    .get(&0)
+                    .unwrap_or(&default_command_meta);
+                let invalid_command = |message: String| ValidationError::InvalidCommand {
+                    line_number: command_meta.line_number,
+                    raw_line: command_meta.raw_line.clone(),
+                    message,
+                };
+                let ReferenceAttrType::Reference(refs) = content.said;
+                match refs {
+                    RefValue::Said(said) => match get_oca_bundle(storage, said, false) {
+                        Ok(oca_bundle) => {
+                            base = Some(oca_bundle.bundle.clone());
                         }
-                    }
+                        Err(FetchError::Storage(e)) => errors.push(ValidationError::Storage(e)),
+                        Err(e) => errors.push(invalid_command(e.to_string())),
+                    },
+                    RefValue::Name(name) => errors.push(invalid_command(format!(
+                        "Base bundle must be given by SAID (refs:), not by name refn:{name}"
+                    ))),
                 }
                 oca_ast.commands.remove(0);
             }
         };
+        if !errors.is_empty() {
+            return Err(errors);
+        }
         Ok((base, oca_ast))
     }
 
@@ -237,22 +387,24 @@ impl Facade {
         if schema_name.is_some() {
             let schema_name = schema_name.unwrap();
             let said = oca_build.oca_bundle.said.clone().unwrap().to_string();
-            references.save(schema_name, said.clone());
+            references
+                .save(schema_name, said.clone())
+                .map_err(|e| vec![e.into()])?;
         };
         Ok(oca_build)
     }
 
-    fn build_cache(&self, oca_bundle: &OCABundle) {
-        let oca_bundle_cache_repo = OCABundleCacheRepo::new(self.connection());
+    pub(crate) fn build_cache(&self, oca_bundle: &OCABundle) -> Result<(), StorageError> {
+        let oca_bundle_cache_repo = OCABundleCacheRepo::new(self.connection())?;
         let oca_bundle_cache_record = OCABundleCacheRecord::new(oca_bundle);
-        oca_bundle_cache_repo.insert(oca_bundle_cache_record);
+        oca_bundle_cache_repo.insert(oca_bundle_cache_record)?;
 
-        let capture_base_cache_repo = CaptureBaseCacheRepo::new(self.connection());
+        let capture_base_cache_repo = CaptureBaseCacheRepo::new(self.connection())?;
         let capture_base_cache_record = CaptureBaseCacheRecord::new(&oca_bundle.capture_base);
-        capture_base_cache_repo.insert(capture_base_cache_record);
+        capture_base_cache_repo.insert(capture_base_cache_record)
     }
 
-    fn build_meta(&self, oca_bundle: &OCABundle) {
+    pub(crate) fn build_meta(&self, oca_bundle: &OCABundle) -> Result<(), StorageError> {
         let meta_overlays = oca_bundle
             .overlays
             .iter()
@@ -262,7 +414,7 @@ impl Facade {
             })
             .collect::<Vec<_>>();
         if !meta_overlays.is_empty() {
-            let oca_bundle_fts_repo = OCABundleFTSRepo::new(self.connection());
+            let oca_bundle_fts_repo = OCABundleFTSRepo::new(self.connection())?;
             for meta_overlay in meta_overlays {
                 let oca_bundle_fts_record = OCABundleFTSRecord::new(
                     oca_bundle.said.clone().unwrap().to_string(),
@@ -279,173 +431,319 @@ impl Facade {
                     meta_overlay.language,
                 );
 
-                oca_bundle_fts_repo.insert(oca_bundle_fts_record);
+                oca_bundle_fts_repo.insert(oca_bundle_fts_record)?;
             }
         }
+        Ok(())
     }
 
-    fn build_step(&mut self, step: &OCABuildStep) {
-        let mut input: Vec<u8> = vec![];
-        match &step.parent_said {
-            Some(said) => {
-                input.push(said.to_string().len().try_into().unwrap());
-                input.extend(said.to_string().as_bytes());
-            }
-            None => {
-                input.push(0);
-            }
-        }
-
-        let command_str = serde_json::to_string(&step.command).unwrap();
-        input.extend(command_str.as_bytes());
+    fn build_step(
+        step: &OCABuildStep,
+        batch: &mut Batch,
+        cache_batch: &mut Batch,
+    ) -> Result<(), StorageError> {
+        let encoding_error = |e: &dyn std::fmt::Display| StorageError::Encoding(e.to_string());
+        let command_str = serde_json::to_string(&step.command).map_err(|e| encoding_error(&e))?;
+        let input = Record::new()
+            .with_field(parent_field(&step.parent_said))
+            .with_field(command_str)
+            .encode();
         let result_bundle = step.result.clone();
-        self.db
-            .insert(
-                Namespace::OCA,
-                &format!("oca.{}.operation", result_bundle.said.clone().unwrap()),
-                &input,
-            )
-            .unwrap();
+        batch.insert(
+            Namespace::OCA,
+            &format!("oca.{}.operation", result_bundle.said.clone().unwrap()),
+            &input,
+        );
+
+        Self::stage_bundle_objects(&result_bundle, cache_batch)
+    }
 
+    /// Adds JSON of the bundle, its capture base and overlays to the batch.
+    pub(crate) fn stage_bundle_objects(
+        bundle: &OCABundle,
+        cache_batch: &mut Batch,
+    ) -> Result<(), StorageError> {
+        let encoding_error = |e: &dyn std::fmt::Display| StorageError::Encoding(e.to_string());
         let code = HashFunctionCode::Blake3_256;
         let format = SerializationFormats::JSON;
-        self.db_cache
-            .insert(
-                Namespace::OCABundlesJSON,
-                &result_bundle.said.clone().unwrap().to_string(),
-                &result_bundle.encode(&code, &format).unwrap(),
-            )
-            .unwrap();
-        self.db_cache
-            .insert(
+        cache_batch.insert(
+            Namespace::OCABundlesJSON,
+            &bundle.said.clone().unwrap().to_string(),
+            &bundle
+                .encode(&code, &format)
+                .map_err(|e| encoding_error(&e))?,
+        );
+        cache_batch.insert(
+            Namespace::OCAObjectsJSON,
+            &bundle.capture_base.said.clone().unwrap().to_string(),
+            &serde_json::to_vec(&bundle.capture_base).map_err(|e| encoding_error(&e))?,
+        );
+        for overlay in &bundle.overlays {
+            cache_batch.insert(
                 Namespace::OCAObjectsJSON,
-                &result_bundle.capture_base.said.clone().unwrap().to_string(),
-                &serde_json::to_string(&result_bundle.capture_base)
-                    .unwrap()
-                    .into_bytes(),
-            )
-            .unwrap();
-        result_bundle.overlays.iter().for_each(|overlay| {
-            self.db_cache
-                .insert(
-                    Namespace::OCAObjectsJSON,
-                    &overlay.said().clone().unwrap().to_string(),
-                    &serde_json::to_string(&overlay).unwrap().into_bytes(),
-                )
-                .unwrap();
-        });
+                &overlay.said().clone().unwrap().to_string(),
+                &serde_json::to_vec(&overlay).map_err(|e| encoding_error(&e))?,
+            );
+        }
+        Ok(())
     }
 
-    fn build_models(&mut self, oca_build: &OCABuild) {
+    fn build_models(oca_build: &OCABuild, batch: &mut Batch) {
         let result_models = build_core_db_model(oca_build);
         result_models.iter().for_each(|model| {
             if let Some(command_model) = &model.command {
-                self.db
-                    .insert(
-                        Namespace::CoreModel,
-                        &format!("core_model.{}", command_model.digest),
-                        &command_model.json.clone().into_bytes(),
-                    )
-                    .unwrap();
+                batch.insert(
+                    Namespace::CoreModel,
+                    &format!("core_model.{}", command_model.digest),
+                    &command_model.json.clone().into_bytes(),
+                );
             }
 
             if let Some(capture_base_model) = &model.capture_base {
-                let mut input: Vec<u8> = vec![];
-                match &capture_base_model.parent {
-                    Some(said) => {
-                        input.push(said.to_string().len().try_into().unwrap());
-                        input.extend(said.to_string().as_bytes());
-                    }
-                    None => {
-                        input.push(0);
-                    }
-                }
-
-                input.push(
-                    capture_base_model
-                        .command_digest
-                        .to_string()
-                        .len()
-                        .try_into()
-                        .unwrap(),
+                let input = Record::new()
+                    .with_field(parent_field(&capture_base_model.parent))
+                    .with_field(capture_base_model.command_digest.to_string());
+
+                batch.insert(
+                    Namespace::CoreModel,
+                    &format!("core_model.{}", capture_base_model.capture_base_said),
+                    &input.encode(),
                 );
-                input.extend(capture_base_model.command_digest.to_string().as_bytes());
-
-                self.db
-                    .insert(
-                        Namespace::CoreModel,
-                        &format!("core_model.{}", capture_base_model.capture_base_said),
-                        &input,
-                    )
-                    .unwrap();
             }
 
             if let Some(overlay_model) = &model.overlay {
-                let mut input: Vec<u8> = vec![];
-                match &overlay_model.parent {
-                    Some(said) => {
-                        input.push(said.to_string().len().try_into().unwrap());
-                        input.extend(said.to_string().as_bytes());
-                    }
-                    None => {
-                        input.push(0);
-                    }
-                }
-
-                input.push(
-                    overlay_model
-                        .command_digest
-                        .to_string()
-                        .len()
-                        .try_into()
-                        .unwrap(),
+                let input = Record::new()
+                    .with_field(parent_field(&overlay_model.parent))
+                    .with_field(overlay_model.command_digest.to_string());
+
+                batch.insert(
+                    Namespace::CoreModel,
+                    &format!("core_model.{}", overlay_model.overlay_said),
+                    &input.encode(),
                 );
-                input.extend(overlay_model.command_digest.to_string().as_bytes());
-
-                self.db
-                    .insert(
-                        Namespace::CoreModel,
-                        &format!("core_model.{}", overlay_model.overlay_said),
-                        &input,
-                    )
-                    .unwrap();
             }
 
             if let Some(oca_bundle_model) = &model.oca_bundle {
-                let mut input: Vec<u8> = vec![];
-                match &oca_bundle_model.parent {
-                    Some(said) => {
-                        input.push(said.to_string().len().try_into().unwrap());
-                        input.extend(said.to_string().as_bytes());
-                    }
-                    None => {
-                        input.push(0);
-                    }
-                }
-
-                input.push(
-                    oca_bundle_model
-                        .capture_base_said
-                        .to_string()
-                        .len()
-                        .try_into()
-                        .unwrap(),
-                );
-                input.extend(oca_bundle_model.capture_base_said.to_string().as_bytes());
-
+                let mut input = Record::new()
+                    .with_field(parent_field(&oca_bundle_model.parent))
+                    .with_field(oca_bundle_model.capture_base_said.to_string());
                 for said in &oca_bundle_model.overlays_said {
-                    input.push(said.to_string().len().try_into().unwrap());
-                    input.extend(said.to_string().as_bytes());
+                    input.push(said.to_string());
                 }
 
-                self.db
-                    .insert(
-                        Namespace::CoreModel,
-                        &format!("core_model.{}", oca_bundle_model.oca_bundle_said),
-                        &input,
-                    )
-                    .unwrap();
+                batch.insert(
+                    Namespace::CoreModel,
+                    &format!("core_model.{}", oca_bundle_model.oca_bundle_said),
+                    &input.encode(),
+                );
             }
         });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_storage::{DataStorage, InMemoryDataStorage};
+    use crate::repositories::SQLiteConfig;
+
+    fn facade() -> Facade {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap()
+    }
+
+    /// Storage failing every batch.
+    #[derive(Clone)]
+    struct ReadOnlyStorage(InMemoryDataStorage);
+
+    impl DataStorage for ReadOnlyStorage {
+        fn get(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+            self.0.get(namespace, key)
+        }
+
+        fn get_all(
+            &self,
+            namespace: Namespace,
+        ) -> Result<std::collections::HashMap<String, Vec<u8>>, StorageError> {
+            self.0.get_all(namespace)
+        }
+
+        fn insert(
+            &mut self,
+            namespace: Namespace,
+            key: &str,
+            value: &[u8],
+        ) -> Result<(), StorageError> {
+            self.0.insert(namespace, key, value)
+        }
+
+        fn delete(&mut self, namespace: Namespace, key: &str) -> Result<(), StorageError> {
+            self.0.delete(namespace, key)
+        }
+
+        fn list_keys(
+            &self,
+            namespace: Namespace,
+            prefix: &str,
+            offset: usize,
+            limit: Option<usize>,
+        ) -> Result<Vec<String>, StorageError> {
+            self.0.list_keys(namespace, prefix, offset, limit)
+        }
+
+        fn write_batch(&mut self, _batch: Batch) -> Result<(), StorageError> {
+            Err(StorageError::Io("read only".to_string()))
+        }
+
+        fn new() -> Self {
+            Self(InMemoryDataStorage::new())
+        }
+
+        fn config(&self, _config: std::collections::HashMap<String, String>) -> Self {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn facade_build_with_failing_storage() {
+        let ocafile = "ADD ATTRIBUTE name=Text\nADD LABEL en ATTRS name=\"Name\"\n";
+        let oca_build = facade().validate_ocafile(ocafile.to_string()).unwrap();
+        let said = oca_build.oca_bundle.said.clone().unwrap();
+
+        let mut facade = Facade::new(
+            Box::new(ReadOnlyStorage(InMemoryDataStorage::new())),
+            Box::new(InMemoryDataStorage::new()),
+            SQLiteConfig::build().unwrap(),
+        )
+        .unwrap();
+        let error = facade.build(&oca_build).unwrap_err();
+        assert_eq!(error.code(), "io");
+        // Content addressed objects of the first batch stay behind
+        assert!(facade
+            .db_cache
+            .get(Namespace::OCABundlesJSON, &said.to_string())
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            facade
+                .search_oca_bundle(None, "Name".to_string(), 10, 1)
+                .unwrap()
+                .metadata
+                .total,
+            0
+        );
+    }
+
+    #[test]
+    fn facade_import_bundle() {
+        let ocafile = r#"
+ADD ATTRIBUTE d=Text i=Text passed=Boolean
+ADD META en PROPS description="Entrance credential" name="Entrance credential"
+ADD CHARACTER_ENCODING ATTRS d="utf-8" i="utf-8" passed="utf-8"
+ADD CONFORMANCE ATTRS d="M" i="M" passed="M"
+ADD LABEL en ATTRS d="Schema digest" i="Credential Issuee" passed="Passed"
+ADD FORMAT ATTRS d="image/jpeg"
+ADD UNIT ATTRS i=m
+ADD ATTRIBUTE list=Array[Text] el=Text
+ADD CARDINALITY ATTRS list="1-2"
+ADD ENTRY_CODE ATTRS list="entry_code_said" el=["o1", "o2", "o3"]
+ADD ENTRY en ATTRS list="refs:ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI" el={"o1": "o1_label", "o2": "o2_label", "o3": "o3_label"}
+"#;
+        let bundle = facade().build_from_ocafile(ocafile.to_string()).unwrap();
+        let said = bundle.said.clone().unwrap();
+        let json = String::from_utf8(
+            bundle
+                .encode(&HashFunctionCode::Blake3_256, &SerializationFormats::JSON)
+                .unwrap(),
+        )
+        .unwrap();
+
+        let mut facade = facade();
+        let imported = facade.import_bundle(&json).unwrap();
+        assert_eq!(imported.said, Some(said.clone()));
+        assert_eq!(
+            facade
+                .get_oca_bundle(said.clone(), false)
+                .unwrap()
+                .bundle
+                .said,
+            Some(said.clone())
+        );
+        assert!(!facade
+            .get_oca_bundle_steps(said.clone())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            facade.explore(said.to_string()).unwrap().relations.len(),
+            bundle.overlays.len() + 1
+        );
+        let found = facade
+            .search_oca_bundle(None, "Entrance".to_string(), 10, 1)
+            .unwrap();
+        assert_eq!(found.metadata.total, 1);
+        assert_eq!(
+            facade.fetch_all_capture_base(10, 1).unwrap().metadata.total,
+            1
+        );
+        assert!(facade
+            .db
+            .get(Namespace::CoreModel, &format!("core_model.{}", said))
+            .unwrap()
+            .is_some());
+
+        let tampered = json.replacen("Schema digest", "Schema hash", 1);
+        let Err(Error::ValidationError(errors)) = facade.import_bundle(&tampered) else {
+            panic!("Tampered bundle imported");
+        };
+        assert!(errors.iter().all(|e| e.code() == "invalid_bundle"));
+        let error = facade.import_bundle("{}").unwrap_err();
+        assert_eq!(error.code(), "validation");
+        let malformed_overlay = json.replacen("\"type\":\"spec/overlays/label", "\"type\":1", 1);
+        assert!(facade.import_bundle(&malformed_overlay).is_err());
+    }
+
+    #[test]
+    fn facade_validate_transformation() {
+        let mut facade = facade();
+        let source = facade
+            .build_from_ocafile("ADD ATTRIBUTE surname=Text age=Text\n".to_string())
+            .unwrap();
+        let target = facade
+            .build_from_ocafile("ADD ATTRIBUTE last_name=Text age=Numeric\n".to_string())
+            .unwrap();
+        let header = format!(
+            "-- source=refs:{}\n-- target=refs:{}\n",
+            source.said.clone().unwrap(),
+            target.said.clone().unwrap()
+        );
+
+        let transformation = facade
+            .validate_transformation(format!("{header}RENAME ATTRIBUTE surname=last_name\n"))
+            .unwrap();
+        assert_eq!(transformation.attributes["surname"], "last_name");
+
+        let errors = facade
+            .validate_transformation(format!("{header}LINK ATTRIBUTE age -> age\n"))
+            .unwrap_err();
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["Error at line 3 (LINK ATTRIBUTE age -> age): Attribute age of type Text can not be mapped to age of type Numeric"]
+        );
+
+        let errors = facade
+            .validate_transformation(
+                "-- source=refs:unknown\nRENAME ATTRIBUTE surname=last_name\n".to_string(),
+            )
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let errors = facade
+            .validate_transformation(format!(
+                "-- source=refs:unknown\n-- target=refs:{}\nRENAME ATTRIBUTE a=b\n",
+                target.said.clone().unwrap()
+            ))
+            .unwrap_err();
+        assert!(matches!(&errors[..], [ValidationError::UnknownBundle(said)] if said == "unknown"));
+    }
+}
@@ -1,139 +1,154 @@
-use crate::data_storage::Namespace;
+use super::fetch::FetchError;
+use crate::data_storage::{Batch, Namespace, StorageError};
 use oca_ast_semantics::ast::{BundleContent, CaptureContent, Content, ObjectKind, RefValue};
 use oca_bundle_semantics::state::oca::OCABundle;
+use oca_dag_semantics::record::Record;
 use serde::{ser::SerializeStruct, Serialize};
 use std::collections::HashSet;
 
 use super::Facade;
 
 impl Facade {
-    pub fn explore(&self, said: String) -> Option<Relationship> {
-        let relations_u8_res = self.db.get(Namespace::OCARelations, &said);
-
-        match relations_u8_res {
-            Ok(Some(relations_u8)) => {
-                let relationship: Relationship = relations_u8.into();
-                Some(Relationship {
-                    base_object: OCAObject::new(self, said),
-                    relations: relationship.relations,
-                })
-            }
-            _ => None,
-        }
+    pub fn explore(&self, said: String) -> Result<Relationship, FetchError> {
+        let relations_u8 = self
+            .db
+            .get(Namespace::OCARelations, &said)?
+            .ok_or_else(|| FetchError::not_found("relations", &said))?;
+        let relationship = Relationship::try_from(relations_u8)?;
+        Ok(Relationship {
+            base_object: OCAObject::new(self, said)?,
+            relations: relationship.relations,
+        })
     }
 
-    fn insert_oca_objects_metadata(&mut self, oca_bundle: OCABundle) -> Result<(), String> {
-        self.db.insert(
-            Namespace::OCARelations,
-            &format!("{}.metadata", oca_bundle.said.clone().unwrap()),
-            &[ObjectKind::OCABundle(BundleContent {
-                said: oca_ast_semantics::ast::ReferenceAttrType::Reference(RefValue::Name(
-                    "".to_string(),
-                )),
-            })
-            .into()],
-        )?;
-        self.db.insert(
-            Namespace::OCARelations,
-            &format!("{}.metadata", oca_bundle.capture_base.said.clone().unwrap()),
-            &[ObjectKind::CaptureBase(CaptureContent {
-                attributes: None,
-                properties: None,
-                flagged_attributes: None,
-            })
-            .into()],
-        )?;
-        oca_bundle.overlays.iter().for_each(|overlay| {
-            let _ = self.db.insert(
+    fn stage_oca_objects_metadata(oca_bundle: &OCABundle, batch: &mut Batch) {
+        for object in Self::oca_objects(oca_bundle) {
+            batch.insert(
                 Namespace::OCARelations,
-                &format!("{}.metadata", overlay.said().clone().unwrap()),
-                &[ObjectKind::Overlay(
+                &format!("{}.metadata", object.said),
+                &[object.object_type.into()],
+            );
+        }
+    }
+
+    /// Objects making up the bundle: the bundle itself, its capture base and
+    /// overlays, in that order.
+    fn oca_objects(oca_bundle: &OCABundle) -> Vec<OCAObject> {
+        let object = |said: String, kind: ObjectKind| {
+            // Same kind as read back from stored metadata
+            let kind: u8 = kind.into();
+            OCAObject {
+                said,
+                object_type: kind.into(),
+            }
+        };
+        let mut objects = vec![
+            object(
+                oca_bundle.said.clone().unwrap().to_string(),
+                ObjectKind::OCABundle(BundleContent {
+                    said: oca_ast_semantics::ast::ReferenceAttrType::Reference(RefValue::Name(
+                        "".to_string(),
+                    )),
+                }),
+            ),
+            object(
+                oca_bundle.capture_base.said.clone().unwrap().to_string(),
+                ObjectKind::CaptureBase(CaptureContent {
+                    attributes: None,
+                    properties: None,
+                    flagged_attributes: None,
+                }),
+            ),
+        ];
+        objects.extend(oca_bundle.overlays.iter().map(|overlay| {
+            object(
+                overlay.said().clone().unwrap().to_string(),
+                ObjectKind::Overlay(
                     overlay.overlay_type().clone(),
                     Content {
                         attributes: None,
                         properties: None,
                     },
-                )
-                .into()],
-            );
-        });
+                ),
+            )
+        }));
+        objects
+    }
 
-        Ok(())
+    pub fn add_relations(&mut self, oca_bundle: OCABundle) -> Result<(), StorageError> {
+        let mut batch = Batch::new();
+        self.stage_relations(&oca_bundle, &mut batch)?;
+        self.db.write_batch(batch)
     }
 
-    pub fn add_relations(&mut self, oca_bundle: OCABundle) -> Result<(), String> {
-        self.insert_oca_objects_metadata(oca_bundle.clone())?;
-
-        let oca_bundle_said = oca_bundle.said.clone().unwrap().to_string();
-        let capture_base_said = oca_bundle.capture_base.said.clone().unwrap().to_string();
-
-        let mut oca_bundle_rel =
-            self.explore(oca_bundle_said.clone())
-                .unwrap_or(Relationship::new(OCAObject::new(
-                    self,
-                    oca_bundle_said.clone(),
-                )));
-        oca_bundle_rel.add_relation(OCAObject::new(self, capture_base_said.clone()));
-
-        let mut capture_base_rel =
-            self.explore(capture_base_said.clone())
-                .unwrap_or(Relationship::new(OCAObject::new(
-                    self,
-                    capture_base_said.clone(),
-                )));
-        capture_base_rel.add_relation(OCAObject::new(self, oca_bundle_said.clone()));
-
-        for overlay in oca_bundle.overlays {
-            let overlay_said = overlay.said().clone().unwrap().to_string();
-
-            oca_bundle_rel.add_relation(OCAObject::new(self, overlay_said.clone()));
-            capture_base_rel.add_relation(OCAObject::new(self, overlay_said.clone()));
-
-            let mut overlay_rel = self
-                .explore(overlay_said.clone())
-                .unwrap_or(Relationship::new(OCAObject::new(
-                    self,
-                    overlay_said.clone(),
-                )));
-
-            overlay_rel.add_relation(OCAObject::new(self, oca_bundle_said.clone()));
-            overlay_rel.add_relation(OCAObject::new(self, capture_base_said.clone()));
-            let overlay_rel_u8: Vec<u8> = overlay_rel.clone().into();
-            self.db.insert(
+    /// Adds metadata and relations of the bundle objects to the batch,
+    /// merged with the relations already stored.
+    pub(crate) fn stage_relations(
+        &self,
+        oca_bundle: &OCABundle,
+        batch: &mut Batch,
+    ) -> Result<(), StorageError> {
+        Self::stage_oca_objects_metadata(oca_bundle, batch);
+
+        let mut objects = Self::oca_objects(oca_bundle).into_iter();
+        let oca_bundle_object = objects.next().unwrap();
+        let capture_base_object = objects.next().unwrap();
+        let relationship = |object: &OCAObject| match self.explore(object.said.clone()) {
+            Ok(relationship) => Ok(relationship),
+            Err(FetchError::NotFound { .. }) => Ok(Relationship::new(object.clone())),
+            Err(FetchError::Storage(e)) => Err(e),
+            Err(e) => Err(StorageError::Corrupt(e.to_string())),
+        };
+
+        let mut oca_bundle_rel = relationship(&oca_bundle_object)?;
+        oca_bundle_rel.add_relation(capture_base_object.clone());
+
+        let mut capture_base_rel = relationship(&capture_base_object)?;
+        capture_base_rel.add_relation(oca_bundle_object.clone());
+
+        for overlay_object in objects {
+            oca_bundle_rel.add_relation(overlay_object.clone());
+            capture_base_rel.add_relation(overlay_object.clone());
+
+            let mut overlay_rel = relationship(&overlay_object)?;
+            overlay_rel.add_relation(oca_bundle_object.clone());
+            overlay_rel.add_relation(capture_base_object.clone());
+            let overlay_rel_u8: Vec<u8> = overlay_rel.into();
+            batch.insert(
                 Namespace::OCARelations,
-                &overlay_said.clone(),
+                &overlay_object.said,
                 &overlay_rel_u8,
-            )?;
+            );
         }
 
         let oca_bundle_rel_u8: Vec<u8> = oca_bundle_rel.clone().into();
-        self.db.insert(
+        batch.insert(
             Namespace::OCARelations,
             &oca_bundle_rel.base_object.said,
             &oca_bundle_rel_u8,
-        )?;
+        );
 
         let capture_base_rel_u8: Vec<u8> = capture_base_rel.clone().into();
-        self.db.insert(
+        batch.insert(
             Namespace::OCARelations,
             &capture_base_rel.base_object.said,
             &capture_base_rel_u8,
-        )?;
-
+        );
         Ok(())
     }
 
-    fn object_type(&self, said: String) -> ObjectKind {
+    fn object_type(&self, said: &str) -> Result<ObjectKind, FetchError> {
         let object_type = self
             .db
-            .get(Namespace::OCARelations, &format!("{}.metadata", said))
-            .unwrap();
+            .get(Namespace::OCARelations, &format!("{}.metadata", said))?
+            .and_then(|object_type| object_type.first().copied())
+            .ok_or_else(|| FetchError::not_found("OCA object metadata", said))?;
 
-        (*object_type.unwrap().first().unwrap()).into()
+        Ok(object_type.into())
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Relationship {
     pub base_object: OCAObject,
     pub relations: HashSet<OCAObject>,
@@ -154,20 +169,19 @@ impl Relationship {
 
 impl From<Relationship> for Vec<u8> {
     fn from(val: Relationship) -> Self {
-        let mut result: Vec<u8> = Vec::new();
-
+        let mut record = Record::new();
         val.relations.iter().for_each(|object| {
-            result.push(object.object_type.clone().into());
-            result.push(object.said.len().try_into().unwrap());
-            result.extend(object.said.as_bytes());
+            let object_type: u8 = object.object_type.clone().into();
+            record.push([&[object_type], object.said.as_bytes()].concat());
         });
-
-        result
+        record.encode()
     }
 }
 
-impl From<Vec<u8>> for Relationship {
-    fn from(val: Vec<u8>) -> Self {
+impl TryFrom<Vec<u8>> for Relationship {
+    type Error = StorageError;
+
+    fn try_from(val: Vec<u8>) -> Result<Self, Self::Error> {
         let mut result = Relationship::new(OCAObject {
             said: "".to_string(),
             object_type: ObjectKind::OCABundle(BundleContent {
@@ -177,27 +191,53 @@ impl From<Vec<u8>> for Relationship {
             }),
         });
 
-        let mut tmp_val = val.clone();
-        while !tmp_val.is_empty() {
-            let said_len = tmp_val[1];
-            let (o_u8, split_val) = tmp_val.split_at(2 + said_len as usize);
-            result.add_relation(OCAObject::from(o_u8.to_vec()));
-            tmp_val = split_val.to_vec();
+        for field in relation_fields(&val)? {
+            result.add_relation(OCAObject::try_from(field)?);
         }
 
-        result
+        Ok(result)
     }
 }
 
-impl From<Vec<u8>> for OCAObject {
-    fn from(val: Vec<u8>) -> Self {
-        let object_type = val[0];
-        let said_len = val[1];
-        let said = String::from_utf8(val[2..2 + said_len as usize].to_vec()).unwrap();
-        Self {
+/// Splits relations record into `[object type, said...]` fields. Records
+/// written before the versioned format are sequences of object type, `u8`
+/// said length and said, where the type byte never equals the record marker.
+fn relation_fields(val: &[u8]) -> Result<Vec<Vec<u8>>, StorageError> {
+    if Record::is_versioned(val) {
+        return Ok(Record::decode(val)
+            .map_err(|e| StorageError::Corrupt(format!("Relations: {e}")))?
+            .into_fields());
+    }
+    let mut fields = vec![];
+    let mut rest = val;
+    while let [object_type, said_len, tail @ ..] = rest {
+        let said = tail
+            .get(..*said_len as usize)
+            .ok_or_else(truncated_relations)?;
+        fields.push([&[*object_type], said].concat());
+        rest = &tail[said.len()..];
+    }
+    if !rest.is_empty() {
+        return Err(truncated_relations());
+    }
+    Ok(fields)
+}
+
+fn truncated_relations() -> StorageError {
+    StorageError::Corrupt("Relations are truncated".to_string())
+}
+
+impl TryFrom<Vec<u8>> for OCAObject {
+    type Error = StorageError;
+
+    fn try_from(val: Vec<u8>) -> Result<Self, Self::Error> {
+        let (&object_type, said) = val.split_first().ok_or_else(truncated_relations)?;
+        let said = String::from_utf8(said.to_vec())
+            .map_err(|e| StorageError::Corrupt(format!("Related object said: {e}")))?;
+        Ok(Self {
             said,
             object_type: object_type.into(),
-        }
+        })
     }
 }
 
@@ -236,10 +276,10 @@ impl Serialize for OCAObject {
 }
 
 impl OCAObject {
-    fn new(facade: &Facade, said: String) -> Self {
-        Self {
-            said: said.clone(),
-            object_type: facade.object_type(said),
-        }
+    fn new(facade: &Facade, said: String) -> Result<Self, FetchError> {
+        Ok(Self {
+            object_type: facade.object_type(&said)?,
+            said,
+        })
     }
 }
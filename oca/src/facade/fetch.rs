@@ -1,14 +1,21 @@
 use super::Facade;
 use crate::data_storage::Namespace;
 #[cfg(feature = "local-references")]
+use crate::facade::build::ValidationError;
+#[cfg(feature = "local-references")]
 use crate::local_references;
 use crate::{
-    data_storage::DataStorage,
+    data_storage::{DataStorage, StorageError},
     repositories::{OCABundleCacheRepo, OCABundleFTSRepo},
 };
 use oca_ast_semantics::ast::{self, OCAAst, ObjectKind, RefValue};
 use oca_bundle_semantics::build::OCABuildStep;
 use oca_bundle_semantics::state::oca::{capture_base::CaptureBase, DynOverlay, OCABundle};
+use oca_dag_semantics::record::Record;
+#[cfg(feature = "vc")]
+use oca_interop_semantics::vc;
+#[cfg(feature = "presentation")]
+use oca_presentation_semantics::form::FormRenderer;
 use said::{
     derivation::HashFunctionCode,
     sad::{SerializationFormats, SAD},
@@ -87,6 +94,128 @@ pub struct AllCaptureBaseMetadata {
     pub page: usize,
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum FetchError {
+    #[error("No {kind} found for {key}")]
+    NotFound { kind: &'static str, key: String },
+    #[error("Malformed history for said: {0}")]
+    MalformedHistory(String),
+    #[error("No transformation path from {from} to {to}")]
+    NoTransformationPath { from: String, to: String },
+    #[error("Invalid page {0}, pages are numbered from 1")]
+    InvalidPage(usize),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl FetchError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "not_found",
+            Self::MalformedHistory(_) => "malformed_history",
+            Self::NoTransformationPath { .. } => "no_transformation_path",
+            Self::InvalidPage(_) => "invalid_page",
+            Self::Storage(e) => e.code(),
+        }
+    }
+
+    pub(crate) fn not_found(kind: &'static str, key: impl ToString) -> Self {
+        Self::NotFound {
+            kind,
+            key: key.to_string(),
+        }
+    }
+}
+
+impl Serialize for FetchError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::error::serialize(self.code(), self, serializer)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SearchError {
+    #[error("Invalid search query: {0}")]
+    InvalidQuery(String),
+    #[error("Invalid page {0}, pages are numbered from 1")]
+    InvalidPage(usize),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl SearchError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidQuery(_) => "invalid_query",
+            Self::InvalidPage(_) => "invalid_page",
+            Self::Fetch(e) => e.code(),
+            Self::Storage(e) => e.code(),
+        }
+    }
+}
+
+impl Serialize for SearchError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::error::serialize(self.code(), self, serializer)
+    }
+}
+
+#[cfg(feature = "vc")]
+#[derive(thiserror::Error, Debug)]
+pub enum CredentialError {
+    #[error(transparent)]
+    Credential(vc::Error),
+    #[error("Credential does not conform to its OCA bundle: {}", join(.0))]
+    Violations(Vec<vc::Error>),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+}
+
+#[cfg(feature = "vc")]
+fn join(errors: &[vc::Error]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(feature = "vc")]
+impl CredentialError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Credential(_) => "invalid_credential",
+            Self::Violations(_) => "credential_violations",
+            Self::Fetch(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(feature = "vc")]
+impl Serialize for CredentialError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::error::serialize(self.code(), self, serializer)
+    }
+}
+
+/// Decodes stored JSON record, failure means the record is corrupt.
+pub(crate) fn decode<T: serde::de::DeserializeOwned>(
+    kind: &str,
+    said: &str,
+    value: &[u8],
+) -> Result<T, StorageError> {
+    serde_json::from_slice(value)
+        .map_err(|e| StorageError::Corrupt(format!("Failed to parse {kind} {said}: {e}")))
+}
+
 impl Facade {
     pub fn search_oca_bundle(
         &self,
@@ -94,44 +223,48 @@ impl Facade {
         query: String,
         limit: usize,
         page: usize,
-    ) -> SearchResult {
-        let oca_bundle_fts_repo = OCABundleFTSRepo::new(self.connection());
-        let search_result = oca_bundle_fts_repo.search(language, query, limit, page);
-        let records = search_result
-            .records
-            .iter()
-            .map(|record| SearchRecord {
-                // TODO
+    ) -> Result<SearchResult, SearchError> {
+        if page == 0 {
+            return Err(SearchError::InvalidPage(page));
+        }
+        let oca_bundle_fts_repo = OCABundleFTSRepo::new(self.connection())?;
+        let search_result = oca_bundle_fts_repo
+            .search(language, query, limit, page)
+            .map_err(|e| match e {
+                StorageError::Database(message) if message.starts_with("fts5:") => {
+                    SearchError::InvalidQuery(message)
+                }
+                e => e.into(),
+            })?;
+        let mut records = vec![];
+        for record in search_result.records {
+            records.push(SearchRecord {
                 oca_bundle: self
-                    .get_oca_bundle(record.oca_bundle_said.clone(), false)
-                    .unwrap()
-                    .bundle
-                    .clone(),
+                    .get_oca_bundle(record.oca_bundle_said.clone(), false)?
+                    .bundle,
                 metadata: SearchRecordMetadata {
                     phrase: record.metadata.phrase.clone(),
                     scope: record.metadata.scope.clone(),
                     score: record.metadata.score,
                 },
-            })
-            .collect();
-        SearchResult {
+            });
+        }
+        Ok(SearchResult {
             records,
             metadata: SearchMetadata {
                 total: search_result.metadata.total,
                 page: search_result.metadata.page,
             },
-        }
+        })
     }
     #[cfg(feature = "local-references")]
-    pub fn fetch_all_refs(&self) -> Result<HashMap<String, String>, String> {
+    pub fn fetch_all_refs(&self) -> Result<HashMap<String, String>, FetchError> {
         let mut refs: HashMap<String, String> = HashMap::new();
-        self.db
-            .get_all(Namespace::OCAReferences)
-            .unwrap()
-            .iter()
-            .for_each(|(k, v)| {
-                refs.insert(k.clone(), String::from_utf8(v.to_vec()).unwrap());
-            });
+        for (k, v) in self.db.get_all(Namespace::OCAReferences)? {
+            let said = String::from_utf8(v)
+                .map_err(|e| StorageError::Corrupt(format!("Reference {k}: {e}")))?;
+            refs.insert(k, said);
+        }
         Ok(refs)
     }
 
@@ -139,31 +272,27 @@ impl Facade {
         &self,
         limit: usize,
         page: usize,
-    ) -> Result<AllOCABundleResult, Vec<String>> {
+    ) -> Result<AllOCABundleResult, FetchError> {
+        if page == 0 {
+            return Err(FetchError::InvalidPage(page));
+        }
         let mut oca_bundles = vec![];
         let mut total: usize = 0;
-        let mut errors = vec![];
 
-        let oca_bundle_cache_repo = OCABundleCacheRepo::new(self.connection());
-        let all_oca_bundle_records = oca_bundle_cache_repo.fetch_all(limit, page);
+        let oca_bundle_cache_repo = OCABundleCacheRepo::new(self.connection())?;
+        let all_oca_bundle_records = oca_bundle_cache_repo.fetch_all(limit, page)?;
         for all_oca_bundle_record in all_oca_bundle_records {
             if total == 0 {
                 total = all_oca_bundle_record.total;
             }
             if let Some(cache_record) = all_oca_bundle_record.cache_record {
-                match serde_json::from_str(&cache_record.oca_bundle) {
-                    Ok(oca_bundle) => {
-                        oca_bundles.push(oca_bundle);
-                    }
-                    Err(e) => {
-                        errors.push(format!("Failed to parse oca bundle: {}", e));
-                    }
-                }
+                oca_bundles.push(decode(
+                    "OCA bundle",
+                    &cache_record.said,
+                    cache_record.oca_bundle.as_bytes(),
+                )?);
             }
         }
-        if !errors.is_empty() {
-            return Err(errors);
-        }
 
         Ok(AllOCABundleResult {
             records: oca_bundles,
@@ -175,32 +304,28 @@ impl Facade {
         &self,
         limit: usize,
         page: usize,
-    ) -> Result<AllCaptureBaseResult, Vec<String>> {
+    ) -> Result<AllCaptureBaseResult, FetchError> {
+        if page == 0 {
+            return Err(FetchError::InvalidPage(page));
+        }
         let mut capture_bases = vec![];
         let mut total: usize = 0;
-        let mut errors = vec![];
 
         let capture_base_cache_repo =
-            crate::repositories::CaptureBaseCacheRepo::new(self.connection());
-        let all_capture_base_records = capture_base_cache_repo.fetch_all(limit, page);
+            crate::repositories::CaptureBaseCacheRepo::new(self.connection())?;
+        let all_capture_base_records = capture_base_cache_repo.fetch_all(limit, page)?;
         for all_capture_base_record in all_capture_base_records {
             if total == 0 {
                 total = all_capture_base_record.total;
             }
             if let Some(cache_record) = all_capture_base_record.cache_record {
-                match serde_json::from_str(&cache_record.capture_base) {
-                    Ok(capture_base) => {
-                        capture_bases.push(capture_base);
-                    }
-                    Err(e) => {
-                        errors.push(format!("Failed to parse capture base: {}", e));
-                    }
-                }
+                capture_bases.push(decode(
+                    "capture base",
+                    &cache_record.said,
+                    cache_record.capture_base.as_bytes(),
+                )?);
             }
         }
-        if !errors.is_empty() {
-            return Err(errors);
-        }
 
         Ok(AllCaptureBaseResult {
             records: capture_bases,
@@ -208,55 +333,32 @@ impl Facade {
         })
     }
 
-    pub fn get_oca_objects(&self, saids: Vec<String>) -> Result<Vec<OCAObject>, Vec<String>> {
+    pub fn get_oca_objects(&self, saids: Vec<String>) -> Result<Vec<OCAObject>, FetchError> {
         let mut result: Vec<OCAObject> = vec![];
-        let mut errors: Vec<String> = vec![];
 
         for said in saids {
-            let r = self
+            let object = self
                 .db_cache
-                .get(Namespace::OCAObjectsJSON, &said)
-                .map_err(|e| {
-                    errors.push(e.to_string());
-                    errors.clone()
-                })?;
-            let object_str = String::from_utf8(r.ok_or_else(|| {
-                errors.push(format!("No OCA Object found for said: {}", said));
-                errors.clone()
-            })?)
-            .unwrap();
-            let r_type = self
+                .get(Namespace::OCAObjectsJSON, &said)?
+                .ok_or_else(|| FetchError::not_found("OCA object", &said))?;
+            let o_type = self
                 .db
-                .get(Namespace::OCARelations, &format!("{}.metadata", said))
-                .map_err(|e| {
-                    errors.push(e.to_string());
-                    errors.clone()
-                })?;
-            let o_type: ObjectKind = (*r_type.unwrap().first().unwrap()).into();
-            match o_type {
-                ObjectKind::CaptureBase(_) => result.push(OCAObject::CaptureBase(
-                    serde_json::from_str::<CaptureBase>(&object_str).map_err(|e| {
-                        errors.push(format!("Failed to parse OCA object: {}", e));
-                        errors.clone()
-                    })?,
-                )),
+                .get(Namespace::OCARelations, &format!("{}.metadata", said))?
+                .and_then(|r_type| r_type.first().copied())
+                .ok_or_else(|| FetchError::not_found("OCA object metadata", &said))?;
+            match o_type.into() {
+                ObjectKind::CaptureBase(_) => result.push(OCAObject::CaptureBase(decode(
+                    "OCA object",
+                    &said,
+                    &object,
+                )?)),
                 ObjectKind::Overlay(_, _) => {
-                    let oca_overlay = OCAObject::Overlay(
-                        serde_json::from_str::<DynOverlay>(&object_str).map_err(|e| {
-                            errors.push(format!("Failed to parse OCA object: {}", e));
-                            errors.clone()
-                        })?,
-                    );
-                    result.push(oca_overlay);
+                    result.push(OCAObject::Overlay(decode("OCA object", &said, &object)?))
                 }
                 _ => {}
             };
         }
 
-        if !errors.is_empty() {
-            return Err(errors);
-        }
-
         Ok(result)
     }
 
@@ -264,37 +366,63 @@ impl Facade {
         &self,
         said: SelfAddressingIdentifier,
         with_dep: bool,
-    ) -> Result<BundleWithDependencies, Vec<String>> {
+    ) -> Result<BundleWithDependencies, FetchError> {
         get_oca_bundle(self.db_cache.borrow(), said, with_dep)
     }
 
+    /// Validates Verifiable Credential against OCA bundle it claims in
+    /// `credentialSchema`. Returns SAID of the bundle on success.
+    #[cfg(feature = "vc")]
+    pub fn validate_credential(
+        &self,
+        credential: &serde_json::Value,
+    ) -> Result<SelfAddressingIdentifier, CredentialError> {
+        let said = vc::claimed_bundle(credential).map_err(CredentialError::Credential)?;
+        let bundle_with_deps = self.get_oca_bundle(said.clone(), true)?;
+        vc::validate_credential(
+            credential,
+            &bundle_with_deps.bundle,
+            &bundle_with_deps.dependencies,
+        )
+        .map_err(CredentialError::Violations)?;
+        Ok(said)
+    }
+
+    /// Renders HTML data entry form of the bundle, with `refs:` attributes
+    /// resolved from storage.
+    #[cfg(feature = "presentation")]
+    pub fn get_oca_bundle_form(
+        &self,
+        said: SelfAddressingIdentifier,
+        renderer: FormRenderer,
+    ) -> Result<String, FetchError> {
+        let bundle_with_deps = self.get_oca_bundle(said, true)?;
+        Ok(renderer
+            .with_dependencies(bundle_with_deps.dependencies)
+            .render(&bundle_with_deps.bundle))
+    }
+
     pub fn get_oca_bundle_steps(
         &self,
         said: SelfAddressingIdentifier,
-    ) -> Result<Vec<OCABuildStep>, Vec<String>> {
+    ) -> Result<Vec<OCABuildStep>, FetchError> {
         let mut said = said.to_string();
         #[allow(clippy::borrowed_box)]
         fn extract_operation(
             db: &Box<dyn DataStorage>,
             said: &String,
-        ) -> Result<(String, oca_ast_semantics::ast::Command), Vec<String>> {
+        ) -> Result<(String, oca_ast_semantics::ast::Command), FetchError> {
             let r = db
-                .get(Namespace::OCA, &format!("oca.{}.operation", said))
-                .map_err(|e| vec![format!("{}", e)])?
-                .ok_or_else(|| vec![format!("No history found for said: {}", said)])?;
-
-            let said_length = r.first().unwrap();
-            let parent_said = String::from_utf8_lossy(&r[1..*said_length as usize + 1]).to_string();
-            let op_length = r.len() - *said_length as usize - 1;
-            let op = String::from_utf8_lossy(
-                &r[*said_length as usize + 1..*said_length as usize + 1 + op_length],
-            )
-            .to_string();
+                .get(Namespace::OCA, &format!("oca.{}.operation", said))?
+                .ok_or_else(|| FetchError::not_found("history", said))?;
 
-            match serde_json::from_str::<oca_ast_semantics::ast::Command>(&op) {
-                Ok(command) => Ok((parent_said, command)),
-                Err(e) => Err(vec![format!("Failed to parse command: {}", e)]),
-            }
+            let record = Record::read(&r, Some(1))
+                .map_err(|e| StorageError::Corrupt(format!("History of {said}: {e}")))?;
+            let parent_said = record.text(0).unwrap_or_default();
+            let op = record.text(1).unwrap_or_default();
+
+            let command = decode("command", said, op.as_bytes())?;
+            Ok((parent_said, command))
         }
 
         let mut history: Vec<OCABuildStep> = vec![];
@@ -302,14 +430,14 @@ impl Facade {
         loop {
             let (parent_said, command) = extract_operation(&self.db, &said)?;
             if parent_said == said {
-                dbg!("Malformed history for said: {}", said);
-                return Err(vec![format!("Malformed history")]);
+                return Err(FetchError::MalformedHistory(said));
             }
-            let s = SelfAddressingIdentifier::from_str(&said).unwrap(); // TODO
+            let s = SelfAddressingIdentifier::from_str(&said)
+                .map_err(|e| StorageError::Corrupt(format!("Invalid said {said}: {e}")))?;
             history.push(OCABuildStep {
                 parent_said: parent_said.clone().parse().ok(),
                 command,
-                result: self.get_oca_bundle(s, false).unwrap().bundle.clone(),
+                result: self.get_oca_bundle(s, false)?.bundle,
             });
             said = parent_said;
 
@@ -327,7 +455,7 @@ impl Facade {
         &self,
         said: SelfAddressingIdentifier,
         dereference: bool,
-    ) -> Result<String, Vec<String>> {
+    ) -> Result<String, FetchError> {
         let oca_bundle_steps = self.get_oca_bundle_steps(said)?;
         let mut oca_ast = OCAAst::new();
         for step in oca_bundle_steps {
@@ -336,8 +464,13 @@ impl Facade {
 
         if dereference {
             #[cfg(feature = "local-references")]
-            local_references::replace_refn_with_refs(&mut oca_ast, &self.db)
-                .map_err(|e| vec![e.to_string()])?;
+            local_references::replace_refn_with_refs(&mut oca_ast, &self.db).map_err(
+                |e| match e {
+                    ValidationError::UnknownRefn(refn) => FetchError::not_found("reference", refn),
+                    ValidationError::Storage(e) => e.into(),
+                    e => StorageError::Corrupt(e.to_string()).into(),
+                },
+            )?;
         }
 
         Ok(oca_file_semantics::ocafile::generate_from_ast(&oca_ast))
@@ -345,10 +478,7 @@ impl Facade {
 
     /// Retrive steps (AST representation) for a given said
     ///
-    pub fn get_oca_bundle_ast(
-        &self,
-        said: SelfAddressingIdentifier,
-    ) -> Result<OCAAst, Vec<String>> {
+    pub fn get_oca_bundle_ast(&self, said: SelfAddressingIdentifier) -> Result<OCAAst, FetchError> {
         let oca_bundle_steps = self.get_oca_bundle_steps(said)?;
         let mut oca_ast = OCAAst::new();
         for step in oca_bundle_steps {
@@ -367,35 +497,24 @@ pub fn get_oca_bundle(
     storage: &dyn DataStorage,
     said: SelfAddressingIdentifier,
     with_dep: bool,
-) -> Result<BundleWithDependencies, Vec<String>> {
+) -> Result<BundleWithDependencies, FetchError> {
     let r = storage
-        .get(Namespace::OCABundlesJSON, &said.to_string())
-        .map_err(|e| vec![format!("{}", e)])?;
-    let oca_bundle_str = String::from_utf8(
-        r.ok_or_else(|| vec![format!("No OCA Bundle found for said: {}", said)])?,
-    )
-    .unwrap();
-    let oca_bundle: Result<OCABundle, Vec<String>> = serde_json::from_str(&oca_bundle_str)
-        .map_err(|e| vec![format!("Failed to parse oca bundle: {}", e)]);
-
-    match oca_bundle {
-        Ok(oca_bundle) => {
-            let mut dep_bundles = vec![];
-            if with_dep {
-                for refs in retrive_all_references(oca_bundle.clone()) {
-                    let dep_bundle = get_oca_bundle(storage, refs, true)?;
-                    dep_bundles.push(dep_bundle.bundle);
-                    dep_bundles.extend(dep_bundle.dependencies);
-                }
-            }
-            let result = BundleWithDependencies {
-                bundle: oca_bundle,
-                dependencies: dep_bundles,
-            };
-            Ok(result)
+        .get(Namespace::OCABundlesJSON, &said.to_string())?
+        .ok_or_else(|| FetchError::not_found("OCA bundle", &said))?;
+    let oca_bundle: OCABundle = decode("OCA bundle", &said.to_string(), &r)?;
+
+    let mut dep_bundles = vec![];
+    if with_dep {
+        for refs in retrive_all_references(oca_bundle.clone()) {
+            let dep_bundle = get_oca_bundle(storage, refs, true)?;
+            dep_bundles.push(dep_bundle.bundle);
+            dep_bundles.extend(dep_bundle.dependencies);
         }
-        Err(e) => Err(e),
     }
+    Ok(BundleWithDependencies {
+        bundle: oca_bundle,
+        dependencies: dep_bundles,
+    })
 }
 
 /// Retrive all existing references from given OCA Bundle
@@ -405,7 +524,7 @@ pub fn get_oca_bundle(
 ///
 /// # Return
 /// * `Vec<String>` - Vector of all SAID references
-fn retrive_all_references(bundle: OCABundle) -> Vec<SelfAddressingIdentifier> {
+pub(crate) fn retrive_all_references(bundle: OCABundle) -> Vec<SelfAddressingIdentifier> {
     let mut refs: Vec<SelfAddressingIdentifier> = vec![];
 
     for (_, value) in bundle.capture_base.attributes {
@@ -436,7 +555,8 @@ mod test {
         let db = InMemoryDataStorage::new();
         let db_cache = InMemoryDataStorage::new();
         let cache_storage_config = SQLiteConfig::build().unwrap();
-        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config);
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
         let ocafile_input = r#"
 ADD ATTRIBUTE d=Text i=Text passed=Boolean
 ADD META en PROPS description="Entrance credential" name="Entrance credential"
@@ -467,4 +587,123 @@ ADD ENTRY en ATTRS list="refs:ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI" el={
 
         Ok(())
     }
+
+    #[cfg(feature = "vc")]
+    #[test]
+    fn facade_validate_credential() -> Result<(), CredentialError> {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        let ocafile_input = r#"
+ADD ATTRIBUTE name=Text age=Numeric
+ADD CONFORMANCE ATTRS name="M"
+"#
+        .to_string();
+        let oca_bundle = facade.build_from_ocafile(ocafile_input).unwrap();
+
+        let mut credential = vc::credential_template(&oca_bundle);
+        credential["credentialSubject"] = serde_json::json!({ "name": "Alice", "age": 30 });
+        assert_eq!(
+            facade.validate_credential(&credential)?,
+            oca_bundle.said.unwrap()
+        );
+
+        credential["credentialSubject"] = serde_json::json!({ "age": "30" });
+        match facade.validate_credential(&credential) {
+            Err(CredentialError::Violations(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("expected violations, got {other:?}"),
+        }
+
+        credential["credentialSchema"] = serde_json::json!([]);
+        let error = facade.validate_credential(&credential).unwrap_err();
+        assert_eq!(error.code(), "invalid_credential");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "presentation")]
+    #[test]
+    fn facade_get_oca_bundle_form() -> Result<(), FetchError> {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        let address = facade
+            .build_from_ocafile("ADD ATTRIBUTE street=Text".to_string())
+            .unwrap();
+        let person = facade
+            .build_from_ocafile(format!(
+                "ADD ATTRIBUTE address=refs:{}",
+                address.said.unwrap()
+            ))
+            .unwrap();
+
+        let form = facade.get_oca_bundle_form(person.said.unwrap(), FormRenderer::new())?;
+        assert!(form.contains("name=\"address.street\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn facade_typed_errors() {
+        let db = InMemoryDataStorage::new();
+        let mut db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let said: SelfAddressingIdentifier = "EKmZWuURpiUdl_YAMGQbLiossAntKt1DJ0gmUMYSz7Yh"
+            .parse()
+            .unwrap();
+        db_cache
+            .insert(Namespace::OCABundlesJSON, &said.to_string(), b"{")
+            .unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+
+        let error = facade.get_oca_bundle(said.clone(), false).unwrap_err();
+        assert_eq!(error.code(), "corrupt");
+        let error = facade.get_oca_bundle_steps(said).unwrap_err();
+        assert_eq!(error.code(), "not_found");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "not_found",
+                "message": "No history found for EKmZWuURpiUdl_YAMGQbLiossAntKt1DJ0gmUMYSz7Yh"
+            })
+        );
+        let bundle = facade
+            .build_from_ocafile(
+                "ADD ATTRIBUTE name=Text
+"
+                .to_string(),
+            )
+            .unwrap();
+        assert!(facade.explore(bundle.said.unwrap().to_string()).is_ok());
+        assert_eq!(
+            facade.explore("unknown".to_string()).unwrap_err().code(),
+            "not_found"
+        );
+        let error = facade
+            .search_oca_bundle(None, "name".to_string(), 10, 0)
+            .unwrap_err();
+        assert_eq!(error.code(), "invalid_page");
+        assert_eq!(
+            facade.fetch_all_oca_bundle(10, 0).unwrap_err().code(),
+            "invalid_page"
+        );
+
+        let file = std::env::temp_dir().join(format!("oca-typed-errors-{}", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let cache_storage_config = SQLiteConfig::build().path(file.join("cache")).unwrap();
+        let error = Facade::new(
+            Box::new(InMemoryDataStorage::new()),
+            Box::new(InMemoryDataStorage::new()),
+            cache_storage_config,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error.code(), "io");
+        std::fs::remove_file(file).unwrap();
+    }
 }
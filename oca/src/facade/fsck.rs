@@ -0,0 +1,585 @@
+use super::archive::verify_bundle;
+use super::explore::Relationship;
+use super::Facade;
+use crate::data_storage::{Namespace, StorageError};
+use crate::repositories::{CaptureBaseCacheRepo, OCABundleCacheRepo, OCABundleFTSRepo};
+use oca_ast_semantics::ast;
+use oca_bundle_semantics::build::apply_command;
+use oca_bundle_semantics::state::oca::{capture_base::CaptureBase, DynOverlay, OCABox, OCABundle};
+use oca_dag_semantics::{record::Record, CommandModel};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use transformation_file::state::Transformation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsckIssueKind {
+    /// Record can not be parsed.
+    Unreadable,
+    /// Content does not match the SAID or digest it is stored under.
+    SaidMismatch,
+    /// History chain is interrupted or loops.
+    BrokenHistory,
+    /// Record refers to an object which is not stored.
+    MissingObject,
+    /// Related object does not relate back.
+    AsymmetricRelation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FsckIssue {
+    pub namespace: &'static str,
+    pub key: String,
+    pub kind: FsckIssueKind,
+    pub message: String,
+}
+
+impl FsckIssue {
+    fn new(
+        namespace: Namespace,
+        key: impl ToString,
+        kind: FsckIssueKind,
+        message: impl ToString,
+    ) -> Self {
+        Self {
+            namespace: namespace.as_str(),
+            key: key.to_string(),
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FsckReport {
+    /// Number of checked records.
+    pub checked: usize,
+    pub issues: Vec<FsckIssue>,
+}
+
+impl FsckReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn issue(
+        &mut self,
+        namespace: Namespace,
+        key: impl ToString,
+        kind: FsckIssueKind,
+        message: impl ToString,
+    ) {
+        self.issues
+            .push(FsckIssue::new(namespace, key, kind, message));
+    }
+}
+
+/// Result of [`Facade::reindex`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReindexReport {
+    /// Number of indexed bundles.
+    pub indexed: usize,
+    /// Bundles left out of the index as their history can't be replayed.
+    pub issues: Vec<FsckIssue>,
+}
+
+impl Facade {
+    /// Checks integrity of all stored records: re-parses bundles, capture
+    /// bases, overlays and transformations and recomputes their SAIDs,
+    /// follows history chains to their first step, checks command digests
+    /// of core models, relationships symmetry and references targets.
+    /// Failures of the storage itself abort the check, problems with the
+    /// records are reported.
+    pub fn fsck(&self) -> Result<FsckReport, StorageError> {
+        use FsckIssueKind::*;
+        let mut report = FsckReport::default();
+
+        let mut bundles = HashSet::new();
+        for (said, value) in self.db_cache.get_all(Namespace::OCABundlesJSON)? {
+            report.checked += 1;
+            match serde_json::from_slice::<OCABundle>(&value) {
+                Ok(bundle) => {
+                    if let Err(e) = verify_bundle(&said, &bundle) {
+                        report.issue(Namespace::OCABundlesJSON, &said, SaidMismatch, e);
+                    }
+                }
+                Err(e) => report.issue(Namespace::OCABundlesJSON, &said, Unreadable, e),
+            }
+            bundles.insert(said);
+        }
+
+        for (said, value) in self.db_cache.get_all(Namespace::OCAObjectsJSON)? {
+            report.checked += 1;
+            match object_said(&value) {
+                Ok(computed) if computed == said => {}
+                Ok(computed) => report.issue(
+                    Namespace::OCAObjectsJSON,
+                    &said,
+                    SaidMismatch,
+                    format!("Content has SAID {computed}"),
+                ),
+                Err(e) => report.issue(Namespace::OCAObjectsJSON, &said, Unreadable, e),
+            }
+        }
+
+        for (said, value) in self.db.get_all(Namespace::OCATransformationsJSON)? {
+            report.checked += 1;
+            match serde_json::from_slice::<Transformation>(&value) {
+                Ok(mut transformation) => {
+                    transformation.fill_said();
+                    let computed = transformation.said.map(|said| said.to_string());
+                    if computed.as_deref() != Some(said.as_str()) {
+                        report.issue(
+                            Namespace::OCATransformationsJSON,
+                            &said,
+                            SaidMismatch,
+                            format!("Content has SAID {}", computed.unwrap_or_default()),
+                        );
+                    }
+                }
+                Err(e) => report.issue(Namespace::OCATransformationsJSON, &said, Unreadable, e),
+            }
+        }
+
+        let mut parents: HashMap<String, String> = HashMap::new();
+        for (key, value) in self.db.get_all(Namespace::OCA)? {
+            let Some(said) = key
+                .strip_prefix("oca.")
+                .and_then(|key| key.strip_suffix(".operation"))
+            else {
+                continue;
+            };
+            report.checked += 1;
+            let record = match Record::read(&value, Some(1)) {
+                Ok(record) => record,
+                Err(e) => {
+                    report.issue(Namespace::OCA, &key, Unreadable, e);
+                    continue;
+                }
+            };
+            if let Err(e) =
+                serde_json::from_str::<ast::Command>(&record.text(1).unwrap_or_default())
+            {
+                report.issue(Namespace::OCA, &key, Unreadable, e);
+            }
+            if !bundles.contains(said) {
+                report.issue(Namespace::OCA, &key, MissingObject, "Bundle is not stored");
+            }
+            parents.insert(said.to_string(), record.text(0).unwrap_or_default());
+        }
+        // Chains known to reach their first step
+        let mut complete: HashSet<&str> = HashSet::new();
+        for said in parents.keys() {
+            let mut chain = vec![said.as_str()];
+            let mut current = said.as_str();
+            let broken = loop {
+                let parent = parents[current].as_str();
+                if parent.is_empty() || complete.contains(parent) {
+                    break None;
+                }
+                if chain.contains(&parent) {
+                    break Some(format!("History loops at {parent}"));
+                }
+                if !parents.contains_key(parent) {
+                    break Some(format!("History of {parent} is missing"));
+                }
+                chain.push(parent);
+                current = parent;
+            };
+            match broken {
+                Some(message) => report.issue(
+                    Namespace::OCA,
+                    format!("oca.{said}.operation"),
+                    BrokenHistory,
+                    message,
+                ),
+                None => complete.extend(chain),
+            }
+        }
+
+        for (key, value) in self.db.get_all(Namespace::CoreModel)? {
+            report.checked += 1;
+            let digest = key.strip_prefix("core_model.").unwrap_or(&key);
+            if value.starts_with(b"{") {
+                match serde_json::from_slice::<ast::Command>(&value) {
+                    Ok(command) => {
+                        let computed = CommandModel::calculate_command_digest(&command);
+                        if computed.to_string() != digest {
+                            report.issue(
+                                Namespace::CoreModel,
+                                &key,
+                                SaidMismatch,
+                                format!("Command has digest {computed}"),
+                            );
+                        }
+                    }
+                    Err(e) => report.issue(Namespace::CoreModel, &key, Unreadable, e),
+                }
+            } else if let Err(e) = Record::read(&value, None) {
+                report.issue(Namespace::CoreModel, &key, Unreadable, e);
+            }
+        }
+
+        let mut metadata = HashSet::new();
+        let mut relations: HashMap<String, HashSet<String>> = HashMap::new();
+        for (key, value) in self.db.get_all(Namespace::OCARelations)? {
+            report.checked += 1;
+            if let Some(said) = key.strip_suffix(".metadata") {
+                if value.len() == 1 {
+                    metadata.insert(said.to_string());
+                } else {
+                    report.issue(
+                        Namespace::OCARelations,
+                        &key,
+                        Unreadable,
+                        "Expected single object type byte",
+                    );
+                }
+                continue;
+            }
+            match Relationship::try_from(value) {
+                Ok(relationship) => {
+                    let related = relationship
+                        .relations
+                        .into_iter()
+                        .map(|object| object.said)
+                        .collect();
+                    relations.insert(key, related);
+                }
+                Err(e) => report.issue(Namespace::OCARelations, &key, Unreadable, e),
+            }
+        }
+        for said in relations.keys() {
+            if !metadata.contains(said) {
+                report.issue(
+                    Namespace::OCARelations,
+                    said,
+                    MissingObject,
+                    "Object metadata is missing",
+                );
+            }
+            let mut related: Vec<&String> = relations[said].iter().collect();
+            related.sort();
+            for other in related {
+                if !relations.get(other).is_some_and(|back| back.contains(said)) {
+                    report.issue(
+                        Namespace::OCARelations,
+                        said,
+                        AsymmetricRelation,
+                        format!("{other} does not relate back"),
+                    );
+                }
+            }
+        }
+
+        for (name, value) in self.db.get_all(Namespace::OCAReferences)? {
+            report.checked += 1;
+            match String::from_utf8(value) {
+                Ok(said) if bundles.contains(&said) => {}
+                Ok(said) => report.issue(
+                    Namespace::OCAReferences,
+                    &name,
+                    MissingObject,
+                    format!("Bundle {said} is not stored"),
+                ),
+                Err(e) => report.issue(Namespace::OCAReferences, &name, Unreadable, e),
+            }
+        }
+
+        report
+            .issues
+            .sort_by(|a, b| (a.namespace, &a.key).cmp(&(b.namespace, &b.key)));
+        Ok(report)
+    }
+
+    /// Rebuilds search index, bundle and capture base caches purely from
+    /// the primary storage, replaying build history of every bundle. As
+    /// after a build, only bundles registered in the relation graph are
+    /// indexed, not intermediate build steps. Bundles which history can't
+    /// be replayed are left out and reported. The index is replaced in a
+    /// single SQLite transaction, so a failure keeps the previous one.
+    pub fn reindex(&mut self) -> Result<ReindexReport, StorageError> {
+        let mut report = ReindexReport::default();
+        let mut rebuilt = HashMap::new();
+        let mut bundles = vec![];
+        let mut registered = vec![];
+        for key in self.db.list_keys(Namespace::OCARelations, "", 0, None)? {
+            // Relation graph holds capture bases and overlays too
+            let Some(said) = key.strip_suffix(".metadata") else {
+                continue;
+            };
+            let kind = self.db.get(Namespace::OCARelations, &key)?;
+            if let Some(&[kind]) = kind.as_deref() {
+                if matches!(kind.into(), ast::ObjectKind::OCABundle(_)) {
+                    registered.push(said.to_string());
+                }
+            }
+        }
+        for said in registered {
+            match self.replay_history(&said, &mut rebuilt)? {
+                Ok(bundle) => bundles.push(bundle),
+                Err(issue) => report.issues.push(issue),
+            }
+        }
+
+        let connection = self.connection();
+        connection.execute("BEGIN", ())?;
+        let index = || -> Result<(), StorageError> {
+            OCABundleFTSRepo::new(self.connection())?.clear()?;
+            OCABundleCacheRepo::new(self.connection())?.clear()?;
+            CaptureBaseCacheRepo::new(self.connection())?.clear()?;
+            for bundle in &bundles {
+                self.build_cache(bundle)?;
+                self.build_meta(bundle)?;
+            }
+            Ok(())
+        };
+        if let Err(e) = index() {
+            let _ = connection.execute("ROLLBACK", ());
+            return Err(e);
+        }
+        connection.execute("COMMIT", ())?;
+        report.indexed = bundles.len();
+        Ok(report)
+    }
+
+    /// Rebuilds the bundle by applying commands of its history from the
+    /// first step on. Bundles rebuilt on the way are kept in `rebuilt`.
+    fn replay_history(
+        &self,
+        said: &str,
+        rebuilt: &mut HashMap<String, OCABundle>,
+    ) -> Result<Result<OCABundle, FsckIssue>, StorageError> {
+        use FsckIssueKind::*;
+        let issue = |said: &str, kind, message: String| {
+            Ok(Err(FsckIssue::new(
+                Namespace::OCA,
+                format!("oca.{said}.operation"),
+                kind,
+                message,
+            )))
+        };
+
+        // Steps from the bundle back to the first or already rebuilt one
+        let mut steps: Vec<(String, ast::Command)> = vec![];
+        let mut current = said.to_string();
+        while !rebuilt.contains_key(&current) {
+            let Some(value) = self
+                .db
+                .get(Namespace::OCA, &format!("oca.{current}.operation"))?
+            else {
+                return issue(
+                    said,
+                    BrokenHistory,
+                    format!("History of {current} is missing"),
+                );
+            };
+            let record = match Record::read(&value, Some(1)) {
+                Ok(record) => record,
+                Err(e) => return issue(&current, Unreadable, e.to_string()),
+            };
+            let command = match serde_json::from_str(&record.text(1).unwrap_or_default()) {
+                Ok(command) => command,
+                Err(e) => return issue(&current, Unreadable, e.to_string()),
+            };
+            steps.push((current.clone(), command));
+            let parent = record.text(0).unwrap_or_default();
+            if parent.is_empty() {
+                break;
+            }
+            if steps.iter().any(|(step, _)| *step == parent) {
+                return issue(said, BrokenHistory, format!("History loops at {parent}"));
+            }
+            current = parent;
+        }
+
+        let mut bundle = rebuilt.get(&current).cloned();
+        for (step, command) in steps.into_iter().rev() {
+            let result = match apply_command(bundle.map(OCABox::from), command) {
+                Ok(mut oca_box) => oca_box.generate_bundle(),
+                Err(e) => return issue(&step, Unreadable, e.join(", ")),
+            };
+            let computed = result.said.as_ref().map(ToString::to_string);
+            if computed.as_deref() != Some(step.as_str()) {
+                return issue(
+                    &step,
+                    SaidMismatch,
+                    format!("Step results in {}", computed.unwrap_or_default()),
+                );
+            }
+            rebuilt.insert(step, result.clone());
+            bundle = Some(result);
+        }
+        Ok(Ok(bundle.expect("history has at least one step")))
+    }
+}
+
+/// Recomputes SAID of a stored capture base or overlay.
+fn object_said(value: &[u8]) -> Result<String, String> {
+    let json: serde_json::Value = serde_json::from_slice(value).map_err(|e| e.to_string())?;
+    let is_capture_base = json
+        .get("type")
+        .and_then(|object_type| object_type.as_str())
+        .is_some_and(|object_type| object_type.starts_with("spec/capture_base"));
+    let said = if is_capture_base {
+        let mut capture_base: CaptureBase =
+            serde_json::from_value(json).map_err(|e| e.to_string())?;
+        capture_base.fill_said();
+        capture_base.said
+    } else {
+        let mut overlay: DynOverlay = serde_json::from_value(json).map_err(|e| e.to_string())?;
+        overlay.fill_said();
+        overlay.said().clone()
+    };
+    Ok(said.map(|said| said.to_string()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_storage::{DataStorage, InMemoryDataStorage};
+    use crate::repositories::SQLiteConfig;
+
+    #[test]
+    fn facade_fsck_and_reindex() {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        let person = facade
+            .build_from_ocafile(
+                "ADD ATTRIBUTE name=Text\nADD META en PROPS name=\"Person\"\n".to_string(),
+            )
+            .unwrap();
+        let person_said = person.said.clone().unwrap();
+        let extended = facade
+            .build_from_ocafile(format!(
+                "FROM {person_said}\nADD ATTRIBUTE age=Numeric\nADD LABEL en ATTRS age=\"Age\"\n"
+            ))
+            .unwrap();
+        let report = facade.fsck().unwrap();
+        assert!(report.is_ok(), "{:?}", report.issues);
+        assert!(report.checked > 0);
+
+        OCABundleFTSRepo::new(facade.connection())
+            .unwrap()
+            .clear()
+            .unwrap();
+        OCABundleCacheRepo::new(facade.connection())
+            .unwrap()
+            .clear()
+            .unwrap();
+        let search = |facade: &Facade| {
+            facade
+                .search_oca_bundle(None, "Person".to_string(), 10, 1)
+                .unwrap()
+                .metadata
+                .total
+        };
+        assert_eq!(search(&facade), 0);
+        assert_eq!(
+            facade.reindex(),
+            Ok(ReindexReport {
+                indexed: 2,
+                issues: vec![]
+            })
+        );
+        assert_eq!(search(&facade), 2);
+        assert_eq!(
+            facade.fetch_all_oca_bundle(10, 1).unwrap().metadata.total,
+            2
+        );
+
+        let extended_said = extended.said.clone().unwrap().to_string();
+        facade
+            .db_cache
+            .insert(
+                Namespace::OCABundlesJSON,
+                &extended_said,
+                &facade
+                    .db_cache
+                    .get(Namespace::OCABundlesJSON, &person_said.to_string())
+                    .unwrap()
+                    .unwrap(),
+            )
+            .unwrap();
+        facade
+            .db_cache
+            .insert(Namespace::OCAObjectsJSON, "broken", b"{")
+            .unwrap();
+        // Index is rebuilt from history, not from stored bundles
+        assert_eq!(facade.reindex().unwrap().indexed, 2);
+        let cached = facade.fetch_all_oca_bundle(10, 1).unwrap().records;
+        assert!(cached.iter().any(|bundle| bundle.said == extended.said));
+        facade
+            .db
+            .delete(Namespace::OCA, &format!("oca.{person_said}.operation"))
+            .unwrap();
+        facade
+            .db
+            .insert(
+                Namespace::OCARelations,
+                &person.capture_base.said.clone().unwrap().to_string(),
+                &Record::new().encode(),
+            )
+            .unwrap();
+        facade
+            .db
+            .insert(Namespace::OCAReferences, "ghost", b"EMissing")
+            .unwrap();
+
+        let report = facade.fsck().unwrap();
+        let issues = |kind: FsckIssueKind| {
+            report
+                .issues
+                .iter()
+                .filter(|issue| issue.kind == kind)
+                .map(|issue| issue.key.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            issues(FsckIssueKind::SaidMismatch),
+            vec![extended_said.as_str()]
+        );
+        assert_eq!(issues(FsckIssueKind::Unreadable), vec!["broken"]);
+        assert!(issues(FsckIssueKind::BrokenHistory)
+            .contains(&format!("oca.{extended_said}.operation").as_str()));
+        assert_eq!(issues(FsckIssueKind::MissingObject), vec!["ghost"]);
+        assert!(!issues(FsckIssueKind::AsymmetricRelation).is_empty());
+        assert_eq!(
+            serde_json::to_value(&report.issues[0]).unwrap()["kind"],
+            serde_json::to_value(report.issues[0].kind).unwrap()
+        );
+
+        // Bundles of broken history are left out of the index and reported
+        let other = facade
+            .build_from_ocafile(
+                "ADD ATTRIBUTE id=Text\nADD META en PROPS name=\"Other\"\n".to_string(),
+            )
+            .unwrap();
+        let report = facade.reindex().unwrap();
+        assert_eq!(report.indexed, 1);
+        assert!(report
+            .issues
+            .iter()
+            .all(|issue| issue.kind == FsckIssueKind::BrokenHistory));
+        let mut keys = report
+            .issues
+            .iter()
+            .map(|issue| issue.key.clone())
+            .collect::<Vec<_>>();
+        keys.sort();
+        let mut expected = vec![
+            format!("oca.{person_said}.operation"),
+            format!("oca.{extended_said}.operation"),
+        ];
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert_eq!(search(&facade), 0);
+        assert_eq!(
+            facade.fetch_all_oca_bundle(10, 1).unwrap().records[0].said,
+            other.said
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use super::explore::Relationship;
+use super::Facade;
+use crate::data_storage::{Batch, Namespace, StorageError};
+use oca_dag_semantics::record::Record;
+
+impl Facade {
+    /// Rewrites history, core model and relationship records stored in the
+    /// legacy `u8` length prefixed format into the versioned one. Records
+    /// already versioned are left untouched, so it is safe to run again.
+    /// Returns the number of rewritten records.
+    pub fn migrate_records(&mut self) -> Result<usize, StorageError> {
+        let mut batch = Batch::new();
+        let mut count = 0;
+        let mut rewrite = |namespace: Namespace, key: &str, record: Record| {
+            batch.insert(namespace, key, &record.encode());
+            count += 1;
+        };
+
+        for (key, value) in self.db.get_all(Namespace::OCA)? {
+            if key.starts_with("oca.")
+                && key.ends_with(".operation")
+                && !Record::is_versioned(&value)
+            {
+                rewrite(Namespace::OCA, &key, legacy_record(&key, &value, Some(1))?);
+            }
+        }
+        for (key, value) in self.db.get_all(Namespace::CoreModel)? {
+            // Command models are stored as plain JSON
+            if !value.starts_with(b"{") && !Record::is_versioned(&value) {
+                rewrite(
+                    Namespace::CoreModel,
+                    &key,
+                    legacy_record(&key, &value, None)?,
+                );
+            }
+        }
+        for (key, value) in self.db.get_all(Namespace::OCARelations)? {
+            if !key.ends_with(".metadata") && !Record::is_versioned(&value) {
+                let relationship = Relationship::try_from(value)?;
+                batch.insert(Namespace::OCARelations, &key, &Vec::from(relationship));
+                count += 1;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.db.write_batch(batch)?;
+        }
+        Ok(count)
+    }
+}
+
+fn legacy_record(key: &str, value: &[u8], prefixed: Option<usize>) -> Result<Record, StorageError> {
+    Record::decode_legacy(value, prefixed)
+        .map_err(|e| StorageError::Corrupt(format!("Record {key}: {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_storage::{DataStorage, InMemoryDataStorage};
+    use crate::repositories::SQLiteConfig;
+
+    /// Encodes record the way it was stored before the versioned format.
+    fn legacy(record: &Record, prefixed: Option<usize>) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (i, field) in record.fields().iter().enumerate() {
+            if prefixed != Some(i) {
+                bytes.push(field.len() as u8);
+            }
+            bytes.extend(field);
+        }
+        bytes
+    }
+
+    #[test]
+    fn facade_migrate_records() {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        facade
+            .build_from_ocafile("ADD ATTRIBUTE name=Text\n".to_string())
+            .unwrap();
+        let said = facade
+            .build_from_ocafile(
+                "ADD ATTRIBUTE name=Text\nADD LABEL en ATTRS name=\"Name\"\n".to_string(),
+            )
+            .unwrap()
+            .said
+            .unwrap();
+        let steps = facade.get_oca_bundle_steps(said.clone()).unwrap();
+        let relations = facade.explore(said.to_string()).unwrap().relations;
+        assert_eq!(facade.migrate_records(), Ok(0));
+
+        let mut legacy_count = 0;
+        for namespace in [
+            Namespace::OCA,
+            Namespace::CoreModel,
+            Namespace::OCARelations,
+        ] {
+            for (key, value) in facade.db.get_all(namespace).unwrap() {
+                if !Record::is_versioned(&value) {
+                    continue;
+                }
+                let record = Record::decode(&value).unwrap();
+                let value = match namespace {
+                    Namespace::OCA => legacy(&record, Some(1)),
+                    Namespace::OCARelations => Relationship::try_from(value)
+                        .unwrap()
+                        .relations
+                        .into_iter()
+                        .flat_map(|object| {
+                            let object_type: u8 = object.object_type.into();
+                            [
+                                &[object_type, object.said.len() as u8],
+                                object.said.as_bytes(),
+                            ]
+                            .concat()
+                        })
+                        .collect(),
+                    _ => legacy(&record, None),
+                };
+                facade.db.insert(namespace, &key, &value).unwrap();
+                legacy_count += 1;
+            }
+        }
+        assert!(legacy_count > 0);
+        assert_eq!(
+            facade.get_oca_bundle_steps(said.clone()).unwrap().len(),
+            steps.len()
+        );
+
+        assert_eq!(facade.migrate_records(), Ok(legacy_count));
+        assert_eq!(facade.migrate_records(), Ok(0));
+        let migrated = facade.get_oca_bundle_steps(said.clone()).unwrap();
+        assert_eq!(
+            migrated
+                .iter()
+                .map(|step| step.parent_said.clone())
+                .collect::<Vec<_>>(),
+            steps
+                .iter()
+                .map(|step| step.parent_said.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            facade.explore(said.to_string()).unwrap().relations,
+            relations
+        );
+
+        facade
+            .db
+            .insert(Namespace::OCA, "oca.broken.operation", &[40, b'E'])
+            .unwrap();
+        assert_eq!(facade.migrate_records().unwrap_err().code(), "corrupt");
+    }
+}
@@ -1,14 +1,24 @@
 use rusqlite::Params;
 
-use crate::data_storage::DataStorage;
+use crate::data_storage::{DataStorage, StorageError};
 use crate::repositories::SQLiteConfig;
 use std::borrow::Borrow;
 use std::sync::{Arc, Mutex};
 
+mod archive;
+pub use archive::{ArchiveError, ArchiveManifest, ARCHIVE_VERSION};
 pub mod build;
 pub mod bundle;
 mod explore;
 mod fetch;
+mod fsck;
+pub use fsck::{FsckIssue, FsckIssueKind, FsckReport};
+mod migrate;
+pub mod sync;
+#[cfg(feature = "vc")]
+pub use fetch::CredentialError;
+pub use fetch::{FetchError, SearchError};
+mod transformation;
 pub use said::{derivation::HashFunctionCode, sad::SerializationFormats, version::Encode};
 
 #[derive(Clone)]
@@ -17,19 +27,19 @@ pub struct Connection {
 }
 
 impl Connection {
-    pub fn new(path: &str) -> Self {
-        let conn = rusqlite::Connection::open(path).unwrap();
-        Self {
+    pub fn new(path: &str) -> Result<Self, StorageError> {
+        let conn = rusqlite::Connection::open(path)?;
+        Ok(Self {
             connection: Arc::new(Mutex::new(conn)),
-        }
+        })
     }
 
-    pub fn execute<P>(&self, sql: &str, params: P) -> rusqlite::Result<usize>
+    pub fn execute<P>(&self, sql: &str, params: P) -> Result<usize, StorageError>
     where
         P: Params,
     {
-        let connection = self.connection.lock().unwrap();
-        connection.execute(sql, params)
+        let connection = self.connection.lock()?;
+        Ok(connection.execute(sql, params)?)
     }
 }
 
@@ -44,26 +54,14 @@ impl Facade {
         db: Box<dyn DataStorage>,
         db_cache: Box<dyn DataStorage>,
         cache_storage_config: SQLiteConfig,
-    ) -> Self {
-        let cache_path: String = match cache_storage_config.path {
-            Some(path) => {
-                if !path.try_exists().unwrap() {
-                    std::fs::create_dir_all(path.clone()).unwrap();
-                }
-                path.join("search_data.db")
-                    .clone()
-                    .into_os_string()
-                    .into_string()
-                    .unwrap()
-            }
-            None => ":memory:".to_string(),
-        };
+    ) -> Result<Self, StorageError> {
+        let cache_path = cache_storage_config.database_path()?;
 
-        Self {
+        Ok(Self {
             db,
             db_cache,
-            connection: Connection::new(&cache_path),
-        }
+            connection: Connection::new(&cache_path)?,
+        })
     }
 
     pub(crate) fn connection(&self) -> Connection {
@@ -0,0 +1,420 @@
+use super::archive::{ArchiveError, ArchiveManifest};
+use super::Facade;
+use crate::data_storage::{Namespace, StorageError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+#[cfg(feature = "http-sync")]
+use std::io::Read;
+
+/// Path of the endpoint returning [`Inventory`] of the repository.
+pub const INVENTORY_PATH: &str = "/sync/inventory";
+/// Path of the endpoint taking [`FetchRequest`] and returning an archive.
+pub const FETCH_PATH: &str = "/sync/fetch";
+/// Path of the endpoint importing an archive and returning its manifest.
+pub const PUSH_PATH: &str = "/sync/push";
+
+/// Bundles stored in a repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// Bundles registered by a build or an import.
+    pub registered: BTreeSet<String>,
+    /// All stored bundles, including intermediate build steps.
+    pub stored: BTreeSet<String>,
+}
+
+/// Asks for an archive of `wants` bundles, leaving out `haves` which the
+/// asking side already stores.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchRequest {
+    pub wants: Vec<String>,
+    pub haves: BTreeSet<String>,
+}
+
+/// Bundles registered on the receiving side by a push or a pull.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SyncReport {
+    pub bundles: Vec<String>,
+}
+
+impl SyncReport {
+    /// Bundles of the imported archive which were not registered before.
+    fn new(manifest: ArchiveManifest, registered: &BTreeSet<String>) -> Self {
+        let bundles = manifest
+            .bundles
+            .into_iter()
+            .chain(manifest.dependencies)
+            .filter(|said| !registered.contains(said))
+            .collect();
+        Self { bundles }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SyncError {
+    #[error("Remote repository is unreachable: {0}")]
+    Unreachable(String),
+    #[error("Remote repository rejected request ({code}): {message}")]
+    Rejected { code: String, message: String },
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl SyncError {
+    /// Stable identifier of the error kind, unlike the message it does not
+    /// change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Unreachable(_) => "unreachable",
+            Self::Rejected { .. } => "rejected",
+            Self::Archive(e) => e.code(),
+            Self::Storage(e) => e.code(),
+        }
+    }
+}
+
+impl Serialize for SyncError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::error::serialize(self.code(), self, serializer)
+    }
+}
+
+/// Other side of a push or a pull.
+pub trait Remote {
+    fn inventory(&self) -> Result<Inventory, SyncError>;
+    /// Archive of the requested bundles, see [`Facade::fetch_archive`].
+    fn fetch(&self, request: &FetchRequest) -> Result<Vec<u8>, SyncError>;
+    /// Imports the archive, see [`Facade::import_archive`].
+    fn receive(&mut self, archive: &[u8]) -> Result<ArchiveManifest, SyncError>;
+}
+
+impl Remote for Facade {
+    fn inventory(&self) -> Result<Inventory, SyncError> {
+        Ok(Facade::inventory(self)?)
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Vec<u8>, SyncError> {
+        Ok(self.fetch_archive(request)?)
+    }
+
+    fn receive(&mut self, archive: &[u8]) -> Result<ArchiveManifest, SyncError> {
+        Ok(self.import_archive(archive)?)
+    }
+}
+
+impl Facade {
+    pub fn inventory(&self) -> Result<Inventory, StorageError> {
+        let stored: BTreeSet<String> = self
+            .db_cache
+            .list_keys(Namespace::OCABundlesJSON, "", 0, None)?
+            .into_iter()
+            .collect();
+        let registered = self
+            .db
+            .list_keys(Namespace::OCARelations, "", 0, None)?
+            .into_iter()
+            .filter(|said| stored.contains(said))
+            .collect();
+        Ok(Inventory { registered, stored })
+    }
+
+    /// Serves [`FetchRequest`] of the other side with an archive, to be
+    /// imported with [`Facade::import_archive`].
+    pub fn fetch_archive(&self, request: &FetchRequest) -> Result<Vec<u8>, ArchiveError> {
+        let excluded: HashSet<String> = request.haves.iter().cloned().collect();
+        let mut archive = vec![];
+        self.export_archive(&request.wants, &excluded, &mut archive)?;
+        Ok(archive)
+    }
+
+    /// Fetches bundles registered in the remote repository and missing here,
+    /// together with their history and dependencies. Everything received is
+    /// verified before it is stored.
+    pub fn pull(&mut self, remote: &impl Remote) -> Result<SyncReport, SyncError> {
+        let local = Facade::inventory(self)?;
+        let remote_inventory = remote.inventory()?;
+        let wants: Vec<String> = remote_inventory
+            .registered
+            .difference(&local.registered)
+            .cloned()
+            .collect();
+        if wants.is_empty() {
+            return Ok(SyncReport::default());
+        }
+        let haves = local
+            .stored
+            .intersection(&remote_inventory.stored)
+            .cloned()
+            .collect();
+        let archive = remote.fetch(&FetchRequest { wants, haves })?;
+        let manifest = self.import_archive(archive.as_slice())?;
+        Ok(SyncReport::new(manifest, &local.registered))
+    }
+
+    /// Sends bundles registered here and missing in the remote repository,
+    /// together with their history and dependencies, which the remote side
+    /// verifies before storing.
+    pub fn push(&self, remote: &mut impl Remote) -> Result<SyncReport, SyncError> {
+        let local = Facade::inventory(self)?;
+        let remote_inventory = remote.inventory()?;
+        let wants: Vec<String> = local
+            .registered
+            .difference(&remote_inventory.registered)
+            .cloned()
+            .collect();
+        if wants.is_empty() {
+            return Ok(SyncReport::default());
+        }
+        let archive = self.fetch_archive(&FetchRequest {
+            wants,
+            haves: local
+                .stored
+                .intersection(&remote_inventory.stored)
+                .cloned()
+                .collect(),
+        })?;
+        let manifest = remote.receive(&archive)?;
+        Ok(SyncReport::new(manifest, &remote_inventory.registered))
+    }
+}
+
+/// Repository reachable over HTTP, serving [`INVENTORY_PATH`],
+/// [`FETCH_PATH`] and [`PUSH_PATH`] endpoints under the base URL. Failures
+/// are expected as `{"code": ..., "message": ...}` JSON.
+#[cfg(feature = "http-sync")]
+pub struct HttpRemote {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "http-sync")]
+impl HttpRemote {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn read(response: ureq::Response) -> Result<Vec<u8>, SyncError> {
+        let mut body = vec![];
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .map_err(|e| SyncError::Unreachable(e.to_string()))?;
+        Ok(body)
+    }
+
+    fn json<T: serde::de::DeserializeOwned>(
+        response: Result<ureq::Response, ureq::Error>,
+    ) -> Result<T, SyncError> {
+        let body = Self::read(response.map_err(Self::error)?)?;
+        serde_json::from_slice(&body).map_err(|e| SyncError::Rejected {
+            code: "invalid_response".to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    fn error(error: ureq::Error) -> SyncError {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            code: String,
+            message: String,
+        }
+
+        match error {
+            ureq::Error::Status(status, response) => {
+                match Self::read(response)
+                    .ok()
+                    .and_then(|body| serde_json::from_slice::<ErrorBody>(&body).ok())
+                {
+                    Some(body) => SyncError::Rejected {
+                        code: body.code,
+                        message: body.message,
+                    },
+                    None => SyncError::Rejected {
+                        code: status.to_string(),
+                        message: format!("HTTP status {status}"),
+                    },
+                }
+            }
+            ureq::Error::Transport(e) => SyncError::Unreachable(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "http-sync")]
+impl Remote for HttpRemote {
+    fn inventory(&self) -> Result<Inventory, SyncError> {
+        Self::json(self.agent.get(&self.url(INVENTORY_PATH)).call())
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Vec<u8>, SyncError> {
+        let request =
+            serde_json::to_vec(request).map_err(|e| StorageError::Encoding(e.to_string()))?;
+        let response = self
+            .agent
+            .post(&self.url(FETCH_PATH))
+            .set("Content-Type", "application/json")
+            .send_bytes(&request)
+            .map_err(Self::error)?;
+        Self::read(response)
+    }
+
+    fn receive(&mut self, archive: &[u8]) -> Result<ArchiveManifest, SyncError> {
+        Self::json(
+            self.agent
+                .post(&self.url(PUSH_PATH))
+                .set("Content-Type", "application/x-tar")
+                .send_bytes(archive),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_storage::{DataStorage, InMemoryDataStorage};
+    use crate::repositories::SQLiteConfig;
+
+    fn facade() -> Facade {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap()
+    }
+
+    fn build(facade: &mut Facade, ocafile: String) -> String {
+        facade
+            .build_from_ocafile(ocafile)
+            .unwrap()
+            .said
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn facade_pull_and_push() -> Result<(), SyncError> {
+        let mut source = facade();
+        let address = build(
+            &mut source,
+            "ADD ATTRIBUTE street=Text\nADD LABEL en ATTRS street=\"Street\"\n".to_string(),
+        );
+        let person = build(
+            &mut source,
+            format!("ADD ATTRIBUTE name=Text address=refs:{address}\n"),
+        );
+
+        let mut target = facade();
+        let report = target.pull(&source)?;
+        assert!(report.bundles.contains(&person));
+        assert!(report.bundles.contains(&address));
+        assert_eq!(
+            Facade::inventory(&target)?.registered,
+            Facade::inventory(&source)?.registered
+        );
+        assert_eq!(
+            target
+                .get_oca_bundle_steps(person.parse().unwrap())
+                .unwrap()
+                .len(),
+            source
+                .get_oca_bundle_steps(person.parse().unwrap())
+                .unwrap()
+                .len()
+        );
+        assert_eq!(target.pull(&source)?, SyncReport::default());
+        assert_eq!(target.push(&mut source)?, SyncReport::default());
+
+        let extended = build(
+            &mut target,
+            format!("FROM {person}\nADD ATTRIBUTE age=Numeric\n"),
+        );
+        let report = target.push(&mut source)?;
+        assert_eq!(report.bundles, vec![extended.clone()]);
+        assert!(source
+            .get_oca_bundle(extended.parse().unwrap(), false)
+            .is_ok());
+        assert_eq!(
+            Facade::inventory(&source)?.registered,
+            Facade::inventory(&target)?.registered
+        );
+        Ok(())
+    }
+
+    /// Serves the sync endpoints of the facade, the way a repository server
+    /// would.
+    #[cfg(feature = "http-sync")]
+    fn serve(mut facade: Facade) -> String {
+        use tiny_http::{Method, Response, Server};
+
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = vec![];
+                request.as_reader().read_to_end(&mut body).unwrap();
+                let result = match (request.method(), request.url()) {
+                    (Method::Get, INVENTORY_PATH) => Facade::inventory(&facade)
+                        .map_err(SyncError::from)
+                        .map(|inventory| serde_json::to_vec(&inventory).unwrap()),
+                    (Method::Post, FETCH_PATH) => {
+                        let fetch = serde_json::from_slice(&body).unwrap();
+                        facade.fetch_archive(&fetch).map_err(SyncError::from)
+                    }
+                    (Method::Post, PUSH_PATH) => facade
+                        .import_archive(body.as_slice())
+                        .map_err(SyncError::from)
+                        .map(|manifest| serde_json::to_vec(&manifest).unwrap()),
+                    _ => Err(SyncError::Rejected {
+                        code: "not_found".to_string(),
+                        message: request.url().to_string(),
+                    }),
+                };
+                let response = match result {
+                    Ok(body) => Response::from_data(body),
+                    Err(e) => {
+                        Response::from_data(serde_json::to_vec(&e).unwrap()).with_status_code(400)
+                    }
+                };
+                request.respond(response).unwrap();
+            }
+        });
+        url
+    }
+
+    #[cfg(feature = "http-sync")]
+    #[test]
+    fn facade_sync_over_http() -> Result<(), SyncError> {
+        let mut source = facade();
+        let person = build(&mut source, "ADD ATTRIBUTE name=Text\n".to_string());
+        let mut remote = HttpRemote::new(format!("{}/", serve(source)));
+
+        let mut target = facade();
+        assert_eq!(target.pull(&remote)?.bundles, vec![person.clone()]);
+        assert_eq!(target.pull(&remote)?, SyncReport::default());
+
+        let extended = build(
+            &mut target,
+            format!("FROM {person}\nADD ATTRIBUTE age=Numeric\n"),
+        );
+        assert_eq!(target.push(&mut remote)?.bundles, vec![extended.clone()]);
+        assert!(remote.inventory()?.registered.contains(&extended));
+
+        let rejected = remote.receive(b"not an archive").unwrap_err();
+        assert_eq!(rejected.code(), "rejected");
+        assert!(matches!(
+            rejected,
+            SyncError::Rejected { code, .. } if code == "malformed_archive"
+        ));
+
+        let unreachable = HttpRemote::new("http://127.0.0.1:1").inventory();
+        assert_eq!(unreachable.unwrap_err().code(), "unreachable");
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use super::fetch::{decode, FetchError};
+use super::Facade;
+use crate::data_storage::{Batch, Namespace, StorageError};
+use said::{
+    derivation::HashFunctionCode, sad::SerializationFormats, version::Encode,
+    SelfAddressingIdentifier,
+};
+use std::collections::{HashMap, VecDeque};
+use transformation_file::state::Transformation;
+
+impl Facade {
+    /// Stores transformation by its SAID, which is always computed, so that
+    /// the given one can't store it under the key of other transformation.
+    pub fn store_transformation(
+        &mut self,
+        transformation: &Transformation,
+    ) -> Result<String, StorageError> {
+        let mut batch = Batch::new();
+        let said = Self::stage_transformation(transformation, &mut batch)?;
+        self.db.write_batch(batch)?;
+        Ok(said)
+    }
+
+    /// Adds transformation, keyed by its computed SAID, to the batch of the
+    /// primary storage.
+    pub(crate) fn stage_transformation(
+        transformation: &Transformation,
+        batch: &mut Batch,
+    ) -> Result<String, StorageError> {
+        let mut transformation = transformation.clone();
+        transformation.fill_said();
+        let said = transformation.said.clone().unwrap().to_string();
+        let code = HashFunctionCode::Blake3_256;
+        let format = SerializationFormats::JSON;
+        let encoded = transformation
+            .encode(&code, &format)
+            .map_err(|e| StorageError::Encoding(e.to_string()))?;
+        batch.insert(Namespace::OCATransformationsJSON, &said, &encoded);
+        Ok(said)
+    }
+
+    pub fn get_transformation(
+        &self,
+        said: SelfAddressingIdentifier,
+    ) -> Result<Transformation, FetchError> {
+        let r = self
+            .db
+            .get(Namespace::OCATransformationsJSON, &said.to_string())?
+            .ok_or_else(|| FetchError::not_found("transformation", &said))?;
+        Ok(decode("transformation", &said.to_string(), &r)?)
+    }
+
+    /// Lists stored transformations from `source` and to `target` bundle,
+    /// or all of them when neither is given, ordered by SAID.
+    pub fn get_transformations(
+        &self,
+        source: Option<&str>,
+        target: Option<&str>,
+    ) -> Result<Vec<Transformation>, FetchError> {
+        let mut transformations = vec![];
+        for (said, value) in self.db.get_all(Namespace::OCATransformationsJSON)? {
+            let transformation: Transformation = decode("transformation", &said, &value)?;
+            if (source.is_none() || transformation.source.as_deref() == source)
+                && (target.is_none() || transformation.target.as_deref() == target)
+            {
+                transformations.push(transformation);
+            }
+        }
+        transformations.sort_by_key(|t| t.said.as_ref().map(ToString::to_string));
+        Ok(transformations)
+    }
+
+    /// Retrieve OCAfile the transformation was built from. For
+    /// transformations stored without one, it is generated.
+    pub fn get_transformation_ocafile(
+        &self,
+        said: SelfAddressingIdentifier,
+    ) -> Result<String, FetchError> {
+        match self
+            .db
+            .get(Namespace::OCA, &format!("transformation.{}.ocafile", said))?
+        {
+            Some(r) => String::from_utf8(r).map_err(|e| {
+                StorageError::Corrupt(format!("OCAfile of transformation {said}: {e}")).into()
+            }),
+            None => {
+                let transformation = self.get_transformation(said)?;
+                Ok(oca_file_transformation::ocafile::generate_from_ast(
+                    &transformation.to_ast(),
+                ))
+            }
+        }
+    }
+
+    /// Finds the shortest chain of stored transformations leading from
+    /// `source` to `target` bundle. Transformations which only rename
+    /// attributes are also followed backwards, inverted. The chain can be
+    /// folded into a single transformation with [`Transformation::compose`].
+    pub fn get_transformation_path(
+        &self,
+        source: &str,
+        target: &str,
+    ) -> Result<Vec<Transformation>, FetchError> {
+        let mut edges: HashMap<String, Vec<Transformation>> = HashMap::new();
+        for transformation in self.get_transformations(None, None)? {
+            let inverted = transformation.invert().ok();
+            for transformation in std::iter::once(transformation).chain(inverted) {
+                if let Some(from) = transformation.source.clone() {
+                    edges.entry(from).or_default().push(transformation);
+                }
+            }
+        }
+        // Stored order is not stable, so ties are broken by SAID
+        edges.values_mut().for_each(|transformations| {
+            transformations.sort_by_key(|t| t.said.as_ref().map(ToString::to_string))
+        });
+
+        let mut previous: HashMap<String, Transformation> = HashMap::new();
+        let mut queue = VecDeque::from([source.to_string()]);
+        while let Some(said) = queue.pop_front() {
+            if said == target {
+                let mut path = vec![];
+                let mut current = said;
+                while let Some(transformation) = previous.get(&current) {
+                    current = transformation.source.clone().unwrap();
+                    path.push(transformation.clone());
+                }
+                path.reverse();
+                return Ok(path);
+            }
+            for transformation in edges.get(&said).into_iter().flatten() {
+                let Some(next) = transformation.target.clone() else {
+                    continue;
+                };
+                if next != source && !previous.contains_key(&next) {
+                    previous.insert(next.clone(), transformation.clone());
+                    queue.push_back(next);
+                }
+            }
+        }
+        Err(FetchError::NoTransformationPath {
+            from: source.to_string(),
+            to: target.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_storage::{DataStorage, InMemoryDataStorage};
+    use crate::repositories::SQLiteConfig;
+
+    fn transformation(source: &str, target: &str, renames: &[(&str, &str)]) -> Transformation {
+        let mut transformation = Transformation::new();
+        transformation.set_source(source.to_string());
+        transformation.set_target(target.to_string());
+        transformation.rename(
+            renames
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        );
+        transformation
+    }
+
+    #[test]
+    fn facade_get_transformation_path() -> Result<(), FetchError> {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        facade.store_transformation(&transformation("v1", "v2", &[("surname", "last_name")]))?;
+        facade.store_transformation(&transformation("v2", "v3", &[("last_name", "name")]))?;
+        let mut v3_v4 = transformation("v3", "v4", &[]);
+        v3_v4.add_operation(transformation_file::state::Operation::Drop {
+            attributes: vec!["tmp".to_string()],
+        });
+        facade.store_transformation(&v3_v4)?;
+
+        let path = facade.get_transformation_path("v1", "v4")?;
+        assert_eq!(
+            path.iter()
+                .map(|t| t.target.clone().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["v2", "v3", "v4"]
+        );
+        let composed = path[1..]
+            .iter()
+            .try_fold(path[0].clone(), |composed, next| composed.compose(next))
+            .unwrap();
+        assert_eq!(composed.attributes["surname"], "name");
+        assert_eq!(composed.operations.len(), 1);
+
+        let path = facade.get_transformation_path("v3", "v1")?;
+        assert_eq!(path.len(), 2);
+        assert_eq!(path[0].attributes["name"], "last_name");
+        assert!(facade.get_transformation_path("v1", "v1")?.is_empty());
+        assert!(facade.get_transformation_path("v4", "v1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn facade_store_transformation_by_computed_said() -> Result<(), FetchError> {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        let mut stored = transformation("v1", "v2", &[("surname", "last_name")]);
+        stored.fill_said();
+        let stored_said = facade.store_transformation(&stored)?;
+        assert_eq!(stored_said, stored.said.clone().unwrap().to_string());
+
+        let mut forged = transformation("v1", "v2", &[("surname", "name")]);
+        forged.said = stored.said.clone();
+        let forged_said = facade.store_transformation(&forged)?;
+        assert_ne!(forged_said, stored_said);
+        assert_eq!(
+            facade
+                .get_transformation(stored_said.parse().unwrap())?
+                .attributes["surname"],
+            "last_name"
+        );
+        assert_eq!(
+            facade
+                .get_transformation(forged_said.parse().unwrap())?
+                .attributes["surname"],
+            "name"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn facade_build_transformation() -> Result<(), FetchError> {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade =
+            Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config).unwrap();
+        let source = facade
+            .build_from_ocafile("ADD ATTRIBUTE surname=Text\n".to_string())
+            .unwrap()
+            .said
+            .unwrap();
+        let target = facade
+            .build_from_ocafile("ADD ATTRIBUTE last_name=Text\n".to_string())
+            .unwrap()
+            .said
+            .unwrap();
+        let ocafile = format!(
+            "-- precompiler=transformation\n-- source=refs:{source}\n-- target=refs:{target}\nRENAME ATTRIBUTE surname=last_name\n"
+        );
+        let built = facade
+            .build_transformation_from_ocafile(ocafile.clone())
+            .unwrap();
+        let said = built.said.clone().unwrap();
+        let other = facade.store_transformation(&transformation(
+            "other",
+            &target.to_string(),
+            &[("name", "last_name")],
+        ))?;
+
+        assert_eq!(
+            facade.get_transformation(said.clone())?.attributes,
+            built.attributes
+        );
+        assert_eq!(facade.get_transformation_ocafile(said.clone())?, ocafile);
+        assert!(facade
+            .db
+            .get(Namespace::OCATransformationsJSON, &said.to_string())?
+            .is_some());
+        assert!(facade
+            .db_cache
+            .get_all(Namespace::OCATransformationsJSON)?
+            .is_empty());
+        assert_eq!(
+            facade.get_transformation_ocafile(other.parse().unwrap())?,
+            format!(
+                "-- precompiler=transformation\n-- source=refs:other\n-- target=refs:{target}\nRENAME ATTRIBUTE name=last_name\n"
+            )
+        );
+        let saids = |transformations: Vec<Transformation>| {
+            transformations
+                .into_iter()
+                .map(|t| t.said.unwrap())
+                .collect::<Vec<_>>()
+        };
+        let source = source.to_string();
+        let target = target.to_string();
+        assert_eq!(
+            saids(facade.get_transformations(Some(&source), None)?),
+            vec![said.clone()]
+        );
+        assert_eq!(
+            saids(facade.get_transformations(Some(&source), Some(&target))?),
+            vec![said.clone()]
+        );
+        assert_eq!(facade.get_transformations(None, Some(&target))?.len(), 2);
+        assert!(facade.get_transformations(Some(&target), None)?.is_empty());
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 pub mod data_storage;
+mod error;
 pub mod facade;
 pub mod repositories;
 pub use facade::Facade;
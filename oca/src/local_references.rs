@@ -5,11 +5,18 @@ use oca_ast_semantics::ast::{
 };
 use said::SelfAddressingIdentifier;
 
+use crate::data_storage::StorageError;
 use crate::facade::build::ValidationError;
 
 pub trait References {
-    fn find(&self, refn: &str) -> Option<String>;
-    fn save(&mut self, refn: &str, value: String);
+    fn find(&self, refn: &str) -> Result<Option<String>, StorageError>;
+    fn save(&mut self, refn: &str, value: String) -> Result<(), StorageError>;
+}
+
+fn parse_said(refn: &str, said: &str) -> Result<SelfAddressingIdentifier, ValidationError> {
+    SelfAddressingIdentifier::from_str(said).map_err(|e| {
+        ValidationError::Storage(StorageError::Corrupt(format!("Reference {refn}: {e}")))
+    })
 }
 
 // Iterate over all commands and dereference all attribute references
@@ -25,8 +32,8 @@ pub fn replace_refn_with_refs<R: References>(
                 for (_, attr_type) in attributes {
                     match attr_type {
                         NestedAttrType::Reference(RefValue::Name(refn)) => {
-                            if let Some(said) = references.find(refn) {
-                                let said = SelfAddressingIdentifier::from_str(&said).unwrap(); // todo
+                            if let Some(said) = references.find(refn)? {
+                                let said = parse_said(refn, &said)?;
                                 *attr_type = NestedAttrType::Reference(RefValue::Said(said));
                             } else {
                                 return Err(ValidationError::UnknownRefn(refn.clone()));
@@ -36,8 +43,8 @@ pub fn replace_refn_with_refs<R: References>(
                             if let NestedAttrType::Reference(RefValue::Name(refn)) =
                                 &**box_attr_type
                             {
-                                if let Some(said) = references.find(refn) {
-                                    let said = SelfAddressingIdentifier::from_str(&said).unwrap(); // todo
+                                if let Some(said) = references.find(refn)? {
+                                    let said = parse_said(refn, &said)?;
                                     **box_attr_type =
                                         NestedAttrType::Reference(RefValue::Said(said));
                                 } else {
@@ -57,8 +64,8 @@ pub fn replace_refn_with_refs<R: References>(
             if let Some(properties) = &mut content.properties {
                 if let Some(NestedValue::Reference(RefValue::Name(refn))) = properties.get("target")
                 {
-                    if let Some(said) = references.find(refn) {
-                        let said = SelfAddressingIdentifier::from_str(&said).unwrap(); // todo
+                    if let Some(said) = references.find(refn)? {
+                        let said = parse_said(refn, &said)?;
                         properties.insert(
                             "target".to_string(),
                             NestedValue::Reference(RefValue::Said(said)),
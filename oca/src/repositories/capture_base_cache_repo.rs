@@ -1,6 +1,6 @@
 use oca_bundle_semantics::state::oca::capture_base::CaptureBase;
 
-use crate::facade::Connection;
+use crate::{data_storage::StorageError, facade::Connection};
 
 #[derive(Debug)]
 pub struct CaptureBaseCacheRecord {
@@ -28,28 +28,39 @@ pub struct CaptureBaseCacheRepo {
 }
 
 impl CaptureBaseCacheRepo {
-    pub fn new(connection: Connection) -> Self {
+    pub fn new(connection: Connection) -> Result<Self, StorageError> {
         let create_table_query = r#"
         CREATE TABLE IF NOT EXISTS capture_base_cache(
             said TEXT PRIMARY KEY,
             capture_base TEXT
         )"#;
-        let _ = connection.execute(create_table_query, ());
+        connection.execute(create_table_query, ())?;
 
-        Self { connection }
+        Ok(Self { connection })
     }
 
-    pub fn insert(&self, model: CaptureBaseCacheRecord) {
+    /// Records are content addressed, so the one already cached is kept.
+    pub fn insert(&self, model: CaptureBaseCacheRecord) -> Result<(), StorageError> {
         let query = r#"
-        INSERT INTO capture_base_cache(said, capture_base)
+        INSERT OR IGNORE INTO capture_base_cache(said, capture_base)
             VALUES (?1, ?2)"#;
-        let _ = self
-            .connection
-            .execute(query, [&model.said, &model.capture_base]);
+        self.connection
+            .execute(query, [&model.said, &model.capture_base])?;
+        Ok(())
     }
 
-    pub fn fetch_all(&self, limit: usize, page: usize) -> Vec<AllCaptureBaseRecord> {
-        let offset = (page - 1) * limit;
+    pub fn clear(&self) -> Result<(), StorageError> {
+        self.connection
+            .execute("DELETE FROM capture_base_cache", ())?;
+        Ok(())
+    }
+
+    pub fn fetch_all(
+        &self,
+        limit: usize,
+        page: usize,
+    ) -> Result<Vec<AllCaptureBaseRecord>, StorageError> {
+        let offset = page.saturating_sub(1) * limit;
         let mut results = vec![];
         let query = "
         SELECT results.*, count.total
@@ -67,25 +78,25 @@ impl CaptureBaseCacheRepo {
         ON true
         GROUP BY said";
 
-        let connection = self.connection.connection.lock().unwrap();
-        let mut statement = connection.prepare(query).unwrap();
+        let connection = self.connection.connection.lock()?;
+        let mut statement = connection.prepare(query)?;
 
-        let models = statement
-            .query_map([limit, offset], |row| {
-                let cache_record =
-                    row.get::<_, Option<String>>(0)
-                        .unwrap()
-                        .map(|said| CaptureBaseCacheRecord {
-                            said,
-                            capture_base: row.get(1).unwrap(),
-                        });
-                Ok(AllCaptureBaseRecord {
-                    cache_record,
-                    total: row.get(2).unwrap(),
-                })
+        let models = statement.query_map([limit, offset], |row| {
+            let cache_record = match row.get::<_, Option<String>>(0)? {
+                Some(said) => Some(CaptureBaseCacheRecord {
+                    said,
+                    capture_base: row.get(1)?,
+                }),
+                None => None,
+            };
+            Ok(AllCaptureBaseRecord {
+                cache_record,
+                total: row.get(2)?,
             })
-            .unwrap();
-        models.for_each(|model| results.push(model.unwrap()));
-        results
+        })?;
+        for model in models {
+            results.push(model?);
+        }
+        Ok(results)
     }
 }
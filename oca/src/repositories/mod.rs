@@ -5,6 +5,7 @@ pub use oca_bundle_fts_repo::*;
 pub mod oca_bundle_cache_repo;
 pub use oca_bundle_cache_repo::*;
 
+use crate::data_storage::StorageError;
 use std::path::PathBuf;
 
 #[derive(Clone)]
@@ -16,6 +17,24 @@ impl SQLiteConfig {
     pub fn build() -> SQLiteConfigBuilder {
         SQLiteConfigBuilder { path: None }
     }
+
+    /// Path of the search index database, `search_data.db` in the
+    /// configured directory, which is created when missing. Without
+    /// directory the database is kept in memory.
+    pub fn database_path(&self) -> Result<String, StorageError> {
+        match &self.path {
+            Some(path) => {
+                if !path.try_exists()? {
+                    std::fs::create_dir_all(path.clone())?;
+                }
+                path.join("search_data.db")
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|path| StorageError::Io(format!("Invalid path: {:?}", path)))
+            }
+            None => Ok(":memory:".to_string()),
+        }
+    }
 }
 
 pub struct SQLiteConfigBuilder {
@@ -1,7 +1,7 @@
 use oca_bundle_semantics::{state::oca::OCABundle, Encode};
 use said::{derivation::HashFunctionCode, sad::SerializationFormats};
 
-use crate::facade::Connection;
+use crate::{data_storage::StorageError, facade::Connection};
 
 #[derive(Debug)]
 pub struct OCABundleCacheRecord {
@@ -31,28 +31,39 @@ pub struct OCABundleCacheRepo {
 }
 
 impl OCABundleCacheRepo {
-    pub fn new(connection: Connection) -> Self {
+    pub fn new(connection: Connection) -> Result<Self, StorageError> {
         let create_table_query = r#"
         CREATE TABLE IF NOT EXISTS oca_bundle_cache(
             said TEXT PRIMARY KEY,
             oca_bundle TEXT
         )"#;
-        connection.execute(create_table_query, ()).unwrap();
+        connection.execute(create_table_query, ())?;
 
-        Self { connection }
+        Ok(Self { connection })
     }
 
-    pub fn insert(&self, model: OCABundleCacheRecord) {
+    /// Records are content addressed, so the one already cached is kept.
+    pub fn insert(&self, model: OCABundleCacheRecord) -> Result<(), StorageError> {
         let query = r#"
-        INSERT INTO oca_bundle_cache(said, oca_bundle)
+        INSERT OR IGNORE INTO oca_bundle_cache(said, oca_bundle)
             VALUES (?1, ?2)"#;
-        let _ = self
-            .connection
-            .execute(query, [&model.said, &model.oca_bundle]);
+        self.connection
+            .execute(query, [&model.said, &model.oca_bundle])?;
+        Ok(())
     }
 
-    pub fn fetch_all(&self, limit: usize, page: usize) -> Vec<AllOCABundleRecord> {
-        let offset = (page - 1) * limit;
+    pub fn clear(&self) -> Result<(), StorageError> {
+        self.connection
+            .execute("DELETE FROM oca_bundle_cache", ())?;
+        Ok(())
+    }
+
+    pub fn fetch_all(
+        &self,
+        limit: usize,
+        page: usize,
+    ) -> Result<Vec<AllOCABundleRecord>, StorageError> {
+        let offset = page.saturating_sub(1) * limit;
         let mut results = vec![];
         let query = "
         SELECT results.*, count.total
@@ -70,24 +81,24 @@ impl OCABundleCacheRepo {
         ON true
         GROUP BY said";
 
-        let connection = self.connection.connection.lock().unwrap();
-        let mut statement = connection.prepare(query).unwrap();
-        let models = statement
-            .query_map([limit, offset], |row| {
-                let cache_record =
-                    row.get::<_, Option<String>>(0)
-                        .unwrap()
-                        .map(|said| OCABundleCacheRecord {
-                            said,
-                            oca_bundle: row.get(1).unwrap(),
-                        });
-                Ok(AllOCABundleRecord {
-                    cache_record,
-                    total: row.get(2).unwrap(),
-                })
+        let connection = self.connection.connection.lock()?;
+        let mut statement = connection.prepare(query)?;
+        let models = statement.query_map([limit, offset], |row| {
+            let cache_record = match row.get::<_, Option<String>>(0)? {
+                Some(said) => Some(OCABundleCacheRecord {
+                    said,
+                    oca_bundle: row.get(1)?,
+                }),
+                None => None,
+            };
+            Ok(AllOCABundleRecord {
+                cache_record,
+                total: row.get(2)?,
             })
-            .unwrap();
-        models.for_each(|model| results.push(model.unwrap()));
-        results
+        })?;
+        for model in models {
+            results.push(model?);
+        }
+        Ok(results)
     }
 }
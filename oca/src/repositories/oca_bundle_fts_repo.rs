@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use said::SelfAddressingIdentifier;
 
-use crate::facade::Connection;
+use crate::{data_storage::StorageError, facade::Connection};
 
 #[derive(Debug)]
 pub struct OCABundleFTSRecord {
@@ -33,7 +33,7 @@ pub struct OCABundleFTSRepo {
 }
 
 impl OCABundleFTSRepo {
-    pub fn new(connection: Connection) -> Self {
+    pub fn new(connection: Connection) -> Result<Self, StorageError> {
         let create_table_query = r#"
         CREATE VIRTUAL TABLE IF NOT EXISTS oca_bundle_fts
         USING FTS5(
@@ -43,14 +43,14 @@ impl OCABundleFTSRepo {
             oca_bundle_said UNINDEXED,
             tokenize="trigram"
         )"#;
-        connection.execute(create_table_query, ()).unwrap();
+        connection.execute(create_table_query, ())?;
 
-        Self { connection }
+        Ok(Self { connection })
     }
 
-    pub fn insert(&self, model: OCABundleFTSRecord) {
+    pub fn insert(&self, model: OCABundleFTSRecord) -> Result<(), StorageError> {
         let query = r#"
-        INSERT INTO oca_bundle_fts
+        INSERT OR REPLACE INTO oca_bundle_fts
         (rowid, name, description, language_code, oca_bundle_said)
         VALUES (
             (
@@ -59,7 +59,7 @@ impl OCABundleFTSRepo {
                 LIMIT 1
             ), ?1, ?2, ?3, ?4
         )"#;
-        let _ = self.connection.execute(
+        self.connection.execute(
             query,
             [
                 &model.name,
@@ -67,7 +67,13 @@ impl OCABundleFTSRepo {
                 &model.language_code,
                 &model.oca_bundle_said,
             ],
-        );
+        )?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), StorageError> {
+        self.connection.execute("DELETE FROM oca_bundle_fts", ())?;
+        Ok(())
     }
 
     pub fn search(
@@ -76,8 +82,8 @@ impl OCABundleFTSRepo {
         meta_query: String,
         limit: usize,
         page: usize,
-    ) -> SearchResult {
-        let offset = (page - 1) * limit;
+    ) -> Result<SearchResult, StorageError> {
+        let offset = page.saturating_sub(1) * limit;
         let query = match language {
             Some(lang) => {
                 let lang_code = isolang::Language::to_639_3(&lang).to_string();
@@ -147,7 +153,11 @@ impl OCABundleFTSRepo {
                     }
                 }
                 snippet_regex = v.join("...");
-                let re = regex::Regex::new(&format!("(?m)^([^:]+):{snippet_regex:}$")).unwrap();
+                // Snippet of a phrase with regex special characters is no
+                // valid pattern, the scope is unknown then
+                let Ok(re) = regex::Regex::new(&format!("(?m)^([^:]+):{snippet_regex:}$")) else {
+                    return String::new();
+                };
                 let hay = format!(
                     "\
 meta_overlay:{}
@@ -164,36 +174,36 @@ meta_overlay:{}
             }
         }
 
-        let connection = self.connection.connection.lock().unwrap();
-        let mut statement = connection.prepare(sql_query).unwrap();
-
-        let rows = statement
-            .query_map(
-                [query.clone(), limit.to_string(), offset.to_string()],
-                |row| {
-                    Ok(Record {
-                        name: row.get(0).unwrap(),
-                        description: row.get(1).unwrap(),
-                        oca_bundle_said: row.get(3).unwrap(),
-                        rank: row.get(4).unwrap(),
-                        total: row.get(7).unwrap(),
-                        snippet: row.get(5).unwrap(),
-                    })
-                },
-            )
-            .unwrap();
+        let connection = self.connection.connection.lock()?;
+        let mut statement = connection.prepare(sql_query)?;
+
+        let rows = statement.query_map(
+            [query.clone(), limit.to_string(), offset.to_string()],
+            |row| {
+                Ok(Record {
+                    name: row.get(0)?,
+                    description: row.get(1)?,
+                    oca_bundle_said: row.get(3)?,
+                    rank: row.get(4)?,
+                    total: row.get(7)?,
+                    snippet: row.get(5)?,
+                })
+            },
+        )?;
 
         let mut records = vec![];
         let mut total: usize = 0;
 
         for row in rows {
-            let record = row.unwrap();
+            let record = row?;
             if total == 0 {
                 total = record.total as usize;
             }
-            if record.oca_bundle_said.is_none() {
+            let Some(oca_bundle_said) = &record.oca_bundle_said else {
                 continue;
-            }
+            };
+            let oca_bundle_said = SelfAddressingIdentifier::from_str(oca_bundle_said)
+                .map_err(|e| StorageError::Corrupt(format!("{oca_bundle_said}: {e}")))?;
             let metdata = SearchRecordMetadata {
                 phrase: record.snippet.clone().unwrap(),
                 scope: record.get_scope().clone(),
@@ -201,18 +211,15 @@ meta_overlay:{}
             };
 
             records.push(SearchRecord {
-                oca_bundle_said: SelfAddressingIdentifier::from_str(
-                    &record.oca_bundle_said.unwrap(),
-                )
-                .unwrap(), //TODO
+                oca_bundle_said,
                 metadata: metdata,
             });
         }
 
-        SearchResult {
+        Ok(SearchResult {
             records,
             metadata: SearchMetadata { total, page },
-        }
+        })
     }
 }
 
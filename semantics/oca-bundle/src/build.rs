@@ -152,6 +152,14 @@ pub fn apply_command(base: Option<OCABox>, op: ast::Command) -> Result<OCABox, V
                     }
                 }
             }
+            if let Some(ref flagged_attributes) = content.flagged_attributes {
+                for attr_name in flagged_attributes {
+                    match oca.attributes.get_mut(attr_name) {
+                        Some(attribute) => attribute.set_flagged(),
+                        None => errors.push(format!("Undefined attribute: {attr_name}")),
+                    }
+                }
+            }
         }
         (ast::CommandType::Add, ast::ObjectKind::Overlay(overlay_type, content)) => {
             match overlay_type {
@@ -742,6 +750,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn flag_capture_base_attributes() {
+        let capture_base = |flagged: &[&str]| {
+            let mut attributes = IndexMap::new();
+            attributes.insert(
+                "name".to_string(),
+                ast::NestedAttrType::Value(AttributeType::Text),
+            );
+            attributes.insert(
+                "age".to_string(),
+                ast::NestedAttrType::Value(AttributeType::Numeric),
+            );
+            ast::Command {
+                kind: ast::CommandType::Add,
+                object_kind: ast::ObjectKind::CaptureBase(CaptureContent {
+                    attributes: Some(attributes),
+                    properties: None,
+                    flagged_attributes: Some(flagged.iter().map(|s| s.to_string()).collect()),
+                }),
+            }
+        };
+
+        let mut oca = apply_command(None, capture_base(&["name"])).unwrap();
+        assert!(oca.attributes.get("name").unwrap().is_flagged);
+        assert!(!oca.attributes.get("age").unwrap().is_flagged);
+        let bundle = oca.generate_bundle();
+        assert_eq!(bundle.capture_base.flagged_attributes, vec!["name"]);
+
+        match apply_command(None, capture_base(&["name", "unknown"])) {
+            Ok(_) => panic!("flagging an undefined attribute must fail"),
+            Err(errors) => assert_eq!(errors, vec!["Undefined attribute: unknown".to_string()]),
+        }
+    }
+
     #[test]
     fn build_from_ast() {
         let mut commands = vec![];
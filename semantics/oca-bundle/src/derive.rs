@@ -0,0 +1,138 @@
+//! Building OCA bundles out of Rust types. With `derive` feature, structs
+//! implement [`Oca`] with `#[derive(Oca)]`.
+use crate::state::oca::{OCABox, OCABundle};
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType};
+use said::SelfAddressingIdentifier;
+
+#[cfg(feature = "derive")]
+pub use oca_derive_semantics::Oca;
+
+/// Type described by OCA bundle.
+pub trait Oca {
+    fn oca_box() -> OCABox;
+
+    fn oca_bundle() -> OCABundle {
+        Self::oca_box().generate_bundle()
+    }
+
+    fn oca_said() -> SelfAddressingIdentifier {
+        Self::oca_bundle().said.expect("generated bundle has SAID")
+    }
+
+    /// Bundles of the types referenced by the bundle, directly or not.
+    fn oca_dependencies() -> Vec<OCABundle> {
+        vec![]
+    }
+}
+
+/// Mapping of Rust type onto OCA attribute type.
+pub trait OcaType {
+    fn attribute_type() -> NestedAttrType;
+
+    /// Bundles referenced by the attribute type.
+    fn dependencies() -> Vec<OCABundle> {
+        vec![]
+    }
+}
+
+macro_rules! oca_type {
+    ($attribute_type:ident: $($ty:ty),*) => {
+        $(
+            impl OcaType for $ty {
+                fn attribute_type() -> NestedAttrType {
+                    NestedAttrType::Value(AttributeType::$attribute_type)
+                }
+            }
+        )*
+    };
+}
+
+oca_type!(Text: String, str, char);
+oca_type!(Boolean: bool);
+oca_type!(Numeric: i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl<T: OcaType + ?Sized> OcaType for &T {
+    fn attribute_type() -> NestedAttrType {
+        T::attribute_type()
+    }
+
+    fn dependencies() -> Vec<OCABundle> {
+        T::dependencies()
+    }
+}
+
+impl<T: OcaType + ?Sized> OcaType for Box<T> {
+    fn attribute_type() -> NestedAttrType {
+        T::attribute_type()
+    }
+
+    fn dependencies() -> Vec<OCABundle> {
+        T::dependencies()
+    }
+}
+
+impl<T: OcaType> OcaType for Option<T> {
+    fn attribute_type() -> NestedAttrType {
+        T::attribute_type()
+    }
+
+    fn dependencies() -> Vec<OCABundle> {
+        T::dependencies()
+    }
+}
+
+impl<T: OcaType> OcaType for Vec<T> {
+    fn attribute_type() -> NestedAttrType {
+        NestedAttrType::Array(Box::new(T::attribute_type()))
+    }
+
+    fn dependencies() -> Vec<OCABundle> {
+        T::dependencies()
+    }
+}
+
+impl<T: OcaType> OcaType for [T] {
+    fn attribute_type() -> NestedAttrType {
+        NestedAttrType::Array(Box::new(T::attribute_type()))
+    }
+
+    fn dependencies() -> Vec<OCABundle> {
+        T::dependencies()
+    }
+}
+
+/// Items used by code generated with `#[derive(Oca)]`.
+#[doc(hidden)]
+pub mod __private {
+    pub use super::OcaType;
+    pub use crate::state::{
+        attribute::Attribute,
+        entry_codes::EntryCodes,
+        oca::{
+            overlay::{
+                cardinality::Cardinalitys, conformance::Conformances,
+                entry_code::EntryCodes as EntryCodesOverlay, information::Information,
+                label::Labels, meta::Metas, unit::Units,
+            },
+            OCABox, OCABundle,
+        },
+    };
+    pub use isolang::Language;
+    pub use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+    pub use serde_json;
+
+    /// Language of ISO 639-1 code, validated by the macro.
+    pub fn language(code: &str) -> Language {
+        Language::from_639_1(code).expect("valid ISO 639-1 code")
+    }
+
+    pub fn dedup(bundles: Vec<OCABundle>) -> Vec<OCABundle> {
+        let mut unique: Vec<OCABundle> = vec![];
+        for bundle in bundles {
+            if !unique.iter().any(|b| b.said == bundle.said) {
+                unique.push(bundle);
+            }
+        }
+        unique
+    }
+}
@@ -4,6 +4,7 @@ extern crate cascade;
 
 pub mod build;
 pub mod controller;
+pub mod derive;
 mod io;
 pub mod state;
 
@@ -3,5 +3,6 @@ pub mod encoding;
 pub mod entries;
 pub mod entry_codes;
 pub mod oca;
+pub mod record;
 pub mod standard;
 pub mod validator;
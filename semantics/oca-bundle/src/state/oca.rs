@@ -641,10 +641,10 @@ where
             while let Some((_, value)) = map.next_entry::<String, serde_value::Value>()? {
                 if let serde_value::Value::Seq(ov) = value {
                     for o in ov {
-                        overlays.push(o.deserialize_into().unwrap());
+                        overlays.push(o.deserialize_into().map_err(serde::de::Error::custom)?);
                     }
                 } else if let serde_value::Value::Map(_) = value {
-                    overlays.push(value.deserialize_into().unwrap());
+                    overlays.push(value.deserialize_into().map_err(serde::de::Error::custom)?);
                 }
             }
 
@@ -918,6 +918,19 @@ impl From<OCABundle> for OCABox {
 }
 
 impl OCABundle {
+    /// Languages of all language specific overlays, sorted by ISO 639-3
+    /// code.
+    pub fn languages(&self) -> Vec<Language> {
+        let mut languages = self
+            .overlays
+            .iter()
+            .filter_map(|overlay| overlay.language().copied())
+            .collect::<Vec<_>>();
+        languages.sort_by_key(|language| language.to_639_3());
+        languages.dedup();
+        languages
+    }
+
     pub fn fill_said(&mut self) {
         let code = HashFunctionCode::Blake3_256;
         let format = SerializationFormats::JSON;
@@ -1294,6 +1307,15 @@ impl OCABundle {
     }
 }
 
+/// ISO 639-1 code of the language, as used in OCAfile, or ISO 639-3 code
+/// when the language has no 639-1 code.
+pub fn language_code(language: Language) -> String {
+    language
+        .to_639_1()
+        .unwrap_or_else(|| language.to_639_3())
+        .to_string()
+}
+
 /*
 #[derive(Clone)]
 struct AttributeLayoutValues {
@@ -0,0 +1,632 @@
+use crate::state::{
+    attribute::Attribute,
+    entry_codes::EntryCodes,
+    oca::{overlay::conditional::Conditionals, OCABox, OCABundle},
+};
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, RefValue};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+/// Error found while validating a data record against an OCA bundle.
+/// `path` points to the offending value, e.g. `address.street` or `phones[1]`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Error {
+    #[error("{path}: missing mandatory attribute")]
+    MissingAttribute { path: String },
+    #[error("{path}: unknown attribute")]
+    UnknownAttribute { path: String },
+    #[error("{path}: expected {expected}")]
+    InvalidType { path: String, expected: String },
+    #[error("{path}: `{value}` is not one of the entry codes")]
+    InvalidEntryCode { path: String, value: String },
+    #[error("{path}: value does not match format `{format}`")]
+    FormatMismatch { path: String, format: String },
+    #[error("{path}: value {value} is out of range {range}")]
+    OutOfRange {
+        path: String,
+        value: String,
+        range: String,
+    },
+    #[error("{path}: expected {cardinality} elements, found {count}")]
+    Cardinality {
+        path: String,
+        cardinality: String,
+        count: usize,
+    },
+    #[error("{path}: attribute is not applicable, condition `{condition}` is not met")]
+    NotApplicable { path: String, condition: String },
+    #[error("{path}: cannot evaluate condition: {message}")]
+    Condition { path: String, message: String },
+    #[error("{path}: unresolved reference {reference}")]
+    UnresolvedReference { path: String, reference: String },
+}
+
+/// Validates data records (JSON objects) against an OCA bundle.
+///
+/// Checks attribute types, mandatory attributes (Conformance overlay),
+/// entry codes, formats of text attributes, ranges of numeric attributes,
+/// cardinality of arrays and Conditional overlay rules. Attributes
+/// referencing other bundles are validated against bundles provided with
+/// `with_dependencies`.
+pub struct RecordValidator<'a> {
+    bundle: &'a OCABundle,
+    dependencies: Vec<&'a OCABundle>,
+    allow_unknown_attributes: bool,
+}
+
+impl<'a> RecordValidator<'a> {
+    pub fn new(bundle: &'a OCABundle) -> Self {
+        Self {
+            bundle,
+            dependencies: vec![],
+            allow_unknown_attributes: false,
+        }
+    }
+
+    pub fn with_dependencies(mut self, dependencies: &'a [OCABundle]) -> Self {
+        self.dependencies = dependencies.iter().collect();
+        self
+    }
+
+    pub fn allow_unknown_attributes(mut self, allow: bool) -> Self {
+        self.allow_unknown_attributes = allow;
+        self
+    }
+
+    pub fn validate(&self, record: &Value) -> Result<(), Vec<Error>> {
+        let mut errors = vec![];
+        self.validate_object(self.bundle, record, "", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn find_dependency(&self, reference: &RefValue) -> Option<&'a OCABundle> {
+        match reference {
+            RefValue::Said(said) => self
+                .dependencies
+                .iter()
+                .find(|dep| dep.said.as_ref() == Some(said))
+                .copied(),
+            RefValue::Name(_) => None,
+        }
+    }
+
+    fn validate_object(
+        &self,
+        bundle: &OCABundle,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<Error>,
+    ) {
+        let Some(object) = value.as_object() else {
+            errors.push(Error::InvalidType {
+                path: display_path(path),
+                expected: "object".to_string(),
+            });
+            return;
+        };
+        let attributes = attributes_of(bundle);
+
+        for attribute in &attributes {
+            let attr_path = join_path(path, &attribute.name);
+            let value = object.get(&attribute.name).filter(|v| !v.is_null());
+
+            if attribute.condition.is_some() {
+                match evaluate_condition(attribute, object) {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        if value.is_some() {
+                            errors.push(Error::NotApplicable {
+                                path: attr_path,
+                                condition: attribute.condition.clone().unwrap_or_default(),
+                            });
+                        }
+                        continue;
+                    }
+                    Err(message) => {
+                        errors.push(Error::Condition {
+                            path: attr_path,
+                            message,
+                        });
+                        continue;
+                    }
+                }
+            }
+
+            match value {
+                None => {
+                    if attribute.conformance.as_deref() == Some("M") {
+                        errors.push(Error::MissingAttribute { path: attr_path });
+                    }
+                }
+                Some(value) => {
+                    if let Some(attr_type) = &attribute.attribute_type {
+                        self.validate_value(attribute, attr_type, value, &attr_path, errors);
+                    }
+                }
+            }
+        }
+
+        if !self.allow_unknown_attributes {
+            for key in object.keys() {
+                if !attributes.iter().any(|attr| &attr.name == key) {
+                    errors.push(Error::UnknownAttribute {
+                        path: join_path(path, key),
+                    });
+                }
+            }
+        }
+    }
+
+    fn validate_value(
+        &self,
+        attribute: &Attribute,
+        attr_type: &NestedAttrType,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<Error>,
+    ) {
+        match attr_type {
+            NestedAttrType::Null => (),
+            NestedAttrType::Value(attribute_type) => {
+                validate_scalar(attribute, attribute_type, value, path, errors)
+            }
+            NestedAttrType::Reference(reference) => match self.find_dependency(reference) {
+                Some(dependency) => self.validate_object(dependency, value, path, errors),
+                None => errors.push(Error::UnresolvedReference {
+                    path: path.to_string(),
+                    reference: reference.to_string(),
+                }),
+            },
+            NestedAttrType::Array(element_type) => {
+                let Some(elements) = value.as_array() else {
+                    errors.push(Error::InvalidType {
+                        path: path.to_string(),
+                        expected: "array".to_string(),
+                    });
+                    return;
+                };
+                if let Some(cardinality) = &attribute.cardinality {
+                    if let Some((min, max)) = parse_cardinality(cardinality) {
+                        let count = elements.len();
+                        if count < min || max.map(|max| count > max).unwrap_or(false) {
+                            errors.push(Error::Cardinality {
+                                path: path.to_string(),
+                                cardinality: cardinality.clone(),
+                                count,
+                            });
+                        }
+                    }
+                }
+                for (i, element) in elements.iter().enumerate() {
+                    let element_path = format!("{path}[{i}]");
+                    self.validate_value(attribute, element_type, element, &element_path, errors);
+                }
+            }
+        }
+    }
+}
+
+fn validate_scalar(
+    attribute: &Attribute,
+    attribute_type: &AttributeType,
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<Error>,
+) {
+    let type_matches = match attribute_type {
+        AttributeType::Boolean => value.is_boolean(),
+        AttributeType::Numeric => value.is_number(),
+        AttributeType::Text | AttributeType::DateTime | AttributeType::Binary => value.is_string(),
+    };
+    if !type_matches {
+        errors.push(Error::InvalidType {
+            path: path.to_string(),
+            expected: format!("{attribute_type:?}"),
+        });
+        return;
+    }
+
+    if let Some(codes) = attribute.entry_codes.as_ref().and_then(entry_codes_list) {
+        let code = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        if !codes.contains(&code) {
+            errors.push(Error::InvalidEntryCode {
+                path: path.to_string(),
+                value: code,
+            });
+        }
+    }
+
+    #[cfg(feature = "format_overlay")]
+    match (attribute_type, &attribute.format) {
+        (AttributeType::Text, Some(format)) => {
+            if let (Ok(re), Some(text)) = (regex::Regex::new(format), value.as_str()) {
+                if !re.is_match(text) {
+                    errors.push(Error::FormatMismatch {
+                        path: path.to_string(),
+                        format: format.clone(),
+                    });
+                }
+            }
+        }
+        (AttributeType::Numeric, Some(format)) => {
+            if let (Some(range), Some(number)) = (NumericRange::parse(format), value.as_f64()) {
+                if !range.contains(number) {
+                    errors.push(Error::OutOfRange {
+                        path: path.to_string(),
+                        value: value.to_string(),
+                        range: format.clone(),
+                    });
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Range of allowed values of numeric attribute, kept in Format overlay in
+/// interval notation, e.g. `[0,120]`, `(0,1]` or `[18,)` when unbounded.
+/// Ranges of whole numbers are prefixed with `integer`, e.g. `integer[1,5]`,
+/// or are just `integer` when unbounded. The notation is a convention of this
+/// repository, described in the crate README.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NumericRange {
+    pub minimum: Option<f64>,
+    pub exclusive_minimum: bool,
+    pub maximum: Option<f64>,
+    pub exclusive_maximum: bool,
+    pub integer: bool,
+}
+
+impl NumericRange {
+    pub fn parse(range: &str) -> Option<Self> {
+        let range = range.trim();
+        let (integer, range) = match range.strip_prefix("integer") {
+            Some(range) => (true, range.trim_start()),
+            None => (false, range),
+        };
+        if integer && range.is_empty() {
+            return Some(Self {
+                integer,
+                ..Default::default()
+            });
+        }
+        let exclusive_minimum = match range.chars().next()? {
+            '[' => false,
+            '(' => true,
+            _ => return None,
+        };
+        let exclusive_maximum = match range.chars().last()? {
+            ']' => false,
+            ')' => true,
+            _ => return None,
+        };
+        let (minimum, maximum) = range.get(1..range.len() - 1)?.split_once(',')?;
+        let bound = |b: &str| -> Option<Option<f64>> {
+            let b = b.trim();
+            if b.is_empty() {
+                Some(None)
+            } else {
+                b.parse().ok().map(Some)
+            }
+        };
+        Some(Self {
+            minimum: bound(minimum)?,
+            exclusive_minimum,
+            maximum: bound(maximum)?,
+            exclusive_maximum,
+            integer,
+        })
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        let above = match self.minimum {
+            Some(min) if self.exclusive_minimum => value > min,
+            Some(min) => value >= min,
+            None => true,
+        };
+        let below = match self.maximum {
+            Some(max) if self.exclusive_maximum => value < max,
+            Some(max) => value <= max,
+            None => true,
+        };
+        above && below && (!self.integer || value.fract() == 0.0)
+    }
+}
+
+impl std::fmt::Display for NumericRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.integer {
+            write!(f, "integer")?;
+            if self.minimum.is_none() && self.maximum.is_none() {
+                return Ok(());
+            }
+        }
+        let bound = |b: Option<f64>| b.map(|b| b.to_string()).unwrap_or_default();
+        write!(
+            f,
+            "{}{},{}{}",
+            if self.exclusive_minimum || self.minimum.is_none() {
+                '('
+            } else {
+                '['
+            },
+            bound(self.minimum),
+            bound(self.maximum),
+            if self.exclusive_maximum || self.maximum.is_none() {
+                ')'
+            } else {
+                ']'
+            },
+        )
+    }
+}
+
+/// Returns attributes of the bundle, with all overlays applied, sorted by name.
+pub fn attributes_of(bundle: &OCABundle) -> Vec<Attribute> {
+    let oca_box = OCABox::from(bundle.clone());
+    let mut attributes = oca_box.attributes.into_values().collect::<Vec<_>>();
+    attributes.sort_by(|a, b| a.name.cmp(&b.name));
+    attributes
+}
+
+/// Returns all codes allowed by entry codes, or `None` when codes are kept
+/// in external object referenced by SAI.
+pub fn entry_codes_list(entry_codes: &EntryCodes) -> Option<Vec<String>> {
+    match entry_codes {
+        EntryCodes::Sai(_) => None,
+        EntryCodes::Array(codes) => Some(codes.clone()),
+        EntryCodes::Object(groups) => Some(groups.values().flatten().cloned().collect()),
+    }
+}
+
+/// Parses cardinality in `n`, `n-m`, `n-` or `-m` notation into minimum and
+/// optional maximum number of elements.
+pub fn parse_cardinality(cardinality: &str) -> Option<(usize, Option<usize>)> {
+    let cardinality = cardinality.trim();
+    match cardinality.split_once('-') {
+        None => {
+            let n = cardinality.parse().ok()?;
+            Some((n, Some(n)))
+        }
+        Some((min, max)) => {
+            let min = if min.is_empty() { 0 } else { min.parse().ok()? };
+            let max = if max.is_empty() {
+                None
+            } else {
+                Some(max.parse().ok()?)
+            };
+            Some((min, max))
+        }
+    }
+}
+
+/// Evaluates condition of the attribute against values of sibling attributes
+/// in the record. Missing values are passed to the condition as `nil`.
+pub fn evaluate_condition(
+    attribute: &Attribute,
+    record: &Map<String, Value>,
+) -> Result<bool, String> {
+    let mut dependency_values: BTreeMap<String, Box<dyn Display + 'static>> = BTreeMap::new();
+    for dependency in attribute.dependencies.iter().flatten() {
+        let value = record
+            .get(dependency)
+            .map(lua_literal)
+            .unwrap_or_else(|| "nil".to_string());
+        dependency_values.insert(dependency.clone(), Box::new(value));
+    }
+    attribute
+        .check_condition(dependency_values)
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(|e| match e {
+                    crate::state::oca::overlay::conditional::Error::Custom(msg) => msg.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+}
+
+fn lua_literal(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!(
+            "\"{}\"",
+            s.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        ),
+        Value::Null | Value::Array(_) | Value::Object(_) => "nil".to_string(),
+    }
+}
+
+fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        "$".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Groups validation errors by bundle attribute path.
+pub fn errors_by_path(errors: &[Error]) -> HashMap<String, Vec<String>> {
+    let mut grouped: HashMap<String, Vec<String>> = HashMap::new();
+    for error in errors {
+        let path = match error {
+            Error::MissingAttribute { path }
+            | Error::UnknownAttribute { path }
+            | Error::InvalidType { path, .. }
+            | Error::InvalidEntryCode { path, .. }
+            | Error::FormatMismatch { path, .. }
+            | Error::OutOfRange { path, .. }
+            | Error::Cardinality { path, .. }
+            | Error::NotApplicable { path, .. }
+            | Error::Condition { path, .. }
+            | Error::UnresolvedReference { path, .. } => path.clone(),
+        };
+        grouped.entry(path).or_default().push(error.to_string());
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::oca::overlay::{
+        cardinality::Cardinalitys, conformance::Conformances, entry_code::EntryCodes as _,
+    };
+    use serde_json::json;
+
+    fn address_bundle() -> OCABundle {
+        let mut oca = OCABox::new();
+        oca.add_attribute(cascade! {
+            Attribute::new("street".to_string());
+            ..set_attribute_type(NestedAttrType::Value(AttributeType::Text));
+            ..set_conformance("M".to_string());
+        });
+        oca.generate_bundle()
+    }
+
+    fn person_bundle(address: &OCABundle) -> OCABundle {
+        let mut oca = OCABox::new();
+        oca.add_attribute(cascade! {
+            Attribute::new("name".to_string());
+            ..set_attribute_type(NestedAttrType::Value(AttributeType::Text));
+            ..set_conformance("M".to_string());
+        });
+        oca.add_attribute(cascade! {
+            Attribute::new("sex".to_string());
+            ..set_attribute_type(NestedAttrType::Value(AttributeType::Text));
+            ..set_entry_codes(EntryCodes::Array(vec!["F".to_string(), "M".to_string()]));
+        });
+        oca.add_attribute(cascade! {
+            Attribute::new("pregnant".to_string());
+            ..set_attribute_type(NestedAttrType::Value(AttributeType::Boolean));
+            ..set_condition("${sex} == 'F'".to_string());
+        });
+        oca.add_attribute(cascade! {
+            Attribute::new("scores".to_string());
+            ..set_attribute_type(NestedAttrType::Array(Box::new(NestedAttrType::Value(AttributeType::Numeric))));
+            ..set_cardinality("1-2".to_string());
+        });
+        oca.add_attribute(cascade! {
+            Attribute::new("address".to_string());
+            ..set_attribute_type(NestedAttrType::Reference(RefValue::Said(address.said.clone().unwrap())));
+        });
+        oca.generate_bundle()
+    }
+
+    #[test]
+    fn validate_valid_record() {
+        let address = address_bundle();
+        let person = person_bundle(&address);
+        let dependencies = vec![address];
+        let validator = RecordValidator::new(&person).with_dependencies(&dependencies);
+
+        let record = json!({
+            "name": "Alice",
+            "sex": "F",
+            "pregnant": false,
+            "scores": [1, 2.5],
+            "address": { "street": "Main" }
+        });
+        assert_eq!(validator.validate(&record), Ok(()));
+    }
+
+    #[test]
+    fn validate_invalid_record() {
+        let address = address_bundle();
+        let person = person_bundle(&address);
+        let dependencies = vec![address];
+        let validator = RecordValidator::new(&person).with_dependencies(&dependencies);
+
+        let record = json!({
+            "sex": "X",
+            "pregnant": true,
+            "scores": [1, "2", 3],
+            "address": {},
+            "extra": 1
+        });
+        let errors = validator.validate(&record).unwrap_err();
+        let grouped = errors_by_path(&errors);
+
+        assert!(errors.contains(&Error::MissingAttribute {
+            path: "name".to_string()
+        }));
+        assert!(errors.contains(&Error::InvalidEntryCode {
+            path: "sex".to_string(),
+            value: "X".to_string()
+        }));
+        assert!(grouped.contains_key("pregnant"));
+        assert!(grouped.contains_key("scores"));
+        assert!(grouped.contains_key("scores[1]"));
+        assert!(errors.contains(&Error::MissingAttribute {
+            path: "address.street".to_string()
+        }));
+        assert!(errors.contains(&Error::UnknownAttribute {
+            path: "extra".to_string()
+        }));
+    }
+
+    #[test]
+    fn validate_unresolved_reference() {
+        let address = address_bundle();
+        let person = person_bundle(&address);
+        let record = json!({ "name": "Bob", "address": { "street": "Main" } });
+
+        let errors = RecordValidator::new(&person).validate(&record).unwrap_err();
+        assert!(matches!(errors[0], Error::UnresolvedReference { .. }));
+    }
+
+    #[test]
+    fn parse_cardinality_notations() {
+        assert_eq!(parse_cardinality("2"), Some((2, Some(2))));
+        assert_eq!(parse_cardinality("1-3"), Some((1, Some(3))));
+        assert_eq!(parse_cardinality("1-"), Some((1, None)));
+        assert_eq!(parse_cardinality("-3"), Some((0, Some(3))));
+        assert_eq!(parse_cardinality("x"), None);
+    }
+
+    #[test]
+    fn parse_numeric_range() {
+        let range = NumericRange::parse("[0,120]").unwrap();
+        assert!(range.contains(0.0) && range.contains(120.0) && !range.contains(121.0));
+        assert_eq!(range.to_string(), "[0,120]");
+
+        let range = NumericRange::parse("(0.5,)").unwrap();
+        assert!(!range.contains(0.5) && range.contains(1e9));
+        assert_eq!(range.to_string(), "(0.5,)");
+
+        let range = NumericRange::parse("integer[1,5]").unwrap();
+        assert!(range.contains(3.0) && !range.contains(2.5) && !range.contains(6.0));
+        assert_eq!(range.to_string(), "integer[1,5]");
+
+        let range = NumericRange::parse("integer").unwrap();
+        assert!(range.contains(-7.0) && !range.contains(0.1));
+        assert_eq!(range.to_string(), "integer");
+
+        assert_eq!(NumericRange::parse("DD/MM/YYYY"), None);
+        assert_eq!(NumericRange::parse("integer DD"), None);
+    }
+}
@@ -0,0 +1,347 @@
+pub mod rust;
+pub mod typescript;
+
+use isolang::Language;
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, RefValue};
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    entries::EntriesElement,
+    oca::{OCABox, OCABundle},
+    record::{attributes_of, entry_codes_list},
+};
+use std::{collections::HashMap, path::Path};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Attribute {attribute} of bundle {bundle} references {reference} which is not provided in dependencies")]
+    UnresolvedReference {
+        bundle: String,
+        attribute: String,
+        reference: String,
+    },
+    #[error("Invalid OCA bundle: {0}")]
+    InvalidBundle(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Reads OCA bundle serialized as JSON, e.g. from `build.rs`.
+pub fn load_bundle(path: impl AsRef<Path>) -> Result<OCABundle, Error> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Language independent description of types generated for a bundle and
+/// the bundles it references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    /// Structs of referenced bundles first, struct of the bundle last.
+    pub structs: Vec<StructDef>,
+    pub enums: Vec<EnumDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    /// Type name, in PascalCase
+    pub name: String,
+    pub said: String,
+    pub doc: Option<String>,
+    pub fields: Vec<FieldDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    /// Attribute name, as in the bundle
+    pub name: String,
+    pub doc: Option<String>,
+    pub field_type: FieldType,
+    /// Conformance `M` without condition
+    pub required: bool,
+    /// Format overlay
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Value(AttributeType),
+    Null,
+    /// Name of the enum of entry codes
+    Enum(String),
+    /// Name of the struct of referenced bundle
+    Struct(String),
+    Array(Box<FieldType>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDef {
+    /// Type name, in PascalCase
+    pub name: String,
+    pub doc: Option<String>,
+    /// Entry codes with their labels
+    pub variants: Vec<(String, Option<String>)>,
+}
+
+impl Model {
+    /// Builds model of the bundle. Referenced bundles are looked up by SAID
+    /// in `dependencies`. Texts are taken in `language`, or in English, or in
+    /// any language the bundle has.
+    pub fn build(
+        bundle: &OCABundle,
+        dependencies: &[OCABundle],
+        language: Option<Language>,
+    ) -> Result<Self, Error> {
+        let mut builder = ModelBuilder {
+            dependencies,
+            language,
+            names: HashMap::new(),
+            model: Model {
+                structs: vec![],
+                enums: vec![],
+            },
+        };
+        builder.add_struct(bundle)?;
+        Ok(builder.model)
+    }
+}
+
+struct ModelBuilder<'a> {
+    dependencies: &'a [OCABundle],
+    language: Option<Language>,
+    /// Type names of bundles by SAID
+    names: HashMap<String, String>,
+    model: Model,
+}
+
+impl ModelBuilder<'_> {
+    fn add_struct(&mut self, bundle: &OCABundle) -> Result<String, Error> {
+        let said = bundle
+            .said
+            .as_ref()
+            .map(|said| said.to_string())
+            .unwrap_or_default();
+        if let Some(name) = self.names.get(&said) {
+            return Ok(name.clone());
+        }
+
+        let meta = OCABox::from(bundle.clone()).meta.unwrap_or_default();
+        let meta = self.translate(Some(&meta)).cloned().unwrap_or_default();
+        let base_name = meta
+            .get("name")
+            .map(|name| pascal_case(name))
+            .filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .unwrap_or_else(|| {
+                format!(
+                    "Bundle{}",
+                    pascal_case(&said.chars().take(8).collect::<String>())
+                )
+            });
+        let name = self.unique_name(base_name);
+        self.names.insert(said.clone(), name.clone());
+
+        let mut fields = vec![];
+        for attribute in attributes_of(bundle) {
+            let Some(attr_type) = &attribute.attribute_type else {
+                continue;
+            };
+            let field_type = self.field_type(&name, &said, &attribute, attr_type)?;
+            fields.push(FieldDef {
+                name: attribute.name.clone(),
+                doc: self.doc(&attribute),
+                field_type,
+                required: attribute.conformance.as_deref() == Some("M")
+                    && attribute.condition.is_none(),
+                format: attribute.format.clone(),
+            });
+        }
+        self.model.structs.push(StructDef {
+            name: name.clone(),
+            said,
+            doc: meta.get("description").cloned(),
+            fields,
+        });
+        Ok(name)
+    }
+
+    fn field_type(
+        &mut self,
+        struct_name: &str,
+        said: &str,
+        attribute: &Attribute,
+        attr_type: &NestedAttrType,
+    ) -> Result<FieldType, Error> {
+        Ok(match attr_type {
+            NestedAttrType::Value(AttributeType::Text) => {
+                match attribute.entry_codes.as_ref().and_then(entry_codes_list) {
+                    Some(codes) => FieldType::Enum(self.add_enum(struct_name, attribute, codes)),
+                    None => FieldType::Value(AttributeType::Text),
+                }
+            }
+            NestedAttrType::Value(attribute_type) => FieldType::Value(*attribute_type),
+            NestedAttrType::Null => FieldType::Null,
+            NestedAttrType::Array(element_type) => FieldType::Array(Box::new(self.field_type(
+                struct_name,
+                said,
+                attribute,
+                element_type,
+            )?)),
+            NestedAttrType::Reference(reference) => {
+                let dependency = match reference {
+                    RefValue::Said(reference) => self
+                        .dependencies
+                        .iter()
+                        .find(|dependency| dependency.said.as_ref() == Some(reference)),
+                    RefValue::Name(_) => None,
+                };
+                match dependency {
+                    Some(dependency) => FieldType::Struct(self.add_struct(dependency)?),
+                    None => {
+                        return Err(Error::UnresolvedReference {
+                            bundle: said.to_string(),
+                            attribute: attribute.name.clone(),
+                            reference: reference.to_string(),
+                        })
+                    }
+                }
+            }
+        })
+    }
+
+    fn add_enum(&mut self, struct_name: &str, attribute: &Attribute, codes: Vec<String>) -> String {
+        let name = self.unique_name(format!("{struct_name}{}", pascal_case(&attribute.name)));
+        let labels = attribute
+            .entries
+            .as_ref()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|(language, element)| match element {
+                        EntriesElement::Object(labels) => Some((*language, labels.clone())),
+                        EntriesElement::Sai(_) => None,
+                    })
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default();
+        let labels = self.translate(Some(&labels));
+        let variants = codes
+            .into_iter()
+            .map(|code| {
+                let label = labels.and_then(|labels| labels.get(&code)).cloned();
+                (code, label)
+            })
+            .collect();
+        self.model.enums.push(EnumDef {
+            name: name.clone(),
+            doc: self.doc(attribute),
+            variants,
+        });
+        name
+    }
+
+    fn doc(&self, attribute: &Attribute) -> Option<String> {
+        let texts = [
+            self.translate(attribute.labels.as_ref()),
+            self.translate(attribute.informations.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+        if texts.is_empty() {
+            None
+        } else {
+            Some(texts.join("\n\n"))
+        }
+    }
+
+    fn translate<'b, T>(&self, texts: Option<&'b HashMap<Language, T>>) -> Option<&'b T> {
+        let texts = texts?;
+        self.language
+            .and_then(|language| texts.get(&language))
+            .or_else(|| texts.get(&Language::Eng))
+            .or_else(|| {
+                let mut languages = texts.keys().collect::<Vec<_>>();
+                languages.sort_by_key(|language| language.to_639_3());
+                languages.first().and_then(|language| texts.get(language))
+            })
+    }
+
+    fn unique_name(&self, base_name: String) -> String {
+        let taken = |name: &String| {
+            self.model.structs.iter().any(|s| &s.name == name)
+                || self.model.enums.iter().any(|e| &e.name == name)
+                || self.names.values().any(|n| n == name)
+        };
+        let mut name = base_name.clone();
+        let mut i = 2;
+        while taken(&name) {
+            name = format!("{base_name}{i}");
+            i += 1;
+        }
+        name
+    }
+}
+
+/// Splits text into words on non alphanumeric characters and lower to upper
+/// case transitions.
+fn words(text: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+pub fn pascal_case(text: &str) -> String {
+    words(text)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+pub fn snake_case(text: &str) -> String {
+    words(text)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!(pascal_case("first_name"), "FirstName");
+        assert_eq!(pascal_case("dateOfBirth"), "DateOfBirth");
+        assert_eq!(pascal_case("Blood pressure (mmHg)"), "BloodPressureMmHg");
+        assert_eq!(snake_case("dateOfBirth"), "date_of_birth");
+        assert_eq!(snake_case("HTTP-code"), "http_code");
+    }
+}
@@ -0,0 +1,236 @@
+//! Rust types generation, meant to be run from `build.rs` with the output
+//! written to `OUT_DIR` and brought in with `include!`.
+//!
+//! Generated code depends on `serde` with `derive` feature only.
+use crate::{pascal_case, snake_case, EnumDef, Error, FieldType, Model, StructDef};
+use isolang::Language;
+use oca_ast_semantics::ast::AttributeType;
+use oca_bundle_semantics::state::oca::OCABundle;
+use std::path::Path;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+#[derive(Default)]
+pub struct RustGenerator {
+    dependencies: Vec<OCABundle>,
+    language: Option<Language>,
+}
+
+impl RustGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bundles referenced by `refs:` attributes. Each gets its own struct.
+    pub fn with_dependencies(mut self, dependencies: Vec<OCABundle>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Language of labels and descriptions used in doc comments and type
+    /// names. English by default.
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn generate(&self, bundle: &OCABundle) -> Result<String, Error> {
+        let model = Model::build(bundle, &self.dependencies, self.language)?;
+        let mut code = String::from("// Generated from OCA bundle. Do not edit.\n");
+        for enum_def in &model.enums {
+            code.push('\n');
+            code.push_str(&render_enum(enum_def));
+        }
+        for struct_def in &model.structs {
+            code.push('\n');
+            code.push_str(&render_struct(struct_def));
+        }
+        Ok(code)
+    }
+
+    /// Generates code and writes it to file at `path`.
+    pub fn write(&self, bundle: &OCABundle, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.generate(bundle)?)?;
+        Ok(())
+    }
+}
+
+fn render_struct(struct_def: &StructDef) -> String {
+    let mut code = doc_comment(struct_def.doc.as_deref(), "");
+    code.push_str("#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n");
+    code.push_str(&format!("pub struct {} {{\n", struct_def.name));
+    let mut idents = vec![];
+    for field in &struct_def.fields {
+        let ident = unique(field_ident(&field.name), &idents);
+        idents.push(ident.clone());
+
+        code.push_str(&doc_comment(field.doc.as_deref(), "    "));
+        let mut serde_attributes = vec![];
+        if ident.trim_start_matches("r#") != field.name {
+            serde_attributes.push(format!("rename = {:?}", field.name));
+        }
+        let mut field_type = type_name(&field.field_type);
+        if !field.required {
+            serde_attributes.push("default".to_string());
+            serde_attributes.push("skip_serializing_if = \"Option::is_none\"".to_string());
+            field_type = format!("Option<{field_type}>");
+        }
+        if !serde_attributes.is_empty() {
+            code.push_str(&format!("    #[serde({})]\n", serde_attributes.join(", ")));
+        }
+        code.push_str(&format!("    pub {ident}: {field_type},\n"));
+    }
+    code.push_str("}\n\n");
+    code.push_str(&format!(
+        "impl {} {{\n    /// SAID of the OCA bundle the type was generated from.\n    pub const SAID: &'static str = {:?};\n}}\n",
+        struct_def.name, struct_def.said
+    ));
+    code
+}
+
+fn render_enum(enum_def: &EnumDef) -> String {
+    let mut code = doc_comment(enum_def.doc.as_deref(), "");
+    code.push_str(
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]\n",
+    );
+    code.push_str(&format!("pub enum {} {{\n", enum_def.name));
+    let mut idents = vec![];
+    for (code_value, label) in &enum_def.variants {
+        let mut ident = pascal_case(code_value);
+        if !ident.starts_with(|c: char| c.is_alphabetic()) {
+            ident = format!("Code{ident}");
+        }
+        let ident = unique(ident, &idents);
+        idents.push(ident.clone());
+
+        code.push_str(&doc_comment(label.as_deref(), "    "));
+        if &ident != code_value {
+            code.push_str(&format!("    #[serde(rename = {code_value:?})]\n"));
+        }
+        code.push_str(&format!("    {ident},\n"));
+    }
+    code.push_str("}\n");
+    code
+}
+
+fn type_name(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Value(AttributeType::Text)
+        | FieldType::Value(AttributeType::DateTime)
+        | FieldType::Value(AttributeType::Binary) => "String".to_string(),
+        FieldType::Value(AttributeType::Numeric) => "f64".to_string(),
+        FieldType::Value(AttributeType::Boolean) => "bool".to_string(),
+        FieldType::Null => "()".to_string(),
+        FieldType::Enum(name) | FieldType::Struct(name) => name.clone(),
+        FieldType::Array(element_type) => format!("Vec<{}>", type_name(element_type)),
+    }
+}
+
+fn field_ident(name: &str) -> String {
+    let ident = snake_case(name);
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{ident}")
+    } else if ["self", "super", "crate", "Self"].contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{ident}")
+    } else {
+        ident
+    }
+}
+
+fn unique(ident: String, taken: &[String]) -> String {
+    let mut candidate = ident.clone();
+    let mut i = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{ident}{i}");
+        i += 1;
+    }
+    candidate
+}
+
+fn doc_comment(doc: Option<&str>, indent: &str) -> String {
+    doc.map(|doc| {
+        doc.lines()
+            .map(|line| {
+                if line.is_empty() {
+                    format!("{indent}///\n")
+                } else {
+                    format!("{indent}/// {line}\n")
+                }
+            })
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    #[test]
+    fn generate_structs() {
+        let address = build(
+            r#"
+ADD ATTRIBUTE street=Text
+ADD META en PROPS name="Postal address"
+ADD CONFORMANCE ATTRS street="M"
+"#,
+        );
+        let person = build(&format!(
+            r#"
+ADD ATTRIBUTE firstName=Text age=Numeric sex=Text type=Text tags=Array[Text] addresses=Array[refs:{}]
+ADD META en PROPS name="Person" description="Person record"
+ADD LABEL en ATTRS firstName="First name" sex="Sex"
+ADD CONFORMANCE ATTRS firstName="M" sex="M" addresses="M"
+ADD ENTRY_CODE ATTRS sex=["F", "M", "1"]
+ADD ENTRY en ATTRS sex={{"F": "Female", "M": "Male", "1": "Other"}}
+"#,
+            address.said.clone().unwrap()
+        ));
+
+        let code = RustGenerator::new()
+            .with_dependencies(vec![address.clone()])
+            .generate(&person)
+            .unwrap();
+        syn::parse_file(&code).unwrap();
+
+        assert!(code.contains("pub struct PostalAddress {\n    pub street: String,\n}"));
+        assert!(code.contains(&format!(
+            "pub const SAID: &'static str = \"{}\";",
+            address.said.unwrap()
+        )));
+        assert!(code.contains("/// Person record\n#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\npub struct Person {"));
+        assert!(code.contains(
+            "    /// First name\n    #[serde(rename = \"firstName\")]\n    pub first_name: String,\n"
+        ));
+        assert!(code.contains("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub age: Option<f64>,\n"));
+        assert!(code.contains("    pub sex: PersonSex,\n"));
+        assert!(code.contains("    /// Other\n    #[serde(rename = \"1\")]\n    Code1,\n"));
+        assert!(code.contains("    pub r#type: Option<String>,\n"));
+        assert!(code.contains("    pub tags: Option<Vec<String>>,\n"));
+        assert!(code.contains("    pub addresses: Vec<PostalAddress>,\n"));
+    }
+
+    #[test]
+    fn unresolved_reference() {
+        let person =
+            build("ADD ATTRIBUTE address=refs:ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI");
+        assert!(matches!(
+            RustGenerator::new().generate(&person),
+            Err(Error::UnresolvedReference { .. })
+        ));
+    }
+}
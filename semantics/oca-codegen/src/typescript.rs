@@ -0,0 +1,270 @@
+//! TypeScript interfaces and matching zod schemas generation.
+//!
+//! Output depends only on the bundle and its dependencies, so regenerating
+//! and comparing with committed file detects drift between the two. Each
+//! type comes with `<NAME>_SAID` constant of the bundle it was generated from.
+use crate::{snake_case, EnumDef, Error, FieldDef, FieldType, Model, StructDef};
+use isolang::Language;
+use oca_ast_semantics::ast::AttributeType;
+use oca_bundle_semantics::state::{oca::OCABundle, record::NumericRange};
+use std::path::Path;
+
+#[derive(Default)]
+pub struct TypeScriptGenerator {
+    dependencies: Vec<OCABundle>,
+    language: Option<Language>,
+}
+
+impl TypeScriptGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bundles referenced by `refs:` attributes. Each gets its own interface.
+    pub fn with_dependencies(mut self, dependencies: Vec<OCABundle>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Language of labels and descriptions used in JSDoc comments and type
+    /// names. English by default.
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn generate(&self, bundle: &OCABundle) -> Result<String, Error> {
+        let model = Model::build(bundle, &self.dependencies, self.language)?;
+        let mut code = String::from(
+            "// Generated from OCA bundle. Do not edit.\nimport { z } from \"zod\";\n",
+        );
+        for enum_def in &model.enums {
+            code.push('\n');
+            code.push_str(&render_enum(enum_def));
+        }
+        for struct_def in &model.structs {
+            code.push('\n');
+            code.push_str(&render_struct(struct_def));
+        }
+        Ok(code)
+    }
+
+    /// Generates code and writes it to file at `path`.
+    pub fn write(&self, bundle: &OCABundle, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.generate(bundle)?)?;
+        Ok(())
+    }
+}
+
+fn render_enum(enum_def: &EnumDef) -> String {
+    let codes = enum_def
+        .variants
+        .iter()
+        .map(|(code, _)| string_literal(code))
+        .collect::<Vec<_>>();
+    let mut code = js_doc(enum_def.doc.as_deref(), "");
+    // Neither union nor `z.enum` can be empty
+    let (union, schema) = if codes.is_empty() {
+        ("never".to_string(), "z.never()".to_string())
+    } else {
+        (codes.join(" | "), format!("z.enum([{}])", codes.join(", ")))
+    };
+    code.push_str(&format!("export type {} = {union};\n", enum_def.name));
+    code.push_str(&format!(
+        "export const {}Schema = {schema};\n",
+        enum_def.name
+    ));
+    code
+}
+
+fn render_struct(struct_def: &StructDef) -> String {
+    let name = &struct_def.name;
+    let mut code = js_doc(struct_def.doc.as_deref(), "");
+    code.push_str(&format!("export interface {name} {{\n"));
+    for field in &struct_def.fields {
+        code.push_str(&js_doc(field.doc.as_deref(), "  "));
+        code.push_str(&format!(
+            "  {}{}: {};\n",
+            property_name(&field.name),
+            if field.required { "" } else { "?" },
+            type_name(&field.field_type)
+        ));
+    }
+    code.push_str("}\n\n");
+
+    code.push_str(&format!(
+        "export const {name}Schema: z.ZodType<{name}> = z.object({{\n"
+    ));
+    for field in &struct_def.fields {
+        code.push_str(&format!(
+            "  {}: {}{},\n",
+            property_name(&field.name),
+            schema(field, &field.field_type),
+            if field.required { "" } else { ".optional()" }
+        ));
+    }
+    code.push_str("});\n\n");
+    code.push_str(&format!(
+        "export const {}_SAID = {};\n",
+        snake_case(name).to_uppercase(),
+        string_literal(&struct_def.said)
+    ));
+    code
+}
+
+fn type_name(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Value(AttributeType::Text)
+        | FieldType::Value(AttributeType::DateTime)
+        | FieldType::Value(AttributeType::Binary) => "string".to_string(),
+        FieldType::Value(AttributeType::Numeric) => "number".to_string(),
+        FieldType::Value(AttributeType::Boolean) => "boolean".to_string(),
+        FieldType::Null => "null".to_string(),
+        FieldType::Enum(name) | FieldType::Struct(name) => name.clone(),
+        FieldType::Array(element_type) => format!("Array<{}>", type_name(element_type)),
+    }
+}
+
+/// Zod schema of the field value. Format overlay refines text with regex
+/// and numbers with range.
+fn schema(field: &FieldDef, field_type: &FieldType) -> String {
+    let format = field.format.as_deref();
+    match field_type {
+        FieldType::Value(AttributeType::Text) => match format {
+            Some(format) => format!("z.string().regex(new RegExp({}))", string_literal(format)),
+            None => "z.string()".to_string(),
+        },
+        FieldType::Value(AttributeType::DateTime) | FieldType::Value(AttributeType::Binary) => {
+            "z.string()".to_string()
+        }
+        FieldType::Value(AttributeType::Numeric) => {
+            let mut schema = "z.number()".to_string();
+            if let Some(range) = format.and_then(NumericRange::parse) {
+                if range.integer {
+                    schema.push_str(".int()");
+                }
+                if let Some(minimum) = range.minimum {
+                    let method = if range.exclusive_minimum { "gt" } else { "gte" };
+                    schema.push_str(&format!(".{method}({minimum})"));
+                }
+                if let Some(maximum) = range.maximum {
+                    let method = if range.exclusive_maximum { "lt" } else { "lte" };
+                    schema.push_str(&format!(".{method}({maximum})"));
+                }
+            }
+            schema
+        }
+        FieldType::Value(AttributeType::Boolean) => "z.boolean()".to_string(),
+        FieldType::Null => "z.null()".to_string(),
+        FieldType::Enum(name) | FieldType::Struct(name) => format!("{name}Schema"),
+        FieldType::Array(element_type) => format!("z.array({})", schema(field, element_type)),
+    }
+}
+
+fn property_name(name: &str) -> String {
+    let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        name.to_string()
+    } else {
+        string_literal(name)
+    }
+}
+
+fn string_literal(text: &str) -> String {
+    serde_json::Value::String(text.to_string()).to_string()
+}
+
+fn js_doc(doc: Option<&str>, indent: &str) -> String {
+    let Some(doc) = doc else {
+        return String::new();
+    };
+    let doc = doc.replace("*/", "*\\/");
+    let lines = doc.lines().collect::<Vec<_>>();
+    if lines.len() == 1 {
+        return format!("{indent}/** {} */\n", lines[0]);
+    }
+    let mut comment = format!("{indent}/**\n");
+    for line in lines {
+        if line.is_empty() {
+            comment.push_str(&format!("{indent} *\n"));
+        } else {
+            comment.push_str(&format!("{indent} * {line}\n"));
+        }
+    }
+    comment.push_str(&format!("{indent} */\n"));
+    comment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    #[test]
+    fn generate_types_and_schemas() {
+        let address = build(
+            r#"
+ADD ATTRIBUTE street=Text
+ADD META en PROPS name="Postal address"
+ADD CONFORMANCE ATTRS street="M"
+"#,
+        );
+        let person = build(&format!(
+            r#"
+ADD ATTRIBUTE name=Text age=Numeric sex=Text date-of-birth=DateTime address=refs:{}
+ADD META en PROPS name="Person"
+ADD LABEL en ATTRS name="Name" sex="Sex"
+ADD INFORMATION en ATTRS name="Full name, as in passport"
+ADD CONFORMANCE ATTRS name="M" sex="M"
+ADD FORMAT ATTRS name="^[A-Z]\w*$" age="[0,120)"
+ADD ENTRY_CODE ATTRS sex=["F", "M"]
+"#,
+            address.said.clone().unwrap()
+        ));
+
+        let generator = TypeScriptGenerator::new().with_dependencies(vec![address.clone()]);
+        let code = generator.generate(&person).unwrap();
+        assert_eq!(code, generator.generate(&person).unwrap());
+
+        assert!(code.contains(
+            "/** Sex */\nexport type PersonSex = \"F\" | \"M\";\nexport const PersonSexSchema = z.enum([\"F\", \"M\"]);\n"
+        ));
+        assert!(code.contains("export interface PostalAddress {\n  street: string;\n}\n"));
+        assert!(code.contains(&format!(
+            "export const POSTAL_ADDRESS_SAID = \"{}\";",
+            address.said.unwrap()
+        )));
+        assert!(code.contains(
+            "  /**\n   * Name\n   *\n   * Full name, as in passport\n   */\n  name: string;\n"
+        ));
+        assert!(code.contains("  age?: number;\n"));
+        assert!(code.contains("  \"date-of-birth\"?: string;\n"));
+        assert!(code.contains("  address?: PostalAddress;\n"));
+        assert!(code.contains("export const PersonSchema: z.ZodType<Person> = z.object({\n"));
+        assert!(code.contains("  age: z.number().gte(0).lt(120).optional(),\n"));
+        assert!(code.contains("  name: z.string().regex(new RegExp(\"^[A-Z]\\\\w*$\")),\n"));
+        assert!(code.contains("  sex: PersonSexSchema,\n"));
+        assert!(code.contains("  address: PostalAddressSchema.optional(),\n"));
+    }
+
+    #[test]
+    fn render_empty_enum() {
+        let code = render_enum(&EnumDef {
+            name: "PersonSex".to_string(),
+            doc: None,
+            variants: vec![],
+        });
+        assert_eq!(
+            code,
+            "export type PersonSex = never;\nexport const PersonSexSchema = z.never();\n"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod data_storage;
+pub mod record;
 pub mod versioning;
 
 use oca_ast_semantics::ast;
@@ -19,7 +20,7 @@ impl CommandModel {
         }
     }
 
-    fn calculate_command_digest(command: &ast::Command) -> SelfAddressingIdentifier {
+    pub fn calculate_command_digest(command: &ast::Command) -> SelfAddressingIdentifier {
         let command_json = serde_json::to_string(command).unwrap();
         let hash_algorithm = HashFunction::from_str("E").unwrap();
         hash_algorithm.derive(command_json.as_bytes())
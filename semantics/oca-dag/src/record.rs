@@ -0,0 +1,181 @@
+//! Binary encoding of history, core model and relationship records.
+//!
+//! A record is a list of byte fields. Current format starts with
+//! [`MARKER`] and format [`VERSION`], followed by fields, each prefixed with
+//! its length as big endian `u32`:
+//!
+//! ```text
+//! 0xFF | version | len (u32) | field | len (u32) | field | ...
+//! ```
+//!
+//! Records written before used a single `u8` length prefix per field, with
+//! no header. They can still be read with [`Record::read`], which tells both
+//! formats apart by the marker, as no legacy field was ever 255 bytes long.
+
+use std::fmt;
+
+/// First byte of a versioned record.
+pub const MARKER: u8 = 0xFF;
+/// Current version of the record format.
+pub const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Truncated,
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "Record is truncated"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported record format version {}", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    fields: Vec<Vec<u8>>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_field(mut self, field: impl AsRef<[u8]>) -> Self {
+        self.push(field);
+        self
+    }
+
+    pub fn push(&mut self, field: impl AsRef<[u8]>) {
+        self.fields.push(field.as_ref().to_vec());
+    }
+
+    pub fn fields(&self) -> &[Vec<u8>] {
+        &self.fields
+    }
+
+    pub fn into_fields(self) -> Vec<Vec<u8>> {
+        self.fields
+    }
+
+    /// Field as text, lossy for invalid UTF-8.
+    pub fn text(&self, index: usize) -> Option<String> {
+        self.fields
+            .get(index)
+            .map(|field| String::from_utf8_lossy(field).to_string())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![MARKER, VERSION];
+        for field in &self.fields {
+            bytes.extend((field.len() as u32).to_be_bytes());
+            bytes.extend(field);
+        }
+        bytes
+    }
+
+    /// Whether the bytes are a record in the versioned format, of any
+    /// version.
+    pub fn is_versioned(bytes: &[u8]) -> bool {
+        bytes.first() == Some(&MARKER)
+    }
+
+    /// Decodes record in the versioned format.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes {
+            [MARKER, VERSION, rest @ ..] => {
+                let mut record = Self::new();
+                let mut rest = rest;
+                while !rest.is_empty() {
+                    let (len, tail) = rest.split_first_chunk::<4>().ok_or(Error::Truncated)?;
+                    let len = u32::from_be_bytes(*len) as usize;
+                    if tail.len() < len {
+                        return Err(Error::Truncated);
+                    }
+                    let (field, tail) = tail.split_at(len);
+                    record.push(field);
+                    rest = tail;
+                }
+                Ok(record)
+            }
+            [MARKER, version, ..] => Err(Error::UnsupportedVersion(*version)),
+            _ => Err(Error::Truncated),
+        }
+    }
+
+    /// Decodes record in the legacy format, of `u8` length prefixed fields.
+    /// With `prefixed` set, only that many fields are prefixed and the rest
+    /// of bytes, if any, makes the last field.
+    pub fn decode_legacy(bytes: &[u8], prefixed: Option<usize>) -> Result<Self, Error> {
+        let mut record = Self::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            if prefixed.is_some_and(|prefixed| record.fields.len() == prefixed) {
+                record.push(rest);
+                break;
+            }
+            let (len, tail) = rest.split_first().ok_or(Error::Truncated)?;
+            let field = tail.get(..*len as usize).ok_or(Error::Truncated)?;
+            record.push(field);
+            rest = &tail[*len as usize..];
+        }
+        Ok(record)
+    }
+
+    /// Decodes record in either format, see [`Record::decode_legacy`] for
+    /// `prefixed`.
+    pub fn read(bytes: &[u8], prefixed: Option<usize>) -> Result<Self, Error> {
+        if Self::is_versioned(bytes) {
+            Self::decode(bytes)
+        } else {
+            Self::decode_legacy(bytes, prefixed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_read_records() {
+        let long = "x".repeat(300);
+        let record = Record::new()
+            .with_field("")
+            .with_field(&long)
+            .with_field([MARKER]);
+        let encoded = record.encode();
+        assert_eq!(&encoded[..6], &[MARKER, VERSION, 0, 0, 0, 0]);
+        assert_eq!(Record::read(&encoded, None), Ok(record));
+        assert_eq!(Record::decode(&[MARKER, VERSION]), Ok(Record::new()));
+
+        assert_eq!(
+            Record::decode(&encoded[..encoded.len() - 1]),
+            Err(Error::Truncated)
+        );
+        assert_eq!(
+            Record::decode(&[MARKER, 2]),
+            Err(Error::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn read_legacy_records() {
+        let history = [&[3][..], b"abc", br#"{"kind":"add"}"#].concat();
+        let record = Record::read(&history, Some(1)).unwrap();
+        assert_eq!(record.text(0).unwrap(), "abc");
+        assert_eq!(record.text(1).unwrap(), r#"{"kind":"add"}"#);
+
+        let record = Record::read(&[0, 2, b'a', b'b'], None).unwrap();
+        assert_eq!(record.fields(), &[b"".to_vec(), b"ab".to_vec()]);
+        assert_eq!(Record::read(&[], None), Ok(Record::new()));
+        assert_eq!(Record::read(&[3, b'a'], None), Err(Error::Truncated));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::record::Record;
 use oca_bundle_semantics::state::oca::OCABundle;
 
 struct OCABundleDTO {
@@ -13,21 +14,18 @@ impl OCABundleDTO {
 
 impl From<OCABundleDTO> for Vec<u8> {
     fn from(val: OCABundleDTO) -> Self {
-        let mut digests: Vec<u8> = Vec::new();
+        let mut digests = Record::new();
         if let Some(ref said) = val.bundle.capture_base.said {
-            digests.push(said.to_string().len().try_into().unwrap());
-            digests.extend(said.to_string().as_bytes());
+            digests.push(said.to_string());
         }
 
         val.bundle.overlays.iter().for_each(|overlay| {
             if let Some(ref said) = overlay.said() {
-                digests.push(said.to_string().len().try_into().unwrap());
-                // digests.push(overlay.overlay_type().into());
-                digests.extend(said.to_string().as_bytes());
+                digests.push(said.to_string());
             }
         });
 
-        digests
+        digests.encode()
     }
 }
 
@@ -57,12 +55,8 @@ mod tests {
         let oca = oca_bundle_semantics::controller::load_oca(&mut oca_str.as_bytes()).unwrap();
         let digests: Vec<u8> = OCABundleDTO::new(oca).into();
         assert_eq!(
-            digests,
-            vec![
-                44, 69, 73, 74, 71, 74, 109, 83, 95, 80, 57, 106, 119, 90, 68, 97, 109, 66, 54, 99,
-                84, 71, 57, 77, 111, 88, 75, 82, 117, 50, 49, 109, 121, 106, 88, 115, 77, 105, 55,
-                71, 89, 100, 100, 68, 121
-            ]
+            Record::read(&digests, None).unwrap().fields(),
+            &[b"EIJGJmS_P9jwZDamB6cTG9MoXKRu21myjXsMi7GYddDy".to_vec()]
         )
     }
 }
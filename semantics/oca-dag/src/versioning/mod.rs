@@ -1,6 +1,7 @@
 pub mod bundle;
 pub mod ocafile;
 use crate::data_storage::DataStorage;
+use crate::record::Record;
 
 pub struct Graph {
     pub db: Box<dyn DataStorage>,
@@ -23,7 +24,7 @@ impl Graph {
             let children_digests: Option<Vec<u8>> = self.db.get(k)?;
             self.db.insert(
                 &format!("{}.downscending", to),
-                &self.update_children_digests(children_digests.as_deref(), new),
+                &self.update_children_digests(children_digests.as_deref(), new)?,
             )?;
         }
 
@@ -31,22 +32,26 @@ impl Graph {
     }
 
     fn generate_parents_digests(&self, parent_said: Option<&str>) -> Vec<u8> {
-        let mut digests: Vec<u8> = Vec::new();
+        let mut digests = Record::new();
         if let Some(to) = parent_said {
-            digests.push(to.len().try_into().unwrap());
-            digests.extend(to.as_bytes());
+            digests.push(to);
         }
-        digests
+        digests.encode()
     }
 
-    fn update_children_digests(&self, children_digest: Option<&[u8]>, new: &str) -> Vec<u8> {
-        let mut digests: Vec<u8> = Vec::new();
-        if let Some(children_digest) = children_digest {
-            digests.extend(children_digest);
-        }
-        digests.push(new.len().try_into().unwrap());
-        digests.extend(new.as_bytes());
-        digests
+    fn update_children_digests(
+        &self,
+        children_digest: Option<&[u8]>,
+        new: &str,
+    ) -> Result<Vec<u8>, String> {
+        let mut digests = match children_digest {
+            Some(children_digest) => {
+                Record::read(children_digest, None).map_err(|e| e.to_string())?
+            }
+            None => Record::new(),
+        };
+        digests.push(new);
+        Ok(digests.encode())
     }
 }
 
@@ -65,6 +70,14 @@ mod tests {
         let _ = graph.add("dag4", Some("dag2"));
         let _ = graph.add("dag5", Some("dag4"));
 
+        // Database persists between runs, so only latest children are checked
+        let children = graph.db.get("dag2.downscending").unwrap().unwrap();
+        let children = Record::read(&children, None).unwrap().into_fields();
+        assert_eq!(
+            children[children.len() - 2..],
+            [b"dag3".to_vec(), b"dag4".to_vec()]
+        );
+
         /* dbg!(graph.db.get("dag1.upscending"));
         dbg!(graph.db.get("dag1.downscending"));
         dbg!(graph.db.get("dag2.upscending"));
@@ -1,4 +1,5 @@
 use crate::data_storage::DataStorage;
+use crate::record::Record;
 use oca_ast_semantics::ast::{self, RefValue};
 use oca_bundle_semantics::state::oca::{OCABox, OCABundle};
 use said::{derivation::HashFunctionCode, sad::SerializationFormats, version::Encode};
@@ -39,22 +40,14 @@ pub fn build_oca(
             let oca_bundle = oca_box.generate_bundle();
             let command_str = serde_json::to_string(&command).unwrap();
 
-            let mut input: Vec<u8> = vec![];
-            match base {
-                Some(ref mut base) => {
-                    let base_said = base.generate_bundle().said.unwrap().to_string();
-                    input.push(base_said.len().try_into().unwrap());
-                    input.extend(base_said.as_bytes());
-                }
-                None => {
-                    input.push(0);
-                }
-            }
-            input.push(command_str.len().try_into().unwrap());
-            input.extend(command_str.as_bytes());
+            let base_said = match base {
+                Some(ref mut base) => base.generate_bundle().said.unwrap().to_string(),
+                None => String::new(),
+            };
+            let input = Record::new().with_field(base_said).with_field(command_str);
             db.insert(
                 &format!("oca.{}.operation", oca_bundle.clone().said.unwrap()),
-                &input,
+                &input.encode(),
             )?;
             let code = HashFunctionCode::Blake3_256;
             let format = SerializationFormats::JSON;
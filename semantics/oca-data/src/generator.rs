@@ -0,0 +1,719 @@
+//! Seeded generation of synthetic records conforming to OCA bundle.
+//!
+//! Records satisfy capture base types, Conformance, EntryCode, Format
+//! (regex of text and range of numeric attributes), Cardinality and
+//! Conditional overlays, with references generated as nested records of
+//! provided dependencies. The same seed always gives the same records.
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, RefValue};
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    oca::OCABundle,
+    record::{
+        attributes_of, entry_codes_list, evaluate_condition, parse_cardinality, NumericRange,
+    },
+};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+const WORDS: &[&str] = &[
+    "alpha", "amber", "river", "stone", "maple", "cedar", "delta", "ember", "frost", "grove",
+    "harbor", "island", "jasper", "kernel", "lumen", "meadow", "nectar", "orbit", "prism",
+    "quartz", "raven", "solace", "tundra", "umber", "velvet", "willow", "zephyr",
+];
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Depth of nested references after which optional references are omitted.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("No text matching format {format:?} of attribute {attribute} could be generated")]
+    UnsatisfiableFormat { attribute: String, format: String },
+}
+
+/// Kind of deliberately broken constraint, named after
+/// [`record::Error`](oca_bundle_semantics::state::record::Error) reported
+/// for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Violation {
+    /// Mandatory attribute removed
+    MissingAttribute,
+    /// Attribute not in the capture base added
+    UnknownAttribute,
+    /// Value of other type than the attribute type
+    InvalidType,
+    /// Value out of entry codes
+    InvalidEntryCode,
+    /// Text not matching Format regex
+    FormatMismatch,
+    /// Number out of Format range
+    OutOfRange,
+    /// Array length out of Cardinality bounds
+    Cardinality,
+    /// Value of attribute which condition is not met
+    NotApplicable,
+}
+
+/// Violation injected into generated record.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InjectedViolation {
+    pub violation: Violation,
+    /// Name of the broken attribute
+    pub attribute: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GeneratedRecord {
+    pub record: Value,
+    /// Violations injected into the record. Violations which no attribute
+    /// of the bundle can express are skipped.
+    pub violations: Vec<InjectedViolation>,
+}
+
+pub struct RecordGenerator<'a> {
+    bundle: &'a OCABundle,
+    dependencies: &'a [OCABundle],
+    seed: u64,
+    omit_rate: f64,
+    violations: Vec<Violation>,
+}
+
+impl<'a> RecordGenerator<'a> {
+    pub fn new(bundle: &'a OCABundle) -> Self {
+        Self {
+            bundle,
+            dependencies: &[],
+            seed: 0,
+            omit_rate: 0.2,
+            violations: vec![],
+        }
+    }
+
+    /// Bundles referenced by `refs:` attributes, generated as nested records.
+    pub fn with_dependencies(mut self, dependencies: &'a [OCABundle]) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Probability of omitting optional attribute, 0.2 by default. Rates
+    /// out of `0..=1` are clamped and NaN leaves the rate unchanged.
+    pub fn omit_rate(mut self, rate: f64) -> Self {
+        if !rate.is_nan() {
+            self.omit_rate = rate.clamp(0.0, 1.0);
+        }
+        self
+    }
+
+    /// Injects the violation into every generated record.
+    pub fn inject(mut self, violation: Violation) -> Self {
+        self.violations.push(violation);
+        self
+    }
+
+    pub fn generate(&self, count: usize) -> Result<Vec<Value>, Error> {
+        Ok(self
+            .generate_annotated(count)?
+            .into_iter()
+            .map(|generated| generated.record)
+            .collect())
+    }
+
+    /// Generates records along with the violations injected into them.
+    /// Fails when text matching Format regex of some attribute can't be
+    /// generated, rather than giving records which violate it.
+    pub fn generate_annotated(&self, count: usize) -> Result<Vec<GeneratedRecord>, Error> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        (0..count)
+            .map(|_| {
+                let mut record = self.object(self.bundle, &mut rng, 0)?;
+                let mut violations = vec![];
+                for violation in &self.violations {
+                    violations.extend(self.violate(*violation, &mut record, &mut rng)?);
+                }
+                Ok(GeneratedRecord {
+                    record: Value::Object(record),
+                    violations,
+                })
+            })
+            .collect()
+    }
+
+    fn object(
+        &self,
+        bundle: &OCABundle,
+        rng: &mut ChaCha8Rng,
+        depth: usize,
+    ) -> Result<Map<String, Value>, Error> {
+        let attributes = attributes_of(bundle);
+        let mut object = Map::new();
+        for attribute in &attributes {
+            let required = attribute.conformance.as_deref() == Some("M");
+            if !required && (depth >= MAX_DEPTH || rng.gen_bool(self.omit_rate)) {
+                continue;
+            }
+            if let Some(value) = self.attribute_value(attribute, rng, depth)? {
+                object.insert(attribute.name.clone(), value);
+            }
+        }
+
+        // Conditions may depend on attributes which are conditional
+        // themselves, so apply them until nothing changes.
+        for _ in 0..=attributes.len() {
+            let mut changed = false;
+            for attribute in attributes.iter().filter(|a| a.condition.is_some()) {
+                let applicable = evaluate_condition(attribute, &object).unwrap_or(false);
+                let present = object.contains_key(&attribute.name);
+                if !applicable && present {
+                    object.remove(&attribute.name);
+                    changed = true;
+                } else if applicable && !present && attribute.conformance.as_deref() == Some("M") {
+                    if let Some(value) = self.attribute_value(attribute, rng, depth)? {
+                        object.insert(attribute.name.clone(), value);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        Ok(object)
+    }
+
+    /// Value of the attribute, none when its type or referenced bundle is
+    /// unknown.
+    fn attribute_value(
+        &self,
+        attribute: &Attribute,
+        rng: &mut ChaCha8Rng,
+        depth: usize,
+    ) -> Result<Option<Value>, Error> {
+        match attribute.attribute_type.as_ref() {
+            Some(attr_type) => self.value(attribute, attr_type, rng, depth),
+            None => Ok(None),
+        }
+    }
+
+    fn value(
+        &self,
+        attribute: &Attribute,
+        attr_type: &NestedAttrType,
+        rng: &mut ChaCha8Rng,
+        depth: usize,
+    ) -> Result<Option<Value>, Error> {
+        match attr_type {
+            NestedAttrType::Null => Ok(Some(Value::Null)),
+            NestedAttrType::Value(attribute_type) => {
+                scalar(attribute, attribute_type, rng).map(Some)
+            }
+            NestedAttrType::Reference(reference) => match self.find_dependency(reference) {
+                Some(dependency) => Ok(Some(Value::Object(self.object(
+                    dependency,
+                    rng,
+                    depth + 1,
+                )?))),
+                None => Ok(None),
+            },
+            NestedAttrType::Array(element_type) => {
+                let (min, max) = attribute
+                    .cardinality
+                    .as_deref()
+                    .and_then(parse_cardinality)
+                    .unwrap_or((0, None));
+                let max = max.unwrap_or(min.max(1) + 2).max(min);
+                let count = rng.gen_range(min..=max);
+                self.elements(attribute, element_type, count, rng, depth)
+            }
+        }
+    }
+
+    fn elements(
+        &self,
+        attribute: &Attribute,
+        element_type: &NestedAttrType,
+        count: usize,
+        rng: &mut ChaCha8Rng,
+        depth: usize,
+    ) -> Result<Option<Value>, Error> {
+        // Entry codes are picked without repetition while possible
+        if let (NestedAttrType::Value(attribute_type), Some(codes)) = (
+            element_type,
+            attribute.entry_codes.as_ref().and_then(entry_codes_list),
+        ) {
+            if count <= codes.len() {
+                return Ok(Some(Value::Array(
+                    codes
+                        .choose_multiple(rng, count)
+                        .map(|code| code_value(code, attribute_type))
+                        .collect(),
+                )));
+            }
+        }
+        let mut elements = Vec::with_capacity(count);
+        for _ in 0..count {
+            match self.value(attribute, element_type, rng, depth)? {
+                Some(element) => elements.push(element),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(Value::Array(elements)))
+    }
+
+    fn find_dependency(&self, reference: &RefValue) -> Option<&'a OCABundle> {
+        match reference {
+            RefValue::Said(said) => self
+                .dependencies
+                .iter()
+                .find(|dependency| dependency.said.as_ref() == Some(said)),
+            RefValue::Name(_) => None,
+        }
+    }
+
+    /// Breaks the record so that it violates given constraint of randomly
+    /// chosen attribute, if any attribute can express the violation.
+    fn violate(
+        &self,
+        violation: Violation,
+        record: &mut Map<String, Value>,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<Option<InjectedViolation>, Error> {
+        let attributes = attributes_of(self.bundle);
+        let scalar_type = |attribute: &Attribute| match &attribute.attribute_type {
+            Some(NestedAttrType::Value(attribute_type)) => Some(*attribute_type),
+            _ => None,
+        };
+
+        if violation == Violation::UnknownAttribute {
+            let mut name = "unknown_attribute".to_string();
+            while attributes.iter().any(|attribute| attribute.name == name) {
+                name.push('_');
+            }
+            record.insert(name.clone(), Value::Bool(true));
+            return Ok(Some(InjectedViolation {
+                violation,
+                attribute: name,
+            }));
+        }
+
+        let candidates = attributes
+            .iter()
+            .filter(|attribute| {
+                let present = record.contains_key(&attribute.name);
+                match violation {
+                    Violation::MissingAttribute => {
+                        present
+                            && attribute.conformance.as_deref() == Some("M")
+                            && attribute.condition.is_none()
+                    }
+                    Violation::InvalidType => present && attribute.attribute_type.is_some(),
+                    Violation::InvalidEntryCode => {
+                        present
+                            && scalar_type(attribute).is_some()
+                            && attribute
+                                .entry_codes
+                                .as_ref()
+                                .and_then(entry_codes_list)
+                                .is_some()
+                    }
+                    Violation::FormatMismatch => {
+                        present
+                            && scalar_type(attribute) == Some(AttributeType::Text)
+                            && mismatching_text(attribute).is_some()
+                    }
+                    Violation::OutOfRange => {
+                        present
+                            && scalar_type(attribute) == Some(AttributeType::Numeric)
+                            && attribute
+                                .format
+                                .as_deref()
+                                .and_then(NumericRange::parse)
+                                .map(|range| range.minimum.is_some() || range.maximum.is_some())
+                                .unwrap_or(false)
+                    }
+                    Violation::Cardinality => {
+                        present
+                            && matches!(attribute.attribute_type, Some(NestedAttrType::Array(_)))
+                            && attribute
+                                .cardinality
+                                .as_deref()
+                                .and_then(parse_cardinality)
+                                .map(|(min, max)| min > 0 || max.is_some())
+                                .unwrap_or(false)
+                    }
+                    Violation::NotApplicable => {
+                        !present
+                            && attribute.condition.is_some()
+                            && evaluate_condition(attribute, record) == Ok(false)
+                    }
+                    Violation::UnknownAttribute => false,
+                }
+            })
+            .collect::<Vec<_>>();
+        let Some(attribute) = candidates.choose(rng).copied() else {
+            return Ok(None);
+        };
+        let name = attribute.name.clone();
+
+        match violation {
+            Violation::MissingAttribute => {
+                record.remove(&name);
+            }
+            Violation::InvalidType => {
+                let value = match &attribute.attribute_type {
+                    Some(NestedAttrType::Value(AttributeType::Numeric)) => {
+                        Value::String("not a number".to_string())
+                    }
+                    Some(NestedAttrType::Value(_)) => Value::from(42),
+                    _ => Value::String("not an object".to_string()),
+                };
+                record.insert(name.clone(), value);
+            }
+            Violation::InvalidEntryCode => {
+                let codes = attribute
+                    .entry_codes
+                    .as_ref()
+                    .and_then(entry_codes_list)
+                    .unwrap_or_default();
+                let mut code = "invalid".to_string();
+                while codes.contains(&code) {
+                    code.push('_');
+                }
+                let value = match scalar_type(attribute) {
+                    Some(AttributeType::Numeric) => {
+                        let max = codes
+                            .iter()
+                            .filter_map(|code| code.parse::<f64>().ok())
+                            .fold(0.0, f64::max);
+                        Value::from(max.floor() as i64 + 1)
+                    }
+                    _ => Value::String(code),
+                };
+                record.insert(name.clone(), value);
+            }
+            Violation::FormatMismatch => {
+                let Some(text) = mismatching_text(attribute) else {
+                    return Ok(None);
+                };
+                record.insert(name.clone(), Value::String(text));
+            }
+            Violation::OutOfRange => {
+                let range = attribute.format.as_deref().and_then(NumericRange::parse);
+                let value = match range.map(|range| (range.minimum, range.maximum)) {
+                    Some((Some(minimum), _)) => minimum - 1.0,
+                    Some((None, Some(maximum))) => maximum + 1.0,
+                    _ => return Ok(None),
+                };
+                record.insert(name.clone(), number(value));
+            }
+            Violation::Cardinality => {
+                let Some(NestedAttrType::Array(element_type)) = &attribute.attribute_type else {
+                    return Ok(None);
+                };
+                let Some((min, max)) = attribute.cardinality.as_deref().and_then(parse_cardinality)
+                else {
+                    return Ok(None);
+                };
+                let count = match max {
+                    Some(max) => max + 1,
+                    None => min - 1,
+                };
+                let Some(value) = self.elements(attribute, element_type, count, rng, 0)? else {
+                    return Ok(None);
+                };
+                record.insert(name.clone(), value);
+            }
+            Violation::NotApplicable => {
+                let Some(value) = self.attribute_value(attribute, rng, 0)? else {
+                    return Ok(None);
+                };
+                record.insert(name.clone(), value);
+            }
+            Violation::UnknownAttribute => (),
+        }
+        Ok(Some(InjectedViolation {
+            violation,
+            attribute: name,
+        }))
+    }
+}
+
+fn scalar(
+    attribute: &Attribute,
+    attribute_type: &AttributeType,
+    rng: &mut ChaCha8Rng,
+) -> Result<Value, Error> {
+    if let Some(codes) = attribute.entry_codes.as_ref().and_then(entry_codes_list) {
+        if let Some(code) = codes.choose(rng) {
+            return Ok(code_value(code, attribute_type));
+        }
+    }
+    let format = attribute.format.as_deref();
+    Ok(match attribute_type {
+        AttributeType::Boolean => Value::Bool(rng.gen()),
+        AttributeType::Numeric => match format.and_then(NumericRange::parse) {
+            Some(range) => number_in_range(&range, rng),
+            None => Value::from(rng.gen_range(0..=100)),
+        },
+        AttributeType::Text => Value::String(match format {
+            Some(format) => {
+                text_matching(format, rng).ok_or_else(|| Error::UnsatisfiableFormat {
+                    attribute: attribute.name.clone(),
+                    format: format.to_string(),
+                })?
+            }
+            None => words(rng),
+        }),
+        AttributeType::DateTime => {
+            Value::String(date_time(format.unwrap_or("YYYY-MM-DDThh:mm:ssZ"), rng))
+        }
+        AttributeType::Binary => {
+            let length = rng.gen_range(2..=8) * 4;
+            Value::String(
+                (0..length)
+                    .map(|_| *BASE64.choose(rng).unwrap() as char)
+                    .collect(),
+            )
+        }
+    })
+}
+
+/// Entry codes are strings, kept as numbers for numeric attributes.
+fn code_value(code: &str, attribute_type: &AttributeType) -> Value {
+    match (attribute_type, code.parse::<f64>()) {
+        (AttributeType::Numeric, Ok(number)) => self::number(number),
+        _ => Value::String(code.to_string()),
+    }
+}
+
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+fn number_in_range(range: &NumericRange, rng: &mut ChaCha8Rng) -> Value {
+    let low = range
+        .minimum
+        .unwrap_or_else(|| range.maximum.map(|max| max - 100.0).unwrap_or(0.0));
+    let high = range.maximum.unwrap_or(low + 100.0).max(low);
+    for _ in 0..16 {
+        // Integers are preferred, unless the range has none
+        let candidate = if high - low >= 2.0 || (range.integer && low.ceil() <= high.floor()) {
+            rng.gen_range(low.ceil() as i64..=high.floor() as i64) as f64
+        } else {
+            let candidate: f64 = rng.gen_range(low..=high);
+            (candidate * 100.0).round() / 100.0
+        };
+        if range.contains(candidate) {
+            return number(candidate);
+        }
+    }
+    let middle = (low + high) / 2.0;
+    number(if range.integer {
+        middle.round()
+    } else {
+        middle
+    })
+}
+
+fn words(rng: &mut ChaCha8Rng) -> String {
+    let count = rng.gen_range(1..=2);
+    WORDS
+        .choose_multiple(rng, count)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Random text matching the regex, checked against the regex as validators
+/// see it.
+fn text_matching(format: &str, rng: &mut ChaCha8Rng) -> Option<String> {
+    let re = regex::Regex::new(format).ok()?;
+    let pattern = format.trim_start_matches('^').trim_end_matches('$');
+    let generator = rand_regex::Regex::compile(format, 8)
+        .or_else(|_| rand_regex::Regex::compile(pattern, 8))
+        .ok()?;
+    (0..16)
+        .map(|_| rng.sample::<String, _>(&generator))
+        .find(|text| re.is_match(text))
+}
+
+/// Text violating Format regex of the attribute.
+fn mismatching_text(attribute: &Attribute) -> Option<String> {
+    let re = regex::Regex::new(attribute.format.as_deref()?).ok()?;
+    ["", " ", "#", "0", "invalid value", "INVALID-VALUE"]
+        .iter()
+        .find(|text| !re.is_match(text))
+        .map(|text| text.to_string())
+}
+
+/// Date time in OCA format, with `YYYY`, `MM`, `DD`, `hh`, `mm` and `ss`
+/// tokens.
+fn date_time(format: &str, rng: &mut ChaCha8Rng) -> String {
+    let tokens = [
+        ("YYYY", format!("{:04}", rng.gen_range(1950..=2030))),
+        ("MM", format!("{:02}", rng.gen_range(1..=12))),
+        ("DD", format!("{:02}", rng.gen_range(1..=28))),
+        ("hh", format!("{:02}", rng.gen_range(0..=23))),
+        ("mm", format!("{:02}", rng.gen_range(0..=59))),
+        ("ss", format!("{:02}", rng.gen_range(0..=59))),
+    ];
+    let mut result = String::new();
+    let mut rest = format;
+    'outer: while !rest.is_empty() {
+        for (token, value) in &tokens {
+            if let Some(after) = rest.strip_prefix(token) {
+                result.push_str(value);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        let mut chars = rest.chars();
+        result.extend(chars.next());
+        rest = chars.as_str();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::{build::from_ast, state::record::RecordValidator};
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    fn bundles() -> (OCABundle, OCABundle) {
+        let address = build(
+            r#"
+ADD ATTRIBUTE street=Text zip=Text
+ADD CONFORMANCE ATTRS street="M" zip="M"
+ADD FORMAT ATTRS zip="^[0-9]{2}-[0-9]{3}$"
+"#,
+        );
+        let person = build(&format!(
+            r#"
+ADD ATTRIBUTE name=Text age=Numeric sex=Text pregnant=Boolean born=DateTime photo=Binary
+ADD ATTRIBUTE languages=Array[Text] addresses=Array[refs:{}]
+ADD CONFORMANCE ATTRS name="M" age="M" sex="M" pregnant="M" addresses="M"
+ADD FORMAT ATTRS name="^[A-Z][a-z]+$" age="[18,65)" born="YYYY-MM-DD"
+ADD ENTRY_CODE ATTRS sex=["F", "M"] languages=["en", "pl", "de"]
+ADD CARDINALITY ATTRS languages="1-2" addresses="1-3"
+ADD CONDITION ATTRS pregnant="${{sex}} == 'F'"
+"#,
+            address.said.clone().unwrap()
+        ));
+        (person, address)
+    }
+
+    #[test]
+    fn generate_valid_records() {
+        let (person, address) = bundles();
+        let dependencies = vec![address];
+        let generator = RecordGenerator::new(&person)
+            .with_dependencies(&dependencies)
+            .seed(7);
+        let records = generator.generate(50).unwrap();
+        assert_eq!(records, generator.generate(50).unwrap());
+        assert_ne!(records, generator.seed(8).generate(50).unwrap());
+
+        let validator = RecordValidator::new(&person).with_dependencies(&dependencies);
+        for record in &records {
+            assert_eq!(validator.validate(record), Ok(()), "{record}");
+        }
+        assert!(records
+            .iter()
+            .any(|record| record.get("pregnant").is_some()));
+        assert!(records.iter().any(|record| record.get("photo").is_none()));
+        assert!(records.iter().all(|record| {
+            let born = record.get("born").and_then(Value::as_str);
+            born.map(|born| born.len() == 10).unwrap_or(true)
+        }));
+    }
+
+    #[test]
+    fn inject_violations() {
+        let (person, address) = bundles();
+        let dependencies = vec![address];
+        let validator = RecordValidator::new(&person).with_dependencies(&dependencies);
+        for violation in [
+            Violation::MissingAttribute,
+            Violation::UnknownAttribute,
+            Violation::InvalidType,
+            Violation::InvalidEntryCode,
+            Violation::FormatMismatch,
+            Violation::OutOfRange,
+            Violation::Cardinality,
+        ] {
+            let generated = RecordGenerator::new(&person)
+                .with_dependencies(&dependencies)
+                .omit_rate(0.0)
+                .inject(violation)
+                .generate_annotated(10)
+                .unwrap();
+            for generated in generated {
+                assert_eq!(generated.violations.len(), 1);
+                let errors = validator.validate(&generated.record).unwrap_err();
+                let expected = format!("{violation:?}");
+                assert!(
+                    errors.iter().any(|error| {
+                        serde_json::to_value(error).unwrap()["type"] == expected.as_str()
+                    }),
+                    "{violation:?}: {errors:?}"
+                );
+            }
+        }
+
+        let generated = RecordGenerator::new(&person)
+            .with_dependencies(&dependencies)
+            .inject(Violation::NotApplicable)
+            .generate_annotated(20)
+            .unwrap();
+        assert!(generated.iter().any(|g| !g.violations.is_empty()));
+        for generated in generated.iter().filter(|g| !g.violations.is_empty()) {
+            assert_eq!(generated.record["sex"], "M");
+            assert!(generated.record.get("pregnant").is_some());
+        }
+    }
+
+    #[test]
+    fn reject_unsatisfiable_format() {
+        let bundle = build(
+            r#"
+ADD ATTRIBUTE code=Text
+ADD CONFORMANCE ATTRS code="M"
+ADD FORMAT ATTRS code="^a$b"
+"#,
+        );
+        let error = RecordGenerator::new(&bundle).generate(1).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UnsatisfiableFormat { ref attribute, .. } if attribute == "code"
+        ));
+    }
+
+    #[test]
+    fn ignore_nan_omit_rate() {
+        let (person, address) = bundles();
+        let dependencies = vec![address];
+        let generator = RecordGenerator::new(&person).with_dependencies(&dependencies);
+        assert_eq!(
+            generator.omit_rate(f64::NAN).generate(5).unwrap(),
+            RecordGenerator::new(&person)
+                .with_dependencies(&dependencies)
+                .generate(5)
+                .unwrap()
+        );
+    }
+}
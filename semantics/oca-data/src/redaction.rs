@@ -0,0 +1,423 @@
+//! Protection of flagged attributes of records.
+//!
+//! Every attribute flagged in the capture base is redacted, hashed or
+//! tokenized, also inside arrays and records of referenced bundles.
+use hmac::{Hmac, Mac};
+use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+use oca_bundle_semantics::state::{oca::OCABundle, record::attributes_of};
+use rand::Rng;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Line {line}: {message}")]
+    InvalidRecord { line: usize, message: String },
+    #[error("Bundle {reference} referenced at {path} is missing from dependencies")]
+    MissingDependency { reference: String, path: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// Replaces value with `null`
+    Redact,
+    /// Replaces value with hex encoded HMAC-SHA256 of its JSON, keyed with
+    /// the salt. Equal values give equal hashes, so records stay linkable.
+    Hash { salt: Vec<u8> },
+    /// Replaces value with random token. Equal values get the same token,
+    /// and the original values are kept in the token vault.
+    Tokenize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Action {
+    Redacted,
+    Hashed,
+    Tokenized,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// Path of the changed value, e.g. `addresses[0].street`
+    pub path: String,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Redacted {
+    pub record: Value,
+    pub changes: Vec<Change>,
+}
+
+/// Totals of [`Redactor::redact_jsonl`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Summary {
+    pub records: usize,
+    pub changes: usize,
+}
+
+pub struct Redactor<'a> {
+    bundle: &'a OCABundle,
+    dependencies: &'a [OCABundle],
+    method: Method,
+    /// Original values by token
+    vault: HashMap<String, Value>,
+    /// Tokens by JSON of original values
+    tokens: HashMap<String, String>,
+}
+
+impl<'a> Redactor<'a> {
+    pub fn new(bundle: &'a OCABundle, method: Method) -> Self {
+        Self {
+            bundle,
+            dependencies: &[],
+            method,
+            vault: HashMap::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Bundles referenced by `refs:` attributes, which flagged attributes
+    /// are processed in nested records.
+    pub fn with_dependencies(mut self, dependencies: &'a [OCABundle]) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Original values by token, for [`Method::Tokenize`].
+    pub fn token_vault(&self) -> &HashMap<String, Value> {
+        &self.vault
+    }
+
+    /// Fails when the record nests a record of a bundle which is missing
+    /// from dependencies, as its flagged attributes can't be known.
+    pub fn redact(&mut self, record: &Value) -> Result<Redacted, Error> {
+        let mut record = record.clone();
+        let mut changes = vec![];
+        self.redact_object(self.bundle, &mut record, "", &mut changes)?;
+        Ok(Redacted { record, changes })
+    }
+
+    /// Reads records line by line and writes them with flagged attributes
+    /// protected. Empty lines are skipped. When `report` is given, changes
+    /// of each record are written to it as JSON line.
+    pub fn redact_jsonl(
+        &mut self,
+        reader: impl BufRead,
+        mut writer: impl Write,
+        mut report: Option<&mut dyn Write>,
+    ) -> Result<Summary, Error> {
+        let mut summary = Summary::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Value = serde_json::from_str(&line).map_err(|e| Error::InvalidRecord {
+                line: i + 1,
+                message: e.to_string(),
+            })?;
+            let redacted = self.redact(&record)?;
+            writeln!(writer, "{}", redacted.record)?;
+            if let Some(report) = report.as_mut() {
+                writeln!(
+                    report,
+                    "{}",
+                    serde_json::to_string(&redacted.changes).unwrap()
+                )?;
+            }
+            summary.records += 1;
+            summary.changes += redacted.changes.len();
+        }
+        writer.flush()?;
+        Ok(summary)
+    }
+
+    fn redact_object(
+        &mut self,
+        bundle: &OCABundle,
+        value: &mut Value,
+        path: &str,
+        changes: &mut Vec<Change>,
+    ) -> Result<(), Error> {
+        let Some(object) = value.as_object_mut() else {
+            return Ok(());
+        };
+        for attribute in attributes_of(bundle) {
+            let Some(value) = object.get_mut(&attribute.name).filter(|v| !v.is_null()) else {
+                continue;
+            };
+            let attr_path = if path.is_empty() {
+                attribute.name.clone()
+            } else {
+                format!("{path}.{}", attribute.name)
+            };
+            if attribute.is_flagged {
+                self.protect(value, &attr_path, changes);
+            } else if let Some(attr_type) = &attribute.attribute_type {
+                self.redact_nested(attr_type, value, &attr_path, changes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Descends into references of not flagged attribute.
+    fn redact_nested(
+        &mut self,
+        attr_type: &NestedAttrType,
+        value: &mut Value,
+        path: &str,
+        changes: &mut Vec<Change>,
+    ) -> Result<(), Error> {
+        match attr_type {
+            NestedAttrType::Reference(reference) => {
+                let dependencies = self.dependencies;
+                let dependency = match reference {
+                    RefValue::Said(said) => dependencies
+                        .iter()
+                        .find(|dependency| dependency.said.as_ref() == Some(said)),
+                    RefValue::Name(_) => None,
+                };
+                match dependency {
+                    Some(dependency) => self.redact_object(dependency, value, path, changes)?,
+                    None => {
+                        return Err(Error::MissingDependency {
+                            reference: reference.to_string(),
+                            path: path.to_string(),
+                        })
+                    }
+                }
+            }
+            NestedAttrType::Array(element_type) => {
+                if let Some(elements) = value.as_array_mut() {
+                    for (i, element) in elements.iter_mut().enumerate() {
+                        self.redact_nested(
+                            element_type,
+                            element,
+                            &format!("{path}[{i}]"),
+                            changes,
+                        )?;
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Protects value of flagged attribute. Elements of arrays are hashed
+    /// and tokenized one by one.
+    fn protect(&mut self, value: &mut Value, path: &str, changes: &mut Vec<Change>) {
+        match (&self.method, value.as_array_mut()) {
+            (Method::Redact, _) => {
+                *value = Value::Null;
+                changes.push(Change {
+                    path: path.to_string(),
+                    action: Action::Redacted,
+                });
+            }
+            (_, Some(elements)) => {
+                for (i, element) in elements.iter_mut().enumerate() {
+                    self.protect(element, &format!("{path}[{i}]"), changes);
+                }
+            }
+            (Method::Hash { salt }, None) => {
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(salt).expect("HMAC accepts any key length");
+                mac.update(value.to_string().as_bytes());
+                *value = Value::String(hex::encode(mac.finalize().into_bytes()));
+                changes.push(Change {
+                    path: path.to_string(),
+                    action: Action::Hashed,
+                });
+            }
+            (Method::Tokenize, None) => {
+                let key = value.to_string();
+                let token = match self.tokens.get(&key) {
+                    Some(token) => token.clone(),
+                    None => {
+                        let token = loop {
+                            let token =
+                                format!("tok_{}", hex::encode(rand::thread_rng().gen::<[u8; 8]>()));
+                            if !self.vault.contains_key(&token) {
+                                break token;
+                            }
+                        };
+                        self.tokens.insert(key, token.clone());
+                        self.vault.insert(token.clone(), value.clone());
+                        token
+                    }
+                };
+                *value = Value::String(token);
+                changes.push(Change {
+                    path: path.to_string(),
+                    action: Action::Tokenized,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+    use serde_json::json;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    fn bundles() -> (OCABundle, OCABundle) {
+        let contact = build(
+            r#"
+ADD ATTRIBUTE phone=Text kind=Text
+ADD FLAGGED_ATTRIBUTES phone
+"#,
+        );
+        let person = build(&format!(
+            r#"
+ADD ATTRIBUTE name=Text age=Numeric aliases=Array[Text] contacts=Array[refs:{}]
+ADD FLAGGED_ATTRIBUTES name
+ADD FLAGGED_ATTRIBUTES aliases
+"#,
+            contact.said.clone().unwrap()
+        ));
+        (person, contact)
+    }
+
+    fn record() -> Value {
+        json!({
+            "name": "Alice",
+            "age": 30,
+            "aliases": ["Al", "Alice"],
+            "contacts": [
+                { "phone": "123", "kind": "home" },
+                { "kind": "work" }
+            ]
+        })
+    }
+
+    #[test]
+    fn redact_flagged_attributes() {
+        let (person, contact) = bundles();
+        let dependencies = vec![contact];
+        let mut redactor = Redactor::new(&person, Method::Redact).with_dependencies(&dependencies);
+        let redacted = redactor.redact(&record()).unwrap();
+        assert_eq!(
+            redacted.record,
+            json!({
+                "name": null,
+                "age": 30,
+                "aliases": null,
+                "contacts": [
+                    { "phone": null, "kind": "home" },
+                    { "kind": "work" }
+                ]
+            })
+        );
+        assert_eq!(
+            redacted
+                .changes
+                .iter()
+                .map(|change| change.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["aliases", "contacts[0].phone", "name"]
+        );
+    }
+
+    #[test]
+    fn hash_and_tokenize() {
+        let (person, contact) = bundles();
+        let dependencies = vec![contact];
+        let salt = b"salt".to_vec();
+        let hashed = Redactor::new(&person, Method::Hash { salt: salt.clone() })
+            .with_dependencies(&dependencies)
+            .redact(&record())
+            .unwrap();
+        assert_eq!(hashed.record["aliases"][1], hashed.record["name"]);
+        assert_ne!(hashed.record["aliases"][0], hashed.record["name"]);
+        assert_eq!(hashed.changes.len(), 4);
+        let rehashed = Redactor::new(&person, Method::Hash { salt })
+            .with_dependencies(&dependencies)
+            .redact(&record())
+            .unwrap();
+        assert_eq!(hashed, rehashed);
+        let other_salt = Redactor::new(
+            &person,
+            Method::Hash {
+                salt: b"other".to_vec(),
+            },
+        )
+        .with_dependencies(&dependencies)
+        .redact(&record())
+        .unwrap();
+        assert_ne!(hashed.record["name"], other_salt.record["name"]);
+
+        let mut tokenizer =
+            Redactor::new(&person, Method::Tokenize).with_dependencies(&dependencies);
+        let tokenized = tokenizer.redact(&record()).unwrap();
+        assert_eq!(tokenized.record["aliases"][1], tokenized.record["name"]);
+        let token = tokenized.record["name"].as_str().unwrap();
+        assert_eq!(tokenizer.token_vault()[token], json!("Alice"));
+        let token = tokenized.record["contacts"][0]["phone"].as_str().unwrap();
+        assert_eq!(tokenizer.token_vault()[token], json!("123"));
+    }
+
+    #[test]
+    fn reject_missing_dependency() {
+        let (person, _) = bundles();
+        let error = Redactor::new(&person, Method::Redact)
+            .redact(&record())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::MissingDependency { ref path, .. } if path == "contacts[0]"
+        ));
+        // Records without nested ones need no dependencies
+        let redacted = Redactor::new(&person, Method::Redact)
+            .redact(&json!({ "name": "Alice", "contacts": [] }))
+            .unwrap();
+        assert_eq!(redacted.record["name"], Value::Null);
+    }
+
+    #[test]
+    fn redact_jsonl() {
+        let (person, _) = bundles();
+        let input = format!("{}\n\n{}\n", json!({"name": "Alice"}), json!({"age": 3}));
+        let mut output = vec![];
+        let mut report = vec![];
+        let summary = Redactor::new(&person, Method::Redact)
+            .redact_jsonl(input.as_bytes(), &mut output, Some(&mut report))
+            .unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                records: 2,
+                changes: 1
+            }
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"name\":null}\n{\"age\":3}\n"
+        );
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "[{\"path\":\"name\",\"action\":\"Redacted\"}]\n[]\n"
+        );
+
+        let error = Redactor::new(&person, Method::Redact)
+            .redact_jsonl("{}\nnot json\n".as_bytes(), std::io::sink(), None)
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidRecord { line: 2, .. }));
+    }
+}
@@ -0,0 +1,349 @@
+//! `#[derive(Oca)]` macro. Use it through `oca_bundle_semantics::derive`
+//! with `derive` feature enabled, which provides the traits the generated
+//! code implements.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields,
+    Lit, LitStr,
+};
+
+/// Builds OCA bundle out of a struct with named fields.
+///
+/// Container attributes: `#[oca(name(en = "..."), description(en = "..."),
+/// classification = "...")]`.
+///
+/// Field attributes: `#[oca(rename = "...", attribute_type = "DateTime",
+/// label(en = "..."), information(en = "..."), conformance = "M",
+/// unit = "kg", cardinality = "1-3", entry_codes = ["a", "b"], flagged)]`.
+///
+/// Attribute type is taken from the field type through `OcaType` trait,
+/// unless given with `attribute_type`. Conformance defaults to `O` for
+/// `Option` fields and to `M` otherwise.
+#[proc_macro_derive(Oca, attributes(oca))]
+pub fn derive_oca(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ContainerAttributes {
+    names: Vec<(String, LitStr)>,
+    descriptions: Vec<(String, LitStr)>,
+    classification: Option<LitStr>,
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    rename: Option<LitStr>,
+    attribute_type: Option<LitStr>,
+    labels: Vec<(String, LitStr)>,
+    informations: Vec<(String, LitStr)>,
+    conformance: Option<LitStr>,
+    unit: Option<LitStr>,
+    cardinality: Option<LitStr>,
+    entry_codes: Option<Vec<LitStr>>,
+    flagged: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "Oca can not be derived for generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "Oca can be derived only for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "Oca can be derived only for structs",
+            ))
+        }
+    };
+
+    let mut container = ContainerAttributes::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("oca"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                container.names.extend(translations(&meta)?);
+            } else if meta.path.is_ident("description") {
+                container.descriptions.extend(translations(&meta)?);
+            } else if meta.path.is_ident("classification") {
+                container.classification = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unsupported oca container attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut attributes = vec![];
+    let mut field_types = vec![];
+    for field in fields {
+        let mut attrs = FieldAttributes::default();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("oca"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("attribute_type") {
+                    attrs.attribute_type = Some(attribute_type(meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("label") {
+                    attrs.labels.extend(translations(&meta)?);
+                } else if meta.path.is_ident("information") {
+                    attrs.informations.extend(translations(&meta)?);
+                } else if meta.path.is_ident("conformance") {
+                    let conformance: LitStr = meta.value()?.parse()?;
+                    if !["M", "O"].contains(&conformance.value().as_str()) {
+                        return Err(syn::Error::new(
+                            conformance.span(),
+                            "conformance must be \"M\" or \"O\"",
+                        ));
+                    }
+                    attrs.conformance = Some(conformance);
+                } else if meta.path.is_ident("unit") {
+                    attrs.unit = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("cardinality") {
+                    attrs.cardinality = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("entry_codes") {
+                    let array: syn::ExprArray = meta.value()?.parse()?;
+                    let codes = array
+                        .elems
+                        .iter()
+                        .map(|elem| match elem {
+                            Expr::Lit(syn::ExprLit {
+                                lit: Lit::Str(code),
+                                ..
+                            }) => Ok(code.clone()),
+                            _ => Err(syn::Error::new(
+                                elem.span(),
+                                "entry code must be string literal",
+                            )),
+                        })
+                        .collect::<syn::Result<Vec<_>>>()?;
+                    attrs.entry_codes = Some(codes);
+                } else if meta.path.is_ident("flagged") {
+                    attrs.flagged = true;
+                } else {
+                    return Err(meta.error("unsupported oca field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let name = attrs
+            .rename
+            .as_ref()
+            .map(LitStr::value)
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let attribute_type = match &attrs.attribute_type {
+            Some(attribute_type) => quote! {
+                __private::serde_json::from_value::<__private::NestedAttrType>(
+                    __private::serde_json::Value::String(#attribute_type.to_string())
+                ).expect("attribute_type validated by the macro")
+            },
+            None => quote! { <#ty as __private::OcaType>::attribute_type() },
+        };
+        let conformance = match &attrs.conformance {
+            Some(conformance) => quote! { #conformance },
+            None if is_option(ty) => quote! { "O" },
+            None => quote! { "M" },
+        };
+        let labels = attrs.labels.iter().map(|(language, label)| {
+            quote! { attribute.set_label(__private::language(#language), #label.to_string()); }
+        });
+        let informations = attrs.informations.iter().map(|(language, information)| {
+            quote! {
+                attribute.set_information(__private::language(#language), #information.to_string());
+            }
+        });
+        let unit = attrs.unit.iter().map(|unit| {
+            quote! { attribute.set_unit(#unit.to_string()); }
+        });
+        let cardinality = attrs.cardinality.iter().map(|cardinality| {
+            quote! { attribute.set_cardinality(#cardinality.to_string()); }
+        });
+        let entry_codes = attrs.entry_codes.iter().map(|codes| {
+            quote! {
+                attribute.set_entry_codes(__private::EntryCodes::Array(
+                    vec![#(#codes.to_string()),*]
+                ));
+            }
+        });
+        let flagged = attrs.flagged.then(|| quote! { attribute.set_flagged(); });
+
+        attributes.push(quote! {
+            {
+                let mut attribute = __private::Attribute::new(#name.to_string());
+                attribute.set_attribute_type(#attribute_type);
+                attribute.set_conformance(#conformance.to_string());
+                #(#labels)*
+                #(#informations)*
+                #(#unit)*
+                #(#cardinality)*
+                #(#entry_codes)*
+                #flagged
+                oca_box.add_attribute(attribute);
+            }
+        });
+        if attrs.attribute_type.is_none() {
+            field_types.push(ty.clone());
+        }
+    }
+
+    let names = container.names.iter().map(|(language, name)| {
+        quote! {
+            oca_box.add_meta(__private::language(#language), "name".to_string(), #name.to_string());
+        }
+    });
+    let descriptions = container
+        .descriptions
+        .iter()
+        .map(|(language, description)| {
+            quote! {
+                oca_box.add_meta(
+                    __private::language(#language),
+                    "description".to_string(),
+                    #description.to_string(),
+                );
+            }
+        });
+    let classification = container.classification.iter().map(|classification| {
+        quote! { oca_box.add_classification(#classification.to_string()); }
+    });
+
+    let ident = &input.ident;
+    Ok(quote! {
+        const _: () = {
+            use ::oca_bundle_semantics::derive::__private;
+            #[allow(unused_imports)]
+            use __private::{
+                Cardinalitys, Conformances, EntryCodesOverlay, Information, Labels, Metas, Units,
+            };
+
+            impl ::oca_bundle_semantics::derive::Oca for #ident {
+                fn oca_box() -> __private::OCABox {
+                    let mut oca_box = __private::OCABox::new();
+                    #(#names)*
+                    #(#descriptions)*
+                    #(#classification)*
+                    #(#attributes)*
+                    oca_box
+                }
+
+                fn oca_bundle() -> __private::OCABundle {
+                    static BUNDLE: ::std::sync::OnceLock<__private::OCABundle> =
+                        ::std::sync::OnceLock::new();
+                    BUNDLE
+                        .get_or_init(|| Self::oca_box().generate_bundle())
+                        .clone()
+                }
+
+                fn oca_dependencies() -> Vec<__private::OCABundle> {
+                    let mut dependencies = vec![];
+                    #(
+                        dependencies.extend(<#field_types as __private::OcaType>::dependencies());
+                    )*
+                    __private::dedup(dependencies)
+                }
+            }
+
+            impl __private::OcaType for #ident {
+                fn attribute_type() -> __private::NestedAttrType {
+                    __private::NestedAttrType::Reference(__private::RefValue::Said(
+                        <Self as ::oca_bundle_semantics::derive::Oca>::oca_said(),
+                    ))
+                }
+
+                fn dependencies() -> Vec<__private::OCABundle> {
+                    let mut dependencies =
+                        <Self as ::oca_bundle_semantics::derive::Oca>::oca_dependencies();
+                    dependencies.push(<Self as ::oca_bundle_semantics::derive::Oca>::oca_bundle());
+                    dependencies
+                }
+            }
+        };
+    })
+}
+
+/// Parses `key(en = "...", pl = "...")` into ISO 639-1 codes and texts.
+fn translations(meta: &ParseNestedMeta) -> syn::Result<Vec<(String, LitStr)>> {
+    let mut translations = vec![];
+    meta.parse_nested_meta(|language| {
+        let code = language
+            .path
+            .get_ident()
+            .map(|ident| ident.to_string())
+            .unwrap_or_default();
+        if isolang::Language::from_639_1(&code).is_none() {
+            return Err(language.error("expected ISO 639-1 language code"));
+        }
+        translations.push((code, language.value()?.parse()?));
+        Ok(())
+    })?;
+    Ok(translations)
+}
+
+/// Checks that `attribute_type = "..."` names OCA attribute type, as
+/// written in OCAfile.
+fn attribute_type(attribute_type: LitStr) -> syn::Result<LitStr> {
+    serde_json::from_value::<oca_ast_semantics::ast::NestedAttrType>(serde_json::Value::String(
+        attribute_type.value(),
+    ))
+    .map_err(|e| {
+        syn::Error::new(
+            attribute_type.span(),
+            format!("invalid attribute_type: {e}"),
+        )
+    })?;
+    Ok(attribute_type)
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proc_macro2::Span;
+
+    #[test]
+    fn validate_attribute_type() {
+        for valid in ["DateTime", "refn:address"] {
+            assert!(attribute_type(LitStr::new(valid, Span::call_site())).is_ok());
+        }
+        let error = attribute_type(LitStr::new("Date", Span::call_site())).unwrap_err();
+        assert!(error.to_string().starts_with("invalid attribute_type"));
+    }
+}
@@ -0,0 +1,370 @@
+//! Frictionless Table Schema (`tableschema.json`) import and export.
+//!
+//! See <https://specs.frictionlessdata.io/table-schema/>.
+use crate::mapping::{Field, Import, Schema};
+use indexmap::IndexMap;
+use isolang::Language;
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, OCAAst};
+use oca_bundle_semantics::state::{oca::OCABundle, record::NumericRange};
+use serde_json::{json, Map, Value};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum Error {
+    #[error("Invalid table schema: {0}")]
+    InvalidSchema(String),
+    #[error("Field {field}: unsupported type `{field_type}`")]
+    UnsupportedType { field: String, field_type: String },
+}
+
+/// Frictionless date and time types mapped to OCA date time formats.
+const DATE_TIME_TYPES: [(&str, &str); 4] = [
+    ("date", "YYYY-MM-DD"),
+    ("time", "hh:mm:ss"),
+    ("year", "YYYY"),
+    ("yearmonth", "YYYY-MM"),
+];
+
+/// strptime directives mapped to OCA date time format tokens.
+const DATE_TIME_DIRECTIVES: [(&str, &str); 6] = [
+    ("%Y", "YYYY"),
+    ("%m", "MM"),
+    ("%d", "DD"),
+    ("%H", "hh"),
+    ("%M", "mm"),
+    ("%S", "ss"),
+];
+
+/// Parses table schema JSON and converts it into OCA AST.
+pub fn parse_table_schema(input: &str, language: Language) -> Result<OCAAst, Error> {
+    let table_schema: Value =
+        serde_json::from_str(input).map_err(|e| Error::InvalidSchema(e.to_string()))?;
+    Ok(from_table_schema(&table_schema)?.to_ast(language))
+}
+
+/// Exports OCA bundle as table schema, using overlays in given language.
+pub fn export_bundle(bundle: &OCABundle, language: Language) -> Value {
+    to_table_schema(&Schema::from_bundle(bundle, language))
+}
+
+pub fn from_table_schema(table_schema: &Value) -> Result<Import, Error> {
+    let fields = table_schema["fields"]
+        .as_array()
+        .ok_or_else(|| Error::InvalidSchema("missing `fields` array".to_string()))?;
+
+    let mut warnings = vec![];
+    let mut schema = Schema {
+        name: table_schema["title"]
+            .as_str()
+            .or(table_schema["name"].as_str())
+            .map(str::to_string),
+        description: table_schema["description"].as_str().map(str::to_string),
+        fields: vec![],
+    };
+    for field in fields {
+        schema.fields.push(import_field(field, &mut warnings)?);
+    }
+    Ok(Import { schema, warnings })
+}
+
+pub fn to_table_schema(schema: &Schema) -> Value {
+    let mut table_schema = Map::new();
+    if let Some(name) = &schema.name {
+        table_schema.insert("title".to_string(), json!(name));
+    }
+    if let Some(description) = &schema.description {
+        table_schema.insert("description".to_string(), json!(description));
+    }
+    table_schema.insert(
+        "fields".to_string(),
+        Value::Array(schema.fields.iter().map(export_field).collect()),
+    );
+    Value::Object(table_schema)
+}
+
+fn import_field(field: &Value, warnings: &mut Vec<String>) -> Result<Field, Error> {
+    let name = field["name"]
+        .as_str()
+        .ok_or_else(|| Error::InvalidSchema("field without `name`".to_string()))?
+        .to_string();
+    let field_type = field["type"].as_str().unwrap_or("string");
+    let format = field["format"]
+        .as_str()
+        .filter(|format| !matches!(*format, "default" | "any"));
+
+    let (attribute_type, date_format) = match field_type {
+        "string" if format == Some("binary") => (AttributeType::Binary, None),
+        "string" | "any" | "object" | "geopoint" | "geojson" | "duration" => {
+            (AttributeType::Text, None)
+        }
+        "number" | "integer" => (AttributeType::Numeric, None),
+        "boolean" => (AttributeType::Boolean, None),
+        "datetime" => (AttributeType::DateTime, format.map(from_strptime)),
+        "array" => (AttributeType::Text, None),
+        other => match DATE_TIME_TYPES.iter().find(|(t, _)| *t == other) {
+            Some((_, default_format)) => (
+                AttributeType::DateTime,
+                Some(
+                    format
+                        .map(from_strptime)
+                        .unwrap_or(default_format.to_string()),
+                ),
+            ),
+            None => {
+                return Err(Error::UnsupportedType {
+                    field: name,
+                    field_type: other.to_string(),
+                })
+            }
+        },
+    };
+    let attribute_type = match field_type {
+        "array" => NestedAttrType::Array(Box::new(NestedAttrType::Value(attribute_type))),
+        _ => NestedAttrType::Value(attribute_type),
+    };
+    if matches!(field_type, "object" | "geopoint" | "geojson" | "duration") {
+        warnings.push(format!("{name}: `{field_type}` type imported as Text"));
+    }
+
+    let mut result = Field::new(name.clone(), attribute_type);
+    result.label = field["title"].as_str().map(str::to_string);
+    result.description = field["description"].as_str().map(str::to_string);
+    result.format = date_format;
+
+    let constraints = &field["constraints"];
+    result.required = constraints["required"].as_bool().unwrap_or(false);
+    if let Some(pattern) = constraints["pattern"].as_str() {
+        result.format = Some(pattern.to_string());
+    }
+    if let Some(values) = constraints["enum"].as_array() {
+        result.entries = values.iter().map(|v| (value_to_code(v), None)).collect();
+    }
+    if let Some(categories) = field["categories"].as_array() {
+        result.entries = categories
+            .iter()
+            .map(|category| match category {
+                Value::Object(category) => (
+                    value_to_code(&category["value"]),
+                    category["label"].as_str().map(str::to_string),
+                ),
+                value => (value_to_code(value), None),
+            })
+            .collect::<IndexMap<_, _>>();
+    }
+    let minimum = constraints["minimum"].as_f64();
+    let maximum = constraints["maximum"].as_f64();
+    let integer = field_type == "integer";
+    if minimum.is_some() || maximum.is_some() || integer {
+        if field_type == "number" || integer {
+            result.range = Some(NumericRange {
+                minimum,
+                maximum,
+                integer,
+                ..Default::default()
+            });
+        } else {
+            warnings.push(format!(
+                "{name}: minimum/maximum ignored for `{field_type}` type"
+            ));
+        }
+    }
+    for constraint in ["unique", "minLength", "maxLength"] {
+        if !constraints[constraint].is_null() {
+            warnings.push(format!(
+                "{name}: `{constraint}` constraint is not supported"
+            ));
+        }
+    }
+    Ok(result)
+}
+
+fn export_field(field: &Field) -> Value {
+    let mut result = Map::new();
+    result.insert("name".to_string(), json!(field.name));
+    if let Some(label) = &field.label {
+        result.insert("title".to_string(), json!(label));
+    }
+    if let Some(description) = &field.description {
+        result.insert("description".to_string(), json!(description));
+    }
+
+    let (field_type, format) = match &field.attribute_type {
+        NestedAttrType::Array(_) => ("array", None),
+        NestedAttrType::Reference(_) => ("object", None),
+        NestedAttrType::Null => ("any", None),
+        NestedAttrType::Value(AttributeType::Text) => ("string", None),
+        NestedAttrType::Value(AttributeType::Binary) => ("string", Some("binary".to_string())),
+        NestedAttrType::Value(AttributeType::Numeric) => match field.range {
+            Some(range) if range.integer => ("integer", None),
+            _ => ("number", None),
+        },
+        NestedAttrType::Value(AttributeType::Boolean) => ("boolean", None),
+        NestedAttrType::Value(AttributeType::DateTime) => match &field.format {
+            None => ("datetime", None),
+            Some(format) => match DATE_TIME_TYPES.iter().find(|(_, f)| f == format) {
+                Some((field_type, _)) => (*field_type, None),
+                None => (date_time_type(format), Some(to_strptime(format))),
+            },
+        },
+    };
+    result.insert("type".to_string(), json!(field_type));
+    if let Some(format) = format {
+        result.insert("format".to_string(), json!(format));
+    }
+
+    let mut constraints = Map::new();
+    if field.required {
+        constraints.insert("required".to_string(), json!(true));
+    }
+    if field.value_type() == Some(AttributeType::Text) {
+        if let Some(pattern) = &field.format {
+            constraints.insert("pattern".to_string(), json!(pattern));
+        }
+    }
+    if let Some(range) = &field.range {
+        if let Some(minimum) = range.minimum {
+            constraints.insert("minimum".to_string(), json!(minimum));
+        }
+        if let Some(maximum) = range.maximum {
+            constraints.insert("maximum".to_string(), json!(maximum));
+        }
+    }
+    if !field.entries.is_empty() {
+        constraints.insert(
+            "enum".to_string(),
+            json!(field.entries.keys().collect::<Vec<_>>()),
+        );
+        if field.entries.values().any(Option::is_some) {
+            let categories = field
+                .entries
+                .iter()
+                .map(|(code, label)| json!({ "value": code, "label": label.as_deref().unwrap_or(code) }))
+                .collect::<Vec<_>>();
+            result.insert("categories".to_string(), Value::Array(categories));
+        }
+    }
+    if !constraints.is_empty() {
+        result.insert("constraints".to_string(), Value::Object(constraints));
+    }
+    Value::Object(result)
+}
+
+fn value_to_code(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn from_strptime(format: &str) -> String {
+    DATE_TIME_DIRECTIVES
+        .iter()
+        .fold(format.to_string(), |acc, (directive, token)| {
+            acc.replace(directive, token)
+        })
+}
+
+/// Frictionless type of custom date time format: `date` or `time` when it
+/// has only date or only time tokens, `datetime` otherwise.
+fn date_time_type(format: &str) -> &'static str {
+    let has = |tokens: &[&str]| tokens.iter().any(|token| format.contains(token));
+    match (has(&["YYYY", "MM", "DD"]), has(&["hh", "mm", "ss"])) {
+        (true, false) => "date",
+        (false, true) => "time",
+        _ => "datetime",
+    }
+}
+
+fn to_strptime(format: &str) -> String {
+    DATE_TIME_DIRECTIVES
+        .iter()
+        .fold(format.to_string(), |acc, (directive, token)| {
+            acc.replace(token, directive)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    const TABLE_SCHEMA: &str = r#"{
+        "title": "Survey",
+        "fields": [
+            { "name": "id", "type": "integer", "constraints": { "required": true, "unique": true } },
+            { "name": "code", "type": "string", "title": "Code", "constraints": { "pattern": "^[A-Z]{3}$" } },
+            { "name": "age", "type": "number", "constraints": { "minimum": 0, "maximum": 120 } },
+            { "name": "visited", "type": "date", "format": "%d/%m/%Y" },
+            { "name": "answer", "type": "integer", "constraints": { "enum": [1, 2] },
+              "categories": [{ "value": 1, "label": "Yes" }, { "value": 2, "label": "No" }] }
+        ]
+    }"#;
+
+    #[test]
+    fn import_table_schema() {
+        let table_schema: Value = serde_json::from_str(TABLE_SCHEMA).unwrap();
+        let import = from_table_schema(&table_schema).unwrap();
+        assert_eq!(
+            import.warnings,
+            vec!["id: `unique` constraint is not supported"]
+        );
+
+        let ast = import.to_ast(Language::Eng);
+        let bundle = from_ast(None, &ast).unwrap().oca_bundle;
+        let schema = Schema::from_bundle(&bundle, Language::Eng);
+        let field = |name: &str| schema.fields.iter().find(|f| f.name == name).unwrap();
+
+        assert_eq!(schema.name.as_deref(), Some("Survey"));
+        assert!(field("id").required);
+        assert_eq!(field("code").format.as_deref(), Some("^[A-Z]{3}$"));
+        assert_eq!(field("code").label.as_deref(), Some("Code"));
+        assert_eq!(field("age").range, NumericRange::parse("[0,120]"));
+        assert_eq!(field("visited").format.as_deref(), Some("DD/MM/YYYY"));
+        assert_eq!(
+            field("answer").entries,
+            IndexMap::from([
+                ("1".to_string(), Some("Yes".to_string())),
+                ("2".to_string(), Some("No".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let ast = parse_table_schema(TABLE_SCHEMA, Language::Eng).unwrap();
+        let bundle = from_ast(None, &ast).unwrap().oca_bundle;
+        let exported = export_bundle(&bundle, Language::Eng);
+
+        let fields = exported["fields"].as_array().unwrap();
+        let field = |name: &str| fields.iter().find(|f| f["name"] == name).unwrap();
+        assert_eq!(field("id")["type"], json!("integer"));
+        assert_eq!(field("age")["type"], json!("number"));
+        assert_eq!(field("answer")["type"], json!("integer"));
+        assert_eq!(field("visited")["type"], json!("date"));
+        assert_eq!(field("visited")["format"], json!("%d/%m/%Y"));
+        assert_eq!(
+            field("age")["constraints"],
+            json!({ "minimum": 0.0, "maximum": 120.0 })
+        );
+        assert_eq!(
+            field("answer")["categories"][0],
+            json!({ "value": "1", "label": "Yes" })
+        );
+
+        let reimported = parse_table_schema(&exported.to_string(), Language::Eng).unwrap();
+        let rebuilt = from_ast(None, &reimported).unwrap().oca_bundle;
+        assert_eq!(rebuilt.said, bundle.said);
+        assert_eq!(export_bundle(&rebuilt, Language::Eng), exported);
+    }
+
+    #[test]
+    fn reject_unsupported_type() {
+        let table_schema = json!({ "fields": [{ "name": "x", "type": "money" }] });
+        assert_eq!(
+            from_table_schema(&table_schema),
+            Err(Error::UnsupportedType {
+                field: "x".to_string(),
+                field_type: "money".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,6 @@
+pub mod frictionless;
+pub mod mapping;
+pub mod redcap;
+pub mod vc;
+
+pub use oca_bundle_semantics::state::oca::language_code;
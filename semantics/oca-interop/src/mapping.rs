@@ -0,0 +1,394 @@
+//! Format independent description of tabular data.
+//!
+//! Importers parse their input into [`Schema`] which is then turned into
+//! OCA AST, and exporters render [`Schema`] built from an OCA bundle, so both
+//! directions map overlays the same way.
+use crate::language_code;
+use indexmap::IndexMap;
+use isolang::Language;
+use oca_ast_semantics::ast::{
+    AttributeType, CaptureContent, Command, CommandType, Content, NestedAttrType, NestedValue,
+    OCAAst, ObjectKind, OverlayType,
+};
+use oca_bundle_semantics::state::{
+    entries::EntriesElement,
+    oca::{OCABox, OCABundle},
+    record::{attributes_of, entry_codes_list, NumericRange},
+};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub attribute_type: NestedAttrType,
+    /// Label overlay
+    pub label: Option<String>,
+    /// Information overlay
+    pub description: Option<String>,
+    /// Conformance overlay, `M` when required, `O` otherwise
+    pub required: bool,
+    pub flagged: bool,
+    /// Entry codes with their optional labels (Entry overlay)
+    pub entries: IndexMap<String, Option<String>>,
+    /// Format overlay of non numeric attributes: regex for text, date
+    /// pattern for date time and media type for binary attributes.
+    pub format: Option<String>,
+    /// Format overlay of numeric attributes
+    pub range: Option<NumericRange>,
+    pub unit: Option<String>,
+    /// Conditional overlay, with dependencies as `${attribute_name}`
+    pub condition: Option<String>,
+}
+
+/// Result of import, with information about source constructs which could
+/// not be expressed in OCA.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Import {
+    pub schema: Schema,
+    pub warnings: Vec<String>,
+}
+
+impl Import {
+    pub fn to_ast(&self, language: Language) -> OCAAst {
+        self.schema.to_ast(language)
+    }
+}
+
+impl Field {
+    pub fn new(name: String, attribute_type: NestedAttrType) -> Self {
+        Self {
+            name,
+            attribute_type,
+            label: None,
+            description: None,
+            required: false,
+            flagged: false,
+            entries: IndexMap::new(),
+            format: None,
+            range: None,
+            unit: None,
+            condition: None,
+        }
+    }
+
+    /// Value of the Format overlay for this field.
+    pub fn format_value(&self) -> Option<String> {
+        self.range
+            .map(|range| range.to_string())
+            .or_else(|| self.format.clone())
+    }
+
+    /// Scalar type of the field, or of array elements.
+    pub fn value_type(&self) -> Option<AttributeType> {
+        let mut attr_type = &self.attribute_type;
+        while let NestedAttrType::Array(element_type) = attr_type {
+            attr_type = element_type;
+        }
+        match attr_type {
+            NestedAttrType::Value(attribute_type) => Some(*attribute_type),
+            _ => None,
+        }
+    }
+}
+
+impl Schema {
+    /// Builds schema out of bundle attributes, taking language specific
+    /// overlays in given language. Fields are sorted by name.
+    pub fn from_bundle(bundle: &OCABundle, language: Language) -> Self {
+        let oca_box = OCABox::from(bundle.clone());
+        let meta = oca_box
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.get(&language))
+            .cloned()
+            .unwrap_or_default();
+
+        let fields = attributes_of(bundle)
+            .into_iter()
+            .filter_map(|attribute| {
+                let attribute_type = attribute.attribute_type.clone()?;
+                let mut field = Field::new(attribute.name.clone(), attribute_type);
+                field.label = attribute
+                    .labels
+                    .as_ref()
+                    .and_then(|labels| labels.get(&language).cloned());
+                field.description = attribute
+                    .informations
+                    .as_ref()
+                    .and_then(|info| info.get(&language).cloned());
+                field.required = attribute.conformance.as_deref() == Some("M");
+                field.flagged = attribute.is_flagged;
+                let labels = match attribute
+                    .entries
+                    .as_ref()
+                    .and_then(|entries| entries.get(&language))
+                {
+                    Some(EntriesElement::Object(labels)) => labels.clone(),
+                    _ => Default::default(),
+                };
+                if let Some(codes) = attribute.entry_codes.as_ref().and_then(entry_codes_list) {
+                    field.entries = codes
+                        .into_iter()
+                        .map(|code| {
+                            let label = labels.get(&code).cloned();
+                            (code, label)
+                        })
+                        .collect();
+                }
+                match (field.value_type(), attribute.format) {
+                    (Some(AttributeType::Numeric), Some(format)) => {
+                        field.range = NumericRange::parse(&format);
+                    }
+                    (_, format) => field.format = format,
+                }
+                field.unit = attribute.unit.clone();
+                field.condition = attribute.condition.as_ref().map(|condition| {
+                    named_condition(condition, attribute.dependencies.as_deref().unwrap_or(&[]))
+                });
+                Some(field)
+            })
+            .collect();
+
+        Self {
+            name: meta.get("name").cloned(),
+            description: meta.get("description").cloned(),
+            fields,
+        }
+    }
+
+    /// Generates OCA AST with capture base and overlays describing the
+    /// schema. Language specific overlays are added in given language.
+    pub fn to_ast(&self, language: Language) -> OCAAst {
+        let mut ast = OCAAst::new();
+        let lang = || {
+            let mut properties = IndexMap::new();
+            properties.insert(
+                "lang".to_string(),
+                NestedValue::Value(language_code(language)),
+            );
+            Some(properties)
+        };
+
+        ast.commands.push(Command {
+            kind: CommandType::Add,
+            object_kind: ObjectKind::CaptureBase(CaptureContent {
+                attributes: Some(
+                    self.fields
+                        .iter()
+                        .map(|field| (field.name.clone(), field.attribute_type.clone()))
+                        .collect(),
+                ),
+                properties: None,
+                flagged_attributes: None,
+            }),
+        });
+
+        let flagged = self
+            .fields
+            .iter()
+            .filter(|field| field.flagged)
+            .map(|field| field.name.clone())
+            .collect::<Vec<_>>();
+        if !flagged.is_empty() {
+            ast.commands.push(Command {
+                kind: CommandType::Add,
+                object_kind: ObjectKind::CaptureBase(CaptureContent {
+                    attributes: None,
+                    properties: None,
+                    flagged_attributes: Some(flagged),
+                }),
+            });
+        }
+
+        let mut meta = IndexMap::new();
+        if let Some(name) = &self.name {
+            meta.insert("name".to_string(), NestedValue::Value(name.clone()));
+        }
+        if let Some(description) = &self.description {
+            meta.insert(
+                "description".to_string(),
+                NestedValue::Value(description.clone()),
+            );
+        }
+        if !meta.is_empty() {
+            let mut properties = lang().unwrap_or_default();
+            properties.extend(meta);
+            push_overlay(
+                &mut ast,
+                OverlayType::Meta,
+                Some(properties),
+                IndexMap::new(),
+            );
+        }
+
+        let values = |f: &dyn Fn(&Field) -> Option<String>| {
+            self.fields
+                .iter()
+                .filter_map(|field| Some((field.name.clone(), NestedValue::Value(f(field)?))))
+                .collect::<IndexMap<_, _>>()
+        };
+
+        push_overlay(
+            &mut ast,
+            OverlayType::Label,
+            lang(),
+            values(&|field| field.label.clone()),
+        );
+        push_overlay(
+            &mut ast,
+            OverlayType::Information,
+            lang(),
+            values(&|field| field.description.clone()),
+        );
+        push_overlay(
+            &mut ast,
+            OverlayType::Conformance,
+            None,
+            values(&|field| Some(if field.required { "M" } else { "O" }.to_string())),
+        );
+        push_overlay(
+            &mut ast,
+            OverlayType::Format,
+            None,
+            values(&|field| field.format_value()),
+        );
+        push_overlay(
+            &mut ast,
+            OverlayType::Unit,
+            None,
+            values(&|field| field.unit.clone()),
+        );
+
+        let entry_codes = self
+            .fields
+            .iter()
+            .filter(|field| !field.entries.is_empty())
+            .map(|field| {
+                let codes = field
+                    .entries
+                    .keys()
+                    .map(|code| NestedValue::Value(code.clone()))
+                    .collect();
+                (field.name.clone(), NestedValue::Array(codes))
+            })
+            .collect();
+        push_overlay(&mut ast, OverlayType::EntryCode, None, entry_codes);
+
+        let entries = self
+            .fields
+            .iter()
+            .filter(|field| field.entries.values().any(|label| label.is_some()))
+            .map(|field| {
+                let labels = field
+                    .entries
+                    .iter()
+                    .map(|(code, label)| {
+                        let label = label.clone().unwrap_or_else(|| code.clone());
+                        (code.clone(), NestedValue::Value(label))
+                    })
+                    .collect();
+                (field.name.clone(), NestedValue::Object(labels))
+            })
+            .collect();
+        push_overlay(&mut ast, OverlayType::Entry, lang(), entries);
+
+        push_overlay(
+            &mut ast,
+            OverlayType::Conditional,
+            None,
+            values(&|field| field.condition.clone()),
+        );
+
+        ast
+    }
+}
+
+fn push_overlay(
+    ast: &mut OCAAst,
+    overlay_type: OverlayType,
+    properties: Option<IndexMap<String, NestedValue>>,
+    attributes: IndexMap<String, NestedValue>,
+) {
+    if attributes.is_empty() && overlay_type != OverlayType::Meta {
+        return;
+    }
+    ast.commands.push(Command {
+        kind: CommandType::Add,
+        object_kind: ObjectKind::Overlay(
+            overlay_type,
+            Content {
+                attributes: if attributes.is_empty() {
+                    None
+                } else {
+                    Some(attributes)
+                },
+                properties,
+            },
+        ),
+    });
+}
+
+/// Replaces `${i}` dependency indexes used in Conditional overlay with
+/// `${attribute_name}`.
+fn named_condition(condition: &str, dependencies: &[String]) -> String {
+    let re = regex::Regex::new(r"\$\{(\d+)\}").unwrap();
+    re.replace_all(condition, |caps: &regex::Captures| {
+        let name = caps[1]
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| dependencies.get(i))
+            .cloned()
+            .unwrap_or_else(|| caps[1].to_string());
+        format!("${{{name}}}")
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    #[test]
+    fn schema_round_trip() {
+        let mut sex = Field::new(
+            "sex".to_string(),
+            NestedAttrType::Value(AttributeType::Text),
+        );
+        sex.label = Some("Sex".to_string());
+        sex.required = true;
+        sex.entries = IndexMap::from([
+            ("1".to_string(), Some("Female".to_string())),
+            ("2".to_string(), Some("Male".to_string())),
+        ]);
+        let mut pregnant = Field::new(
+            "pregnant".to_string(),
+            NestedAttrType::Value(AttributeType::Boolean),
+        );
+        pregnant.condition = Some("${sex} == '1'".to_string());
+        let mut age = Field::new(
+            "age".to_string(),
+            NestedAttrType::Value(AttributeType::Numeric),
+        );
+        age.range = NumericRange::parse("[0,120]");
+        age.unit = Some("year".to_string());
+        age.flagged = true;
+
+        let schema = Schema {
+            name: Some("Patient".to_string()),
+            description: None,
+            fields: vec![age, pregnant, sex],
+        };
+        let bundle = from_ast(None, &schema.to_ast(Language::Eng))
+            .unwrap()
+            .oca_bundle;
+        assert_eq!(bundle.capture_base.flagged_attributes, vec!["age"]);
+        assert_eq!(Schema::from_bundle(&bundle, Language::Eng), schema);
+    }
+}
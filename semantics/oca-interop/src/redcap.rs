@@ -0,0 +1,352 @@
+//! REDCap data dictionary (CSV) import.
+//!
+//! Columns are recognized by their header, so dictionaries exported from
+//! different REDCap versions are supported as long as the headers are kept.
+use crate::mapping::{Field, Import, Schema};
+use isolang::Language;
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, OCAAst};
+use oca_bundle_semantics::state::record::NumericRange;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum Error {
+    #[error("Invalid CSV: {0}")]
+    Csv(String),
+    #[error("Missing `{0}` column")]
+    MissingColumn(String),
+    #[error("Line {line}: {message}")]
+    InvalidRow { line: u64, message: String },
+}
+
+const VARIABLE: &str = "variable / field name";
+const FORM: &str = "form name";
+const FIELD_TYPE: &str = "field type";
+const LABEL: &str = "field label";
+const CHOICES: &str = "choices, calculations, or slider labels";
+const NOTE: &str = "field note";
+const VALIDATION: &str = "text validation type or show slider number";
+const VALIDATION_MIN: &str = "text validation min";
+const VALIDATION_MAX: &str = "text validation max";
+const IDENTIFIER: &str = "identifier?";
+const BRANCHING_LOGIC: &str = "branching logic (show field only if...)";
+const REQUIRED: &str = "required field?";
+
+/// Text validation types mapped to OCA date time formats.
+const DATE_TIME_VALIDATIONS: [(&str, &str); 11] = [
+    ("date_ymd", "YYYY-MM-DD"),
+    ("date_mdy", "MM-DD-YYYY"),
+    ("date_dmy", "DD-MM-YYYY"),
+    ("datetime_ymd", "YYYY-MM-DD hh:mm"),
+    ("datetime_mdy", "MM-DD-YYYY hh:mm"),
+    ("datetime_dmy", "DD-MM-YYYY hh:mm"),
+    ("datetime_seconds_ymd", "YYYY-MM-DD hh:mm:ss"),
+    ("datetime_seconds_mdy", "MM-DD-YYYY hh:mm:ss"),
+    ("datetime_seconds_dmy", "DD-MM-YYYY hh:mm:ss"),
+    ("time", "hh:mm"),
+    ("time_mm_ss", "mm:ss"),
+];
+
+/// Parses data dictionary CSV and converts it into OCA AST.
+pub fn parse_data_dictionary_to_ast(
+    input: &str,
+    language: Language,
+) -> Result<(OCAAst, Vec<String>), Error> {
+    let import = parse_data_dictionary(input)?;
+    Ok((import.to_ast(language), import.warnings))
+}
+
+pub fn parse_data_dictionary(input: &str) -> Result<Import, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(input.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| Error::Csv(e.to_string()))?
+        .iter()
+        .map(|h| h.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let required_column = |name: &str| column(name).ok_or(Error::MissingColumn(name.to_string()));
+    let variable = required_column(VARIABLE)?;
+    let field_type = required_column(FIELD_TYPE)?;
+
+    let mut warnings = vec![];
+    let mut schema = Schema::default();
+    for record in reader.records() {
+        let record = record.map_err(|e| Error::Csv(e.to_string()))?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let name = cell(Some(variable)).ok_or(Error::InvalidRow {
+            line,
+            message: "missing variable name".to_string(),
+        })?;
+        if schema.name.is_none() {
+            schema.name = cell(column(FORM)).map(str::to_string);
+        }
+        let row = Row {
+            name,
+            field_type: cell(Some(field_type)).unwrap_or("text"),
+            validation: cell(column(VALIDATION)),
+            choices: cell(column(CHOICES)),
+            min: cell(column(VALIDATION_MIN)),
+            max: cell(column(VALIDATION_MAX)),
+        };
+        let Some(mut field) = row
+            .to_field()
+            .map_err(|message| Error::InvalidRow { line, message })?
+        else {
+            warnings.push(format!("{name}: `{}` field skipped", row.field_type));
+            continue;
+        };
+
+        field.label = cell(column(LABEL)).map(strip_html);
+        field.description = cell(column(NOTE)).map(strip_html);
+        field.required = cell(column(REQUIRED)).is_some_and(|v| v.eq_ignore_ascii_case("y"));
+        field.flagged = cell(column(IDENTIFIER)).is_some_and(|v| v.eq_ignore_ascii_case("y"));
+        if let Some(logic) = cell(column(BRANCHING_LOGIC)) {
+            match branching_logic_to_condition(logic) {
+                Ok(condition) => field.condition = Some(condition),
+                Err(reason) => warnings.push(format!("{name}: branching logic skipped, {reason}")),
+            }
+        }
+        schema.fields.push(field);
+    }
+
+    Ok(Import { schema, warnings })
+}
+
+struct Row<'a> {
+    name: &'a str,
+    field_type: &'a str,
+    validation: Option<&'a str>,
+    choices: Option<&'a str>,
+    min: Option<&'a str>,
+    max: Option<&'a str>,
+}
+
+impl Row<'_> {
+    /// Maps the row into field, `None` for fields not capturing any data.
+    fn to_field(&self) -> Result<Option<Field>, String> {
+        let value = |attribute_type| NestedAttrType::Value(attribute_type);
+        let mut format = None;
+        let mut entries = vec![];
+        let attribute_type = match self.field_type {
+            "descriptive" => return Ok(None),
+            "text" => match self.validation {
+                Some("integer" | "number") => value(AttributeType::Numeric),
+                Some(validation) if validation.starts_with("number_") => {
+                    value(AttributeType::Numeric)
+                }
+                Some(validation) => {
+                    match DATE_TIME_VALIDATIONS.iter().find(|(v, _)| *v == validation) {
+                        Some((_, date_format)) => {
+                            format = Some(date_format.to_string());
+                            value(AttributeType::DateTime)
+                        }
+                        None => value(AttributeType::Text),
+                    }
+                }
+                None => value(AttributeType::Text),
+            },
+            "notes" | "sql" => value(AttributeType::Text),
+            "calc" | "slider" => value(AttributeType::Numeric),
+            "file" => value(AttributeType::Binary),
+            "yesno" => {
+                entries = vec![
+                    ("1".to_string(), "Yes".to_string()),
+                    ("0".to_string(), "No".to_string()),
+                ];
+                value(AttributeType::Text)
+            }
+            "truefalse" => {
+                entries = vec![
+                    ("1".to_string(), "True".to_string()),
+                    ("0".to_string(), "False".to_string()),
+                ];
+                value(AttributeType::Text)
+            }
+            "dropdown" | "radio" | "checkbox" => {
+                entries = parse_choices(self.choices.unwrap_or_default())?;
+                if self.field_type == "checkbox" {
+                    NestedAttrType::Array(Box::new(value(AttributeType::Text)))
+                } else {
+                    value(AttributeType::Text)
+                }
+            }
+            other => return Err(format!("unknown field type `{other}` of {}", self.name)),
+        };
+
+        let mut field = Field::new(self.name.to_string(), attribute_type);
+        field.format = format;
+        field.entries = entries
+            .into_iter()
+            .map(|(code, label)| (code, Some(label)))
+            .collect();
+        if field.value_type() == Some(AttributeType::Numeric) {
+            let bound = |b: Option<&str>| b.and_then(|b| b.parse::<f64>().ok());
+            let (min, max) = match self.field_type {
+                "slider" => (Some(0.0), Some(100.0)),
+                _ => (bound(self.min), bound(self.max)),
+            };
+            if min.is_some() || max.is_some() {
+                field.range = Some(NumericRange {
+                    minimum: min,
+                    maximum: max,
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(Some(field))
+    }
+}
+
+/// Parses choices in `1, Yes | 0, No` notation into codes and labels.
+pub fn parse_choices(choices: &str) -> Result<Vec<(String, String)>, String> {
+    choices
+        .split('|')
+        .map(str::trim)
+        .filter(|choice| !choice.is_empty())
+        .map(|choice| {
+            let (code, label) = choice
+                .split_once(',')
+                .ok_or_else(|| format!("invalid choice `{choice}`, expected `code, label`"))?;
+            Ok((code.trim().to_string(), strip_html(label.trim())))
+        })
+        .collect()
+}
+
+/// Converts REDCap branching logic into Conditional overlay expression, e.g.
+/// `[sex] = '1' and [age] <> 18` into `${sex} == '1' and ${age} ~= 18`.
+/// Checkbox options (`[field(code)]`), events and smart variables are not
+/// supported.
+pub fn branching_logic_to_condition(logic: &str) -> Result<String, String> {
+    let variable = regex::Regex::new(r"^\[([A-Za-z0-9_]+)\]").unwrap();
+    let operator = regex::Regex::new(r"^(<>|!=|<=|>=|=|<|>)").unwrap();
+    let keyword = regex::Regex::new(r"(?i)^(and|or|not)\b").unwrap();
+
+    let mut condition = String::new();
+    let mut rest = logic.trim();
+    while let Some(c) = rest.chars().next() {
+        if c == '\'' || c == '"' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| "unterminated string".to_string())?;
+            condition.push_str(&rest[..end + 2]);
+            rest = &rest[end + 2..];
+        } else if c == '[' {
+            let caps = variable
+                .captures(rest)
+                .ok_or_else(|| format!("unsupported variable in `{logic}`"))?;
+            rest = &rest[caps[0].len()..];
+            if rest.starts_with('[') {
+                return Err(format!("events are not supported in `{logic}`"));
+            }
+            condition.push_str(&format!("${{{}}}", &caps[1]));
+        } else if let Some(op) = operator.find(rest) {
+            condition.push_str(match op.as_str() {
+                "<>" | "!=" => "~=",
+                "=" => "==",
+                other => other,
+            });
+            rest = &rest[op.end()..];
+        } else if let Some(word) = keyword.find(rest) {
+            condition.push_str(&word.as_str().to_lowercase());
+            rest = &rest[word.end()..];
+        } else if c.is_ascii_digit() || c.is_whitespace() || "().-+*/".contains(c) {
+            condition.push(c);
+            rest = &rest[c.len_utf8()..];
+        } else {
+            return Err(format!("unsupported expression `{rest}`"));
+        }
+    }
+    Ok(condition)
+}
+
+fn strip_html(text: &str) -> String {
+    let tags = regex::Regex::new(r"<[^>]*>").unwrap();
+    tags.replace_all(text, "").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+    use oca_bundle_semantics::state::oca::OCABox;
+
+    const DICTIONARY: &str = r#""Variable / Field Name","Form Name","Section Header","Field Type","Field Label","Choices, Calculations, OR Slider Labels","Field Note","Text Validation Type OR Show Slider Number","Text Validation Min","Text Validation Max","Identifier?","Branching Logic (Show field only if...)","Required Field?","Custom Alignment","Question Number (surveys only)","Matrix Group Name","Matrix Ranking?","Field Annotation"
+record_id,demographics,,text,Record ID,,,,,,,,,,,,,
+name,demographics,,text,Name,,,,,,y,,y,,,,,
+dob,demographics,,text,Date of birth,,,date_ymd,,,y,,,,,,,
+age,demographics,,text,Age,,years,integer,0,120,,,,,,,,
+sex,demographics,,radio,Sex,"1, Female | 2, Male",,,,,,,y,,,,,
+pregnant,demographics,,yesno,Pregnant?,,,,,,,[sex] = '1' AND [age] >= 12,,,,,,
+symptoms,demographics,,checkbox,Symptoms,"1, Fever | 2, Cough",,,,,,,,,,,,
+cough_days,demographics,,text,Days of cough,,,integer,,,,[symptoms(2)] = '1',,,,,,
+note,demographics,,descriptive,<b>Thank you</b>,,,,,,,,,,,,,
+"#;
+
+    #[test]
+    fn import_data_dictionary() {
+        let import = parse_data_dictionary(DICTIONARY).unwrap();
+        assert_eq!(import.warnings.len(), 2);
+        assert_eq!(import.schema.name.as_deref(), Some("demographics"));
+
+        let bundle = from_ast(None, &import.to_ast(Language::Eng))
+            .unwrap()
+            .oca_bundle;
+        let oca_box = OCABox::from(bundle.clone());
+        let attribute = |name: &str| oca_box.attributes.get(name).unwrap();
+
+        assert_eq!(bundle.capture_base.attributes.len(), 8);
+        let mut flagged = bundle.capture_base.flagged_attributes.clone();
+        flagged.sort();
+        assert_eq!(flagged, vec!["dob", "name"]);
+        assert_eq!(attribute("name").conformance.as_deref(), Some("M"));
+        assert_eq!(attribute("dob").format.as_deref(), Some("YYYY-MM-DD"));
+        assert_eq!(attribute("age").format.as_deref(), Some("[0,120]"));
+        assert_eq!(
+            attribute("age").informations.as_ref().unwrap()[&Language::Eng],
+            "years"
+        );
+        assert!(matches!(
+            attribute("symptoms").attribute_type,
+            Some(NestedAttrType::Array(_))
+        ));
+
+        let schema = Schema::from_bundle(&bundle, Language::Eng);
+        let field = |name: &str| schema.fields.iter().find(|f| f.name == name).unwrap();
+        assert_eq!(
+            field("sex").entries.get("2"),
+            Some(&Some("Male".to_string()))
+        );
+        assert_eq!(
+            field("pregnant").condition.as_deref(),
+            Some("${sex} == '1' and ${age} >= 12")
+        );
+        assert_eq!(field("cough_days").condition, None);
+    }
+
+    #[test]
+    fn convert_branching_logic() {
+        assert_eq!(
+            branching_logic_to_condition("[a] <> \"x = y\" or ([b] = 2 and [c] != 3)"),
+            Ok("${a} ~= \"x = y\" or (${b} == 2 and ${c} ~= 3)".to_string())
+        );
+        assert!(branching_logic_to_condition("[event_1_arm_1][a] = 1").is_err());
+        assert!(branching_logic_to_condition("datediff([a], 'today', 'y') > 1").is_err());
+    }
+
+    #[test]
+    fn reject_invalid_choices() {
+        let dictionary = "Variable / Field Name,Field Type,\"Choices, Calculations, OR Slider Labels\"\nsex,radio,Female\n";
+        assert!(matches!(
+            parse_data_dictionary(dictionary),
+            Err(Error::InvalidRow { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,497 @@
+//! W3C Verifiable Credentials support.
+//!
+//! Generates a JSON Schema usable as VC `credentialSchema` and a credential
+//! template out of an OCA bundle, and validates credentials against the
+//! bundle they claim to be issued for.
+use crate::language_code;
+use isolang::Language;
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, RefValue};
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    oca::{OCABox, OCABundle},
+    record::{
+        self, attributes_of, entry_codes_list, parse_cardinality, NumericRange, RecordValidator,
+    },
+};
+use said::SelfAddressingIdentifier;
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, str::FromStr};
+
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+pub const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+/// `type` of the `credentialSchema` entry pointing to the OCA bundle.
+pub const OCA_BUNDLE_SCHEMA_TYPE: &str = "OCABundle";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum Error {
+    #[error("Credential does not reference any OCA bundle")]
+    MissingBundleReference,
+    #[error("Invalid OCA bundle SAID: {0}")]
+    InvalidSaid(String),
+    #[error("Credential claims bundle {claimed}, but bundle {provided} was provided")]
+    BundleMismatch { claimed: String, provided: String },
+    #[error("Credential has no credentialSubject")]
+    MissingSubject,
+    #[error("Invalid credentialSubject: {0}")]
+    InvalidSubject(#[from] record::Error),
+}
+
+/// Identifier of the JSON Schema generated from the bundle.
+pub fn schema_id(bundle_said: &SelfAddressingIdentifier) -> String {
+    format!("urn:said:{bundle_said}")
+}
+
+/// Generates JSON Schema describing whole credential, which
+/// `credentialSubject` is built from capture base attributes.
+///
+/// Labels and information in given language are used as schema `title` and
+/// `description`. Referenced bundles are inlined when found in
+/// `dependencies`, otherwise they are referenced by `$ref`. Attributes with
+/// Conditional overlay rules are never listed as required, as the rules
+/// can't be expressed in JSON Schema.
+pub fn credential_schema(
+    bundle: &OCABundle,
+    dependencies: &[OCABundle],
+    language: Language,
+) -> Value {
+    let mut subject = object_schema(bundle, dependencies, language);
+    if let Some(properties) = subject["properties"].as_object_mut() {
+        properties.insert(
+            "id".to_string(),
+            json!({ "type": "string", "format": "uri" }),
+        );
+    }
+
+    let mut schema = Map::new();
+    schema.insert("$schema".to_string(), json!(JSON_SCHEMA_DIALECT));
+    if let Some(said) = &bundle.said {
+        schema.insert("$id".to_string(), json!(schema_id(said)));
+    }
+    let meta = bundle_meta(bundle, language);
+    if let Some(name) = meta.get("name") {
+        schema.insert("title".to_string(), json!(name));
+    }
+    if let Some(description) = meta.get("description") {
+        schema.insert("description".to_string(), json!(description));
+    }
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert(
+        "properties".to_string(),
+        json!({ "credentialSubject": subject }),
+    );
+    schema.insert("required".to_string(), json!(["credentialSubject"]));
+    Value::Object(schema)
+}
+
+/// Generates unsigned credential template. `credentialSubject` contains all
+/// capture base attributes set to `null`, `credentialSchema` references the
+/// generated JSON Schema and the OCA bundle SAID, and `display` holds labels
+/// and meta of every language available in the bundle.
+pub fn credential_template(bundle: &OCABundle) -> Value {
+    let said = bundle
+        .said
+        .as_ref()
+        .map(|said| said.to_string())
+        .unwrap_or_default();
+    let oca_box = OCABox::from(bundle.clone());
+
+    let mut credential_type = vec![json!("VerifiableCredential")];
+    let english_meta = bundle_meta(bundle, Language::Eng);
+    if let Some(name) = english_meta.get("name") {
+        credential_type.push(json!(type_name(name)));
+    }
+
+    let mut display = Map::new();
+    for language in bundle.languages() {
+        let mut hints = Map::new();
+        for (key, value) in bundle_meta(bundle, language) {
+            hints.insert(key, json!(value));
+        }
+        let mut labels = Map::new();
+        for attribute in attributes_of(bundle) {
+            if let Some(label) = attribute.labels.as_ref().and_then(|l| l.get(&language)) {
+                labels.insert(attribute.name.clone(), json!(label));
+            }
+        }
+        hints.insert("labels".to_string(), Value::Object(labels));
+        display.insert(language_code(language), Value::Object(hints));
+    }
+
+    let mut subject = Map::new();
+    for attribute in attributes_of(bundle) {
+        subject.insert(attribute.name, Value::Null);
+    }
+
+    let mut template = Map::new();
+    template.insert("@context".to_string(), json!([CREDENTIALS_CONTEXT]));
+    template.insert("type".to_string(), Value::Array(credential_type));
+    if let Some(name) = english_meta.get("name") {
+        template.insert("name".to_string(), json!(name));
+    }
+    if let Some(description) = english_meta.get("description") {
+        template.insert("description".to_string(), json!(description));
+    }
+    template.insert(
+        "credentialSchema".to_string(),
+        json!([
+            { "id": bundle.said.as_ref().map(schema_id).unwrap_or_default(), "type": "JsonSchema" },
+            { "id": said, "type": OCA_BUNDLE_SCHEMA_TYPE },
+        ]),
+    );
+    template.insert("credentialSubject".to_string(), Value::Object(subject));
+    if oca_box
+        .classification
+        .as_deref()
+        .is_some_and(|c| !c.is_empty())
+    {
+        template.insert("classification".to_string(), json!(oca_box.classification));
+    }
+    template.insert("display".to_string(), Value::Object(display));
+    Value::Object(template)
+}
+
+/// Returns SAID of the OCA bundle the credential claims to be issued for.
+///
+/// The bundle is found in `credentialSchema` entry of `OCABundle` type or,
+/// when missing, in identifier of the JSON Schema generated by
+/// [`credential_schema`].
+pub fn claimed_bundle(credential: &Value) -> Result<SelfAddressingIdentifier, Error> {
+    let schemas = match &credential["credentialSchema"] {
+        Value::Array(schemas) => schemas.clone(),
+        Value::Object(_) => vec![credential["credentialSchema"].clone()],
+        _ => vec![],
+    };
+    let bundle_id = schemas
+        .iter()
+        .find(|schema| schema["type"] == OCA_BUNDLE_SCHEMA_TYPE)
+        .and_then(|schema| schema["id"].as_str())
+        .or_else(|| {
+            schemas
+                .iter()
+                .filter_map(|schema| schema["id"].as_str())
+                .find_map(|id| id.strip_prefix("urn:said:"))
+        })
+        .ok_or(Error::MissingBundleReference)?;
+
+    SelfAddressingIdentifier::from_str(bundle_id)
+        .map_err(|_| Error::InvalidSaid(bundle_id.to_string()))
+}
+
+/// Checks that the credential claims given bundle and validates every
+/// credential subject against it. Subject `id` is not a bundle attribute and
+/// is skipped.
+pub fn validate_credential(
+    credential: &Value,
+    bundle: &OCABundle,
+    dependencies: &[OCABundle],
+) -> Result<(), Vec<Error>> {
+    let claimed = claimed_bundle(credential).map_err(|e| vec![e])?;
+    if bundle.said.as_ref() != Some(&claimed) {
+        return Err(vec![Error::BundleMismatch {
+            claimed: claimed.to_string(),
+            provided: bundle
+                .said
+                .as_ref()
+                .map(|said| said.to_string())
+                .unwrap_or_default(),
+        }]);
+    }
+
+    let subjects = match &credential["credentialSubject"] {
+        Value::Null => return Err(vec![Error::MissingSubject]),
+        Value::Array(subjects) => subjects.clone(),
+        subject => vec![subject.clone()],
+    };
+
+    let validator = RecordValidator::new(bundle).with_dependencies(dependencies);
+    let mut errors = vec![];
+    for mut subject in subjects {
+        if let Some(subject) = subject.as_object_mut() {
+            subject.remove("id");
+        }
+        if let Err(record_errors) = validator.validate(&subject) {
+            errors.extend(record_errors.into_iter().map(Error::InvalidSubject));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn object_schema(bundle: &OCABundle, dependencies: &[OCABundle], language: Language) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for attribute in attributes_of(bundle) {
+        let Some(attr_type) = &attribute.attribute_type else {
+            continue;
+        };
+        let mut schema = type_schema(&attribute, attr_type, dependencies, language);
+        if let Some(schema) = schema.as_object_mut() {
+            if let Some(label) = attribute.labels.as_ref().and_then(|l| l.get(&language)) {
+                schema.insert("title".to_string(), json!(label));
+            }
+            if let Some(info) = attribute
+                .informations
+                .as_ref()
+                .and_then(|i| i.get(&language))
+            {
+                schema.insert("description".to_string(), json!(info));
+            }
+        }
+        if attribute.conformance.as_deref() == Some("M") && attribute.condition.is_none() {
+            required.push(json!(attribute.name));
+        }
+        properties.insert(attribute.name.clone(), schema);
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn type_schema(
+    attribute: &Attribute,
+    attr_type: &NestedAttrType,
+    dependencies: &[OCABundle],
+    language: Language,
+) -> Value {
+    match attr_type {
+        NestedAttrType::Value(attribute_type) => scalar_schema(attribute, attribute_type),
+        NestedAttrType::Reference(RefValue::Said(said)) => {
+            match dependencies
+                .iter()
+                .find(|dep| dep.said.as_ref() == Some(said))
+            {
+                Some(dependency) => object_schema(dependency, dependencies, language),
+                None => json!({ "$ref": schema_id(said) }),
+            }
+        }
+        NestedAttrType::Reference(RefValue::Name(name)) => json!({ "$ref": format!("#{name}") }),
+        NestedAttrType::Array(element_type) => {
+            let mut schema = Map::new();
+            schema.insert("type".to_string(), json!("array"));
+            schema.insert(
+                "items".to_string(),
+                type_schema(attribute, element_type, dependencies, language),
+            );
+            if let Some((min, max)) = attribute.cardinality.as_deref().and_then(parse_cardinality) {
+                schema.insert("minItems".to_string(), json!(min));
+                if let Some(max) = max {
+                    schema.insert("maxItems".to_string(), json!(max));
+                }
+            }
+            Value::Object(schema)
+        }
+        NestedAttrType::Null => json!({ "type": "null" }),
+    }
+}
+
+fn scalar_schema(attribute: &Attribute, attribute_type: &AttributeType) -> Value {
+    let mut schema = Map::new();
+    let json_type = match attribute_type {
+        AttributeType::Boolean => "boolean",
+        AttributeType::Numeric => "number",
+        AttributeType::Text | AttributeType::DateTime | AttributeType::Binary => "string",
+    };
+    schema.insert("type".to_string(), json!(json_type));
+
+    match (attribute_type, &attribute.format) {
+        (AttributeType::Text, Some(format)) => {
+            schema.insert("pattern".to_string(), json!(format));
+        }
+        (AttributeType::Binary, Some(format)) => {
+            schema.insert("contentEncoding".to_string(), json!("base64"));
+            schema.insert("contentMediaType".to_string(), json!(format));
+        }
+        (AttributeType::Binary, None) => {
+            schema.insert("contentEncoding".to_string(), json!("base64"));
+        }
+        (AttributeType::Numeric, Some(format)) => {
+            if let Some(range) = NumericRange::parse(format) {
+                let (min_key, max_key) = (
+                    if range.exclusive_minimum {
+                        "exclusiveMinimum"
+                    } else {
+                        "minimum"
+                    },
+                    if range.exclusive_maximum {
+                        "exclusiveMaximum"
+                    } else {
+                        "maximum"
+                    },
+                );
+                if let Some(min) = range.minimum {
+                    schema.insert(min_key.to_string(), json!(min));
+                }
+                if let Some(max) = range.maximum {
+                    schema.insert(max_key.to_string(), json!(max));
+                }
+                if range.integer {
+                    schema.insert("type".to_string(), json!("integer"));
+                }
+            }
+        }
+        (AttributeType::DateTime, None) => {
+            schema.insert("format".to_string(), json!("date-time"));
+        }
+        _ => (),
+    }
+
+    if let Some(codes) = attribute.entry_codes.as_ref().and_then(entry_codes_list) {
+        let members = match attribute_type {
+            // Numbers match codes written the same way, as record
+            // validation compares them, so other codes can't be matched
+            AttributeType::Numeric => codes
+                .iter()
+                .filter_map(|code| {
+                    let number = serde_json::from_str::<serde_json::Number>(code).ok()?;
+                    (number.to_string() == *code).then_some(Value::Number(number))
+                })
+                .collect(),
+            _ => codes.into_iter().map(Value::String).collect(),
+        };
+        schema.insert("enum".to_string(), Value::Array(members));
+    }
+    Value::Object(schema)
+}
+
+fn bundle_meta(bundle: &OCABundle, language: Language) -> BTreeMap<String, String> {
+    let oca_box = OCABox::from(bundle.clone());
+    oca_box
+        .meta
+        .and_then(|mut meta| meta.remove(&language))
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+fn type_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    fn entrance_bundle() -> OCABundle {
+        build(
+            r#"
+ADD ATTRIBUTE d=Text i=Text passed=Boolean level=Numeric
+ADD META en PROPS name="Entrance credential" description="Entrance credential"
+ADD CONFORMANCE ATTRS d="M" i="M" passed="M"
+ADD LABEL en ATTRS d="Schema digest" i="Credential Issuee" passed="Passed"
+ADD LABEL pl ATTRS d="Skrót schematu" i="Odbiorca" passed="Zaliczone"
+ADD FORMAT ATTRS i="^[A-Z]+$"
+ADD ENTRY_CODE ATTRS level=["1", "2"]
+"#,
+        )
+    }
+
+    #[test]
+    fn generate_credential_schema() {
+        let bundle = entrance_bundle();
+        let schema = credential_schema(&bundle, &[], Language::Eng);
+
+        assert_eq!(
+            schema["$id"],
+            json!(schema_id(bundle.said.as_ref().unwrap()))
+        );
+        assert_eq!(schema["title"], json!("Entrance credential"));
+        let subject = &schema["properties"]["credentialSubject"];
+        assert_eq!(subject["required"], json!(["d", "i", "passed"]));
+        assert_eq!(subject["properties"]["passed"]["type"], json!("boolean"));
+        assert_eq!(subject["properties"]["i"]["pattern"], json!("^[A-Z]+$"));
+        assert_eq!(
+            subject["properties"]["i"]["title"],
+            json!("Credential Issuee")
+        );
+        assert_eq!(subject["properties"]["level"]["type"], json!("number"));
+        assert_eq!(subject["properties"]["level"]["enum"], json!([1, 2]));
+    }
+
+    #[test]
+    fn generate_credential_template() {
+        let bundle = entrance_bundle();
+        let template = credential_template(&bundle);
+
+        assert_eq!(
+            template["type"],
+            json!(["VerifiableCredential", "EntranceCredential"])
+        );
+        assert_eq!(
+            template["credentialSubject"],
+            json!({ "d": null, "i": null, "level": null, "passed": null })
+        );
+        assert_eq!(template["display"]["pl"]["labels"]["i"], json!("Odbiorca"));
+        assert_eq!(
+            template["display"]["en"]["name"],
+            json!("Entrance credential")
+        );
+        assert_eq!(claimed_bundle(&template).ok(), bundle.said);
+    }
+
+    #[test]
+    fn validate_credential_against_bundle() {
+        let bundle = entrance_bundle();
+        let mut credential = credential_template(&bundle);
+        credential["credentialSubject"] = json!({
+            "id": "did:example:123",
+            "d": "digest",
+            "i": "ISSUEE",
+            "passed": true,
+            "level": 2
+        });
+        assert_eq!(validate_credential(&credential, &bundle, &[]), Ok(()));
+
+        credential["credentialSubject"]["i"] = json!("issuee");
+        credential["credentialSubject"]["level"] = json!(3);
+        let errors = validate_credential(&credential, &bundle, &[]).unwrap_err();
+        assert_eq!(errors.len(), 2);
+
+        let other = build("ADD ATTRIBUTE x=Text");
+        let errors = validate_credential(&credential, &other, &[]).unwrap_err();
+        assert!(matches!(errors[0], Error::BundleMismatch { .. }));
+    }
+
+    #[test]
+    fn inline_referenced_bundles() {
+        let address = build("ADD ATTRIBUTE street=Text\nADD CONFORMANCE ATTRS street=\"M\"");
+        let address_said = address.said.clone().unwrap();
+        let person = build(&format!(
+            "ADD ATTRIBUTE name=Text addresses=Array[refs:{address_said}] home=refs:{address_said}"
+        ));
+
+        let schema = credential_schema(&person, &[address], Language::Eng);
+        let properties = &schema["properties"]["credentialSubject"]["properties"];
+        assert_eq!(properties["home"]["required"], json!(["street"]));
+        assert_eq!(
+            properties["addresses"]["items"]["properties"]["street"]["type"],
+            json!("string")
+        );
+
+        let schema = credential_schema(&person, &[], Language::Eng);
+        let properties = &schema["properties"]["credentialSubject"]["properties"];
+        assert_eq!(properties["home"]["$ref"], json!(schema_id(&address_said)));
+    }
+}
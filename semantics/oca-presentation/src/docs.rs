@@ -0,0 +1,591 @@
+//! Data dictionary documentation of OCA bundles, rendered as self-contained
+//! HTML page or Markdown.
+use crate::{escape_html, referenced_bundle, type_name, LanguageSelection};
+use isolang::Language;
+use oca_ast_semantics::ast::RefValue;
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    entries::EntriesElement,
+    entry_codes::EntryCodes,
+    oca::{language_code, OCABox, OCABundle},
+    record::attributes_of,
+};
+use std::collections::{BTreeSet, HashMap};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:1em 0}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+th{background:#f3f3f3}code{background:#f6f6f6;padding:0 2px}";
+
+pub struct DocRenderer {
+    selection: LanguageSelection,
+    reference_url: Option<String>,
+}
+
+impl Default for DocRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DocRenderer {
+    pub fn new() -> Self {
+        Self {
+            selection: LanguageSelection::default(),
+            reference_url: None,
+        }
+    }
+
+    /// Languages to document. All languages of the bundle by default.
+    pub fn languages(mut self, languages: Vec<Language>) -> Self {
+        self.selection.languages = languages;
+        self
+    }
+
+    /// Languages used, in order, when a text is missing in documented one.
+    pub fn fallback(mut self, fallback: Vec<Language>) -> Self {
+        self.selection.fallback = fallback;
+        self
+    }
+
+    /// URL of referenced bundles documentation with `{said}` placeholder.
+    /// Defaults to `{said}.html` for HTML and `{said}.md` for Markdown.
+    pub fn reference_url(mut self, template: &str) -> Self {
+        self.reference_url = Some(template.to_string());
+        self
+    }
+
+    pub fn render_html(&self, bundle: &OCABundle) -> String {
+        let blocks = self.document(bundle, "{said}.html");
+        let title = match blocks.first() {
+            Some(Block::Heading(_, title)) => title.clone(),
+            _ => String::new(),
+        };
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n",
+            escape_html(&title)
+        );
+        for block in &blocks {
+            match block {
+                Block::Heading(level, text) => {
+                    html.push_str(&format!("<h{level}>{}</h{level}>\n", escape_html(text)))
+                }
+                Block::Paragraph(inlines) => {
+                    html.push_str(&format!("<p>{}</p>\n", html_inlines(inlines)))
+                }
+                Block::Table(table) => {
+                    html.push_str("<table>\n<thead><tr>");
+                    for header in &table.headers {
+                        html.push_str(&format!("<th>{}</th>", escape_html(header)));
+                    }
+                    html.push_str("</tr></thead>\n<tbody>\n");
+                    for row in &table.rows {
+                        html.push_str("<tr>");
+                        for cell in row {
+                            html.push_str(&format!("<td>{}</td>", html_inlines(cell)));
+                        }
+                        html.push_str("</tr>\n");
+                    }
+                    html.push_str("</tbody>\n</table>\n");
+                }
+            }
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    pub fn render_markdown(&self, bundle: &OCABundle) -> String {
+        let blocks = self.document(bundle, "{said}.md");
+        let mut markdown = String::new();
+        for block in &blocks {
+            match block {
+                Block::Heading(level, text) => {
+                    markdown.push_str(&format!("{} {}\n\n", "#".repeat(*level as usize), text))
+                }
+                Block::Paragraph(inlines) => {
+                    markdown.push_str(&format!("{}\n\n", markdown_inlines(inlines)))
+                }
+                Block::Table(table) => {
+                    let headers = table
+                        .headers
+                        .iter()
+                        .map(|h| markdown_escape(h))
+                        .collect::<Vec<_>>();
+                    markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+                    markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                    for row in &table.rows {
+                        let cells = row.iter().map(|c| markdown_inlines(c)).collect::<Vec<_>>();
+                        markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+                    }
+                    markdown.push('\n');
+                }
+            }
+        }
+        markdown
+    }
+
+    fn document(&self, bundle: &OCABundle, default_reference_url: &str) -> Vec<Block> {
+        let languages = self.selection.resolve(bundle);
+        let primary = languages.first().copied().unwrap_or(Language::Eng);
+        let oca_box = OCABox::from(bundle.clone());
+        let attributes = attributes_of(bundle);
+        let reference_url = self
+            .reference_url
+            .as_deref()
+            .unwrap_or(default_reference_url);
+        let reference_link = |reference: &RefValue| match reference {
+            RefValue::Said(said) => Inline::Link {
+                text: said.to_string(),
+                href: reference_url.replace("{said}", &said.to_string()),
+            },
+            RefValue::Name(name) => Inline::Code(format!("refn:{name}")),
+        };
+
+        // Meta of given language, completed with fallback languages.
+        let meta = |language: Language| {
+            let mut merged = HashMap::new();
+            if let Some(meta) = &oca_box.meta {
+                for language in std::iter::once(&language).chain(&self.selection.fallback) {
+                    for (key, value) in meta.get(language).into_iter().flatten() {
+                        merged.entry(key.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+            }
+            merged
+        };
+
+        let mut blocks = vec![];
+        let primary_meta = meta(primary);
+        blocks.push(Block::Heading(
+            1,
+            primary_meta
+                .get("name")
+                .cloned()
+                .unwrap_or_else(|| "OCA Bundle".to_string()),
+        ));
+        if let Some(description) = primary_meta.get("description") {
+            blocks.push(Block::Paragraph(vec![Inline::Text(description.clone())]));
+        }
+        if let Some(said) = &bundle.said {
+            blocks.push(Block::Paragraph(vec![
+                Inline::Text("SAID: ".to_string()),
+                Inline::Code(said.to_string()),
+            ]));
+        }
+        if !bundle.capture_base.classification.is_empty() {
+            blocks.push(Block::Paragraph(vec![
+                Inline::Text("Classification: ".to_string()),
+                Inline::Text(bundle.capture_base.classification.clone()),
+            ]));
+        }
+
+        // Meta
+        let meta_keys = languages
+            .iter()
+            .flat_map(|language| meta(*language).into_keys())
+            .collect::<BTreeSet<_>>();
+        if !meta_keys.is_empty() {
+            let mut keys = ["name", "description"]
+                .iter()
+                .map(|key| key.to_string())
+                .filter(|key| meta_keys.contains(key))
+                .collect::<Vec<_>>();
+            keys.extend(
+                meta_keys
+                    .iter()
+                    .filter(|key| *key != "name" && *key != "description")
+                    .cloned(),
+            );
+            let mut headers = vec!["Language".to_string()];
+            headers.extend(keys.iter().map(|key| capitalize(key)));
+            let rows = languages
+                .iter()
+                .map(|language| {
+                    let meta = meta(*language);
+                    let mut row = vec![text(&language_code(*language))];
+                    row.extend(
+                        keys.iter()
+                            .map(|key| text(meta.get(key).map(String::as_str).unwrap_or(""))),
+                    );
+                    row
+                })
+                .collect();
+            blocks.push(Block::Heading(2, "Meta".to_string()));
+            blocks.push(Block::Table(Table { headers, rows }));
+        }
+
+        // Attributes
+        let mut headers = [
+            "Name",
+            "Type",
+            "Conformance",
+            "Unit",
+            "Format",
+            "Cardinality",
+            "Flagged",
+        ]
+        .iter()
+        .map(|h| h.to_string())
+        .collect::<Vec<_>>();
+        for language in &languages {
+            headers.push(format!("Label ({})", language_code(*language)));
+        }
+        for language in &languages {
+            headers.push(format!("Information ({})", language_code(*language)));
+        }
+        let rows = attributes
+            .iter()
+            .map(|attribute| {
+                let attr_type = match &attribute.attribute_type {
+                    Some(attr_type) => match referenced_bundle(attr_type) {
+                        Some(reference @ RefValue::Said(said)) => {
+                            let type_name = type_name(attr_type);
+                            let (prefix, suffix) = type_name
+                                .split_once(&said.to_string())
+                                .unwrap_or((&type_name, ""));
+                            vec![
+                                Inline::Text(prefix.to_string()),
+                                reference_link(reference),
+                                Inline::Text(suffix.to_string()),
+                            ]
+                        }
+                        _ => vec![Inline::Text(type_name(attr_type))],
+                    },
+                    None => vec![],
+                };
+                let optional = |value: &Option<String>| text(value.as_deref().unwrap_or(""));
+                let mut row = vec![
+                    vec![Inline::Code(attribute.name.clone())],
+                    attr_type,
+                    optional(&attribute.conformance),
+                    optional(&attribute.unit),
+                    optional(&attribute.format),
+                    optional(&attribute.cardinality),
+                    text(if attribute.is_flagged { "yes" } else { "" }),
+                ];
+                for language in &languages {
+                    row.push(optional(
+                        &self
+                            .selection
+                            .translate(attribute.labels.as_ref(), *language)
+                            .cloned(),
+                    ));
+                }
+                for language in &languages {
+                    row.push(optional(
+                        &self
+                            .selection
+                            .translate(attribute.informations.as_ref(), *language)
+                            .cloned(),
+                    ));
+                }
+                row
+            })
+            .collect();
+        blocks.push(Block::Heading(2, "Attributes".to_string()));
+        blocks.push(Block::Table(Table { headers, rows }));
+
+        // Entry codes
+        let with_entry_codes = attributes
+            .iter()
+            .filter(|attribute| attribute.entry_codes.is_some())
+            .collect::<Vec<_>>();
+        if !with_entry_codes.is_empty() {
+            blocks.push(Block::Heading(2, "Entry codes".to_string()));
+            for attribute in with_entry_codes {
+                blocks.push(Block::Heading(3, attribute.name.clone()));
+                blocks.extend(self.entry_codes(attribute, &languages));
+            }
+        }
+
+        // Framing
+        let mut framing_rows = vec![];
+        for attribute in &attributes {
+            let mut framings = attribute
+                .framings
+                .iter()
+                .flatten()
+                .flat_map(|(frame_id, framing)| {
+                    framing
+                        .iter()
+                        .map(move |(iri, scope)| (frame_id.clone(), iri.clone(), scope.clone()))
+                })
+                .collect::<Vec<_>>();
+            framings.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+            for (frame_id, iri, scope) in framings {
+                framing_rows.push(vec![
+                    vec![Inline::Code(attribute.name.clone())],
+                    vec![iri_inline(iri)],
+                    text(&scope.predicate_id),
+                    text(&scope.framing_justification),
+                    text(&frame_id),
+                ]);
+            }
+        }
+        if !framing_rows.is_empty() {
+            blocks.push(Block::Heading(2, "Framing".to_string()));
+            blocks.push(Block::Table(Table {
+                headers: ["Attribute", "IRI", "Predicate", "Justification", "Frame"]
+                    .iter()
+                    .map(|h| h.to_string())
+                    .collect(),
+                rows: framing_rows,
+            }));
+        }
+
+        // References
+        let references = attributes
+            .iter()
+            .filter_map(|attribute| {
+                let reference = referenced_bundle(attribute.attribute_type.as_ref()?)?;
+                Some(vec![
+                    vec![Inline::Code(attribute.name.clone())],
+                    vec![reference_link(reference)],
+                ])
+            })
+            .collect::<Vec<_>>();
+        if !references.is_empty() {
+            blocks.push(Block::Heading(2, "References".to_string()));
+            blocks.push(Block::Table(Table {
+                headers: vec!["Attribute".to_string(), "Bundle".to_string()],
+                rows: references,
+            }));
+        }
+
+        blocks
+    }
+
+    fn entry_codes(&self, attribute: &Attribute, languages: &[Language]) -> Vec<Block> {
+        let codes = match &attribute.entry_codes {
+            Some(EntryCodes::Sai(sai)) => {
+                return vec![Block::Paragraph(vec![
+                    Inline::Text("Entry codes defined in ".to_string()),
+                    Inline::Code(sai.clone()),
+                ])]
+            }
+            Some(EntryCodes::Array(codes)) => codes
+                .iter()
+                .map(|code| (None, code.clone()))
+                .collect::<Vec<_>>(),
+            Some(EntryCodes::Object(groups)) => groups
+                .iter()
+                .flat_map(|(group, codes)| {
+                    codes.iter().map(|code| (Some(group.clone()), code.clone()))
+                })
+                .collect(),
+            None => return vec![],
+        };
+        let grouped = codes.iter().any(|(group, _)| group.is_some());
+
+        let mut headers = vec![];
+        if grouped {
+            headers.push("Group".to_string());
+        }
+        headers.push("Code".to_string());
+        for language in languages {
+            headers.push(format!("Label ({})", language_code(*language)));
+        }
+
+        let entries = attribute.entries.clone().unwrap_or_default();
+        let label = |language: Language, code: &str| {
+            let fallback = self.selection.fallback.iter();
+            std::iter::once(&language)
+                .chain(fallback)
+                .find_map(|language| match entries.get(language) {
+                    Some(EntriesElement::Object(labels)) => labels.get(code).cloned(),
+                    _ => None,
+                })
+                .unwrap_or_default()
+        };
+        let rows = codes
+            .iter()
+            .map(|(group, code)| {
+                let mut row = vec![];
+                if grouped {
+                    row.push(text(group.as_deref().unwrap_or("")));
+                }
+                row.push(vec![Inline::Code(code.clone())]);
+                for language in languages {
+                    row.push(text(&label(*language, code)));
+                }
+                row
+            })
+            .collect();
+        vec![Block::Table(Table { headers, rows })]
+    }
+}
+
+enum Inline {
+    Text(String),
+    Code(String),
+    Link { text: String, href: String },
+}
+
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<Vec<Inline>>>,
+}
+
+enum Block {
+    Heading(u8, String),
+    Paragraph(Vec<Inline>),
+    Table(Table),
+}
+
+fn text(value: &str) -> Vec<Inline> {
+    if value.is_empty() {
+        vec![]
+    } else {
+        vec![Inline::Text(value.to_string())]
+    }
+}
+
+/// Links IRIs of bundle content only when they are web addresses, others,
+/// e.g. `javascript:` ones, are shown as code.
+fn iri_inline(iri: String) -> Inline {
+    let lowercase = iri.to_ascii_lowercase();
+    if lowercase.starts_with("http://") || lowercase.starts_with("https://") {
+        Inline::Link {
+            text: iri.clone(),
+            href: iri,
+        }
+    } else {
+        Inline::Code(iri)
+    }
+}
+
+fn html_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape_html(text),
+            Inline::Code(code) => format!("<code>{}</code>", escape_html(code)),
+            Inline::Link { text, href } => format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(href),
+                escape_html(text)
+            ),
+        })
+        .collect()
+}
+
+fn markdown_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => markdown_escape(text),
+            Inline::Code(code) => format!("`{}`", code.replace('`', "'")),
+            Inline::Link { text, href } => format!(
+                "[{}](<{}>)",
+                markdown_escape(text),
+                href.replace('>', "%3E")
+            ),
+        })
+        .collect()
+}
+
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("<br>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    fn bundle() -> OCABundle {
+        build(
+            r#"
+ADD ATTRIBUTE name=Text sex=Text address=refs:ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI
+ADD META en PROPS name="Patient" description="Patient <record>"
+ADD META pl PROPS name="Pacjent"
+ADD CONFORMANCE ATTRS name="M"
+ADD LABEL en ATTRS name="Name" sex="Sex"
+ADD LABEL pl ATTRS name="Imię"
+ADD INFORMATION en ATTRS name="Full name | as in passport"
+ADD ENTRY_CODE ATTRS sex=["F", "M"]
+ADD ENTRY en ATTRS sex={"F": "Female", "M": "Male"}
+ADD ENTRY pl ATTRS sex={"F": "Kobieta"}
+ADD ATTR_FRAMING id="SNOMEDCT" label="SNOMED CT" location="https://bioportal.bioontology.org/ontologies/SNOMEDCT" version="2023AA" ATTRS sex = {"http://purl.bioontology.org/ontology/snomedct/734000001": {"Predicate_id": "skos:exactMatch", "Framing_justification": "semapv:ManualMappingCuration"}}
+"#,
+        )
+    }
+
+    #[test]
+    fn render_markdown_with_fallback() {
+        let markdown = DocRenderer::new()
+            .languages(vec![Language::Pol, Language::Eng])
+            .fallback(vec![Language::Eng])
+            .render_markdown(&bundle());
+
+        assert!(markdown.starts_with("# Pacjent\n"));
+        assert!(markdown.contains("| pl | Pacjent | Patient \\<record\\> |"));
+        assert!(markdown.contains("| Label (pl) | Label (en) |"));
+        assert!(markdown.contains("Full name \\| as in passport"));
+        assert!(markdown.contains("| `F` | Kobieta | Female |"));
+        assert!(markdown.contains("| `M` | Male | Male |"));
+        assert!(markdown.contains(
+            "[ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI](<ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI.md>)"
+        ));
+        assert!(markdown.contains(
+            "[http://purl.bioontology.org/ontology/snomedct/734000001](<http://purl.bioontology.org/ontology/snomedct/734000001>)"
+        ));
+    }
+
+    #[test]
+    fn render_html() {
+        let html = DocRenderer::new()
+            .languages(vec![Language::Eng])
+            .reference_url("https://repository.example/bundles/{said}")
+            .render_html(&bundle());
+
+        assert!(html.contains("<title>Patient</title>"));
+        assert!(html.contains("<p>Patient &lt;record&gt;</p>"));
+        assert!(html.contains("<td>refs:<a href=\"https://repository.example/bundles/ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI\">ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI</a></td>"));
+        assert!(!html.contains("Imię"));
+        assert!(
+            html.contains("<a href=\"http://purl.bioontology.org/ontology/snomedct/734000001\">")
+        );
+    }
+
+    #[test]
+    fn render_non_web_iri_as_code() {
+        let bundle = build(
+            r#"
+ADD ATTRIBUTE sex=Text
+ADD ATTR_FRAMING id="X" label="X" location="https://example.org" version="1" ATTRS sex = {"javascript:alert(1)": {"Predicate_id": "skos:exactMatch", "Framing_justification": "semapv:ManualMappingCuration"}}
+"#,
+        );
+        let html = DocRenderer::new().render_html(&bundle);
+        assert!(!html.contains("href=\"javascript:"));
+        assert!(html.contains("<code>javascript:alert(1)</code>"));
+
+        let markdown = DocRenderer::new().render_markdown(&bundle);
+        assert!(!markdown.contains("(<javascript:"));
+        assert!(markdown.contains("`javascript:alert(1)`"));
+    }
+}
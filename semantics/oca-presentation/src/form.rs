@@ -0,0 +1,497 @@
+//! Data entry forms of OCA bundles, rendered as plain HTML `<form>` element.
+//!
+//! The form does not depend on any script. Conditional overlay is exposed in
+//! `data-condition` attribute of the field, with `data-dependencies` listing
+//! names of the fields referenced by `${i}` placeholders, so that client side
+//! code may show or hide the field. Fields required only under a condition
+//! carry `data-required` instead of `required` attribute.
+use crate::{escape_html, LanguageSelection};
+use isolang::Language;
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType, RefValue};
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    entries::EntriesElement,
+    oca::{OCABox, OCABundle},
+    record::{attributes_of, entry_codes_list, parse_cardinality, NumericRange},
+};
+use said::SelfAddressingIdentifier;
+
+/// Placeholder of item index in `<template>` of repeated groups.
+pub const INDEX_PLACEHOLDER: &str = "__index__";
+
+#[derive(Default)]
+pub struct FormRenderer {
+    selection: LanguageSelection,
+    dependencies: Vec<OCABundle>,
+}
+
+impl FormRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Language of labels, help texts and entries. The first language of the
+    /// bundle by default.
+    pub fn language(mut self, language: Language) -> Self {
+        self.selection.languages = vec![language];
+        self
+    }
+
+    /// Languages used, in order, when a text is missing in form language.
+    pub fn fallback(mut self, fallback: Vec<Language>) -> Self {
+        self.selection.fallback = fallback;
+        self
+    }
+
+    /// Bundles referenced by `refs:` attributes, rendered as nested
+    /// fieldsets.
+    pub fn with_dependencies(mut self, dependencies: Vec<OCABundle>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    pub fn render(&self, bundle: &OCABundle) -> String {
+        let language = self
+            .selection
+            .resolve(bundle)
+            .first()
+            .copied()
+            .unwrap_or(Language::Eng);
+        let said = bundle
+            .said
+            .as_ref()
+            .map(|said| said.to_string())
+            .unwrap_or_default();
+
+        let mut html = format!(
+            "<form class=\"oca-form\" data-said=\"{}\">\n",
+            escape_html(&said)
+        );
+        let meta = OCABox::from(bundle.clone())
+            .meta
+            .and_then(|meta| {
+                std::iter::once(&language)
+                    .chain(&self.selection.fallback)
+                    .find_map(|language| meta.get(language).cloned())
+            })
+            .unwrap_or_default();
+        if let Some(name) = meta.get("name") {
+            html.push_str(&format!("<h2>{}</h2>\n", escape_html(name)));
+        }
+        if let Some(description) = meta.get("description") {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+        }
+        let mut stack = bundle.said.iter().cloned().collect();
+        html.push_str(&self.fields(bundle, "", language, &mut stack));
+        html.push_str("</form>\n");
+        html
+    }
+
+    fn fields(
+        &self,
+        bundle: &OCABundle,
+        prefix: &str,
+        language: Language,
+        stack: &mut Vec<SelfAddressingIdentifier>,
+    ) -> String {
+        attributes_of(bundle)
+            .iter()
+            .map(|attribute| self.field(attribute, prefix, language, stack))
+            .collect()
+    }
+
+    fn field(
+        &self,
+        attribute: &Attribute,
+        prefix: &str,
+        language: Language,
+        stack: &mut Vec<SelfAddressingIdentifier>,
+    ) -> String {
+        let Some(attr_type) = &attribute.attribute_type else {
+            return String::new();
+        };
+        let name = format!("{prefix}{}", attribute.name);
+        let id = field_id(&name);
+        let required = attribute.conformance.as_deref() == Some("M");
+
+        let mut field_attributes = format!(" data-attribute=\"{}\"", escape_html(&name));
+        if let Some(condition) = &attribute.condition {
+            let dependencies = attribute
+                .dependencies
+                .iter()
+                .flatten()
+                .map(|dependency| format!("{prefix}{dependency}"))
+                .collect::<Vec<_>>();
+            field_attributes.push_str(&format!(
+                " data-condition=\"{}\" data-dependencies=\"{}\"",
+                escape_html(condition),
+                escape_html(&dependencies.join(" "))
+            ));
+            if required {
+                field_attributes.push_str(" data-required=\"true\"");
+            }
+        }
+        if let Some(cardinality) = &attribute.cardinality {
+            field_attributes.push_str(&format!(
+                " data-cardinality=\"{}\"",
+                escape_html(cardinality)
+            ));
+        }
+        let label = format!(
+            "{}{}",
+            escape_html(
+                self.selection
+                    .translate(attribute.labels.as_ref(), language)
+                    .unwrap_or(&attribute.name)
+            ),
+            if required {
+                "<span class=\"oca-required\">*</span>"
+            } else {
+                ""
+            }
+        );
+        let help = self
+            .selection
+            .translate(attribute.informations.as_ref(), language)
+            .map(|information| {
+                format!(
+                    "<small class=\"oca-help\" id=\"{id}-help\">{}</small>\n",
+                    escape_html(information)
+                )
+            })
+            .unwrap_or_default();
+        let control = Control {
+            attribute,
+            language,
+            required: required && attribute.condition.is_none(),
+            help_id: (!help.is_empty()).then(|| format!("{id}-help")),
+        };
+
+        match attr_type {
+            NestedAttrType::Array(element_type)
+                if matches!(**element_type, NestedAttrType::Value(_))
+                    && attribute.entry_codes.is_some() =>
+            {
+                format!(
+                    "<div class=\"oca-field\"{field_attributes}>\n<label for=\"{id}\">{label}</label>\n{}{help}</div>\n",
+                    self.select(&control, &format!("{name}[]"), &id, true)
+                )
+            }
+            NestedAttrType::Value(_) => format!(
+                "<div class=\"oca-field\"{field_attributes}>\n<label for=\"{id}\">{label}</label>\n{}{help}</div>\n",
+                self.value(&control, attr_type, &name, &id, stack)
+            ),
+            _ => {
+                let class = match attr_type {
+                    NestedAttrType::Array(_) => "oca-array",
+                    _ => "oca-reference",
+                };
+                format!(
+                    "<fieldset class=\"oca-field {class}\"{field_attributes}>\n<legend>{label}</legend>\n{help}{}</fieldset>\n",
+                    self.value(&control, attr_type, &name, &id, stack)
+                )
+            }
+        }
+    }
+
+    /// Renders input of a value of given type, repeated for arrays and
+    /// nested for references.
+    fn value(
+        &self,
+        control: &Control,
+        attr_type: &NestedAttrType,
+        name: &str,
+        id: &str,
+        stack: &mut Vec<SelfAddressingIdentifier>,
+    ) -> String {
+        match attr_type {
+            NestedAttrType::Value(attribute_type) => {
+                if control.attribute.entry_codes.is_some() {
+                    self.select(control, name, id, false)
+                } else {
+                    input(control, *attribute_type, name, id)
+                }
+            }
+            NestedAttrType::Reference(reference) => {
+                let dependency = match reference {
+                    RefValue::Said(said) if !stack.contains(said) => self
+                        .dependencies
+                        .iter()
+                        .find(|dependency| dependency.said.as_ref() == Some(said)),
+                    _ => None,
+                };
+                match dependency {
+                    Some(dependency) => {
+                        stack.extend(dependency.said.clone());
+                        let fields =
+                            self.fields(dependency, &format!("{name}."), control.language, stack);
+                        stack.pop();
+                        fields
+                    }
+                    None => format!(
+                        "<p class=\"oca-unresolved\" data-reference=\"{}\">{}</p>\n",
+                        escape_html(&reference.to_string()),
+                        escape_html(&reference.to_string())
+                    ),
+                }
+            }
+            NestedAttrType::Array(element_type) => {
+                let (minimum, _) = control
+                    .attribute
+                    .cardinality
+                    .as_deref()
+                    .and_then(parse_cardinality)
+                    .unwrap_or((1, None));
+                let tag = match **element_type {
+                    NestedAttrType::Value(_) => "div",
+                    _ => "fieldset",
+                };
+                let item = |index: &str, stack: &mut Vec<SelfAddressingIdentifier>| {
+                    format!(
+                        "<{tag} class=\"oca-array-item\">\n{}</{tag}>\n",
+                        self.value(
+                            control,
+                            element_type,
+                            &format!("{name}[{index}]"),
+                            &format!("{id}-{index}"),
+                            stack
+                        )
+                    )
+                };
+                let mut html = String::new();
+                for index in 0..minimum.max(1) {
+                    html.push_str(&item(&index.to_string(), stack));
+                }
+                html.push_str(&format!(
+                    "<template class=\"oca-array-template\">\n{}</template>\n",
+                    item(INDEX_PLACEHOLDER, stack)
+                ));
+                html
+            }
+            NestedAttrType::Null => String::new(),
+        }
+    }
+
+    fn select(&self, control: &Control, name: &str, id: &str, multiple: bool) -> String {
+        let codes = control
+            .attribute
+            .entry_codes
+            .as_ref()
+            .and_then(entry_codes_list)
+            .unwrap_or_default();
+        let entries = control.attribute.entries.as_ref();
+        let label = |code: &str| {
+            std::iter::once(&control.language)
+                .chain(&self.selection.fallback)
+                .find_map(|language| match entries?.get(language) {
+                    Some(EntriesElement::Object(labels)) => labels.get(code),
+                    _ => None,
+                })
+                .map(String::as_str)
+                .unwrap_or(code)
+                .to_string()
+        };
+
+        let mut html = format!(
+            "<select id=\"{id}\" name=\"{}\"{}{}>\n",
+            escape_html(name),
+            if multiple { " multiple" } else { "" },
+            control.common_attributes()
+        );
+        if !multiple {
+            html.push_str("<option value=\"\"></option>\n");
+        }
+        for code in codes {
+            html.push_str(&format!(
+                "<option value=\"{}\">{}</option>\n",
+                escape_html(&code),
+                escape_html(&label(&code))
+            ));
+        }
+        html.push_str("</select>\n");
+        html
+    }
+}
+
+struct Control<'a> {
+    attribute: &'a Attribute,
+    language: Language,
+    required: bool,
+    help_id: Option<String>,
+}
+
+impl Control<'_> {
+    fn common_attributes(&self) -> String {
+        let mut attributes = String::new();
+        if self.required {
+            attributes.push_str(" required");
+        }
+        attributes.push_str(&self.described_by());
+        attributes
+    }
+
+    fn described_by(&self) -> String {
+        self.help_id
+            .as_ref()
+            .map(|help_id| format!(" aria-describedby=\"{help_id}\""))
+            .unwrap_or_default()
+    }
+}
+
+fn input(control: &Control, attribute_type: AttributeType, name: &str, id: &str) -> String {
+    let format = control.attribute.format.as_deref();
+    let placeholder = || {
+        format
+            .map(|format| format!(" placeholder=\"{}\"", escape_html(format)))
+            .unwrap_or_default()
+    };
+    let typed = match attribute_type {
+        AttributeType::Text => format!(
+            "type=\"text\"{}{}",
+            placeholder(),
+            format
+                .map(|format| format!(" pattern=\"{}\"", escape_html(format)))
+                .unwrap_or_default()
+        ),
+        AttributeType::Numeric => {
+            let range = format.and_then(NumericRange::parse);
+            let step = match range {
+                Some(range) if range.integer => "1",
+                _ => "any",
+            };
+            let mut attributes = format!("type=\"number\" step=\"{step}\"{}", placeholder());
+            if let Some(range) = range {
+                if let (Some(minimum), false) = (range.minimum, range.exclusive_minimum) {
+                    attributes.push_str(&format!(" min=\"{minimum}\""));
+                }
+                if let (Some(maximum), false) = (range.maximum, range.exclusive_maximum) {
+                    attributes.push_str(&format!(" max=\"{maximum}\""));
+                }
+            }
+            attributes
+        }
+        // A required checkbox would have to be checked, so the requirement
+        // is only marked in the label.
+        AttributeType::Boolean => {
+            return format!(
+                "<input type=\"checkbox\" id=\"{id}\" name=\"{}\" value=\"true\"{}>\n",
+                escape_html(name),
+                control.described_by()
+            )
+        }
+        AttributeType::DateTime => {
+            format!("type=\"{}\"{}", date_time_input_type(format), placeholder())
+        }
+        AttributeType::Binary => format!(
+            "type=\"file\"{}",
+            format
+                .map(|format| format!(" accept=\"{}\"", escape_html(format)))
+                .unwrap_or_default()
+        ),
+    };
+    format!(
+        "<input {typed} id=\"{id}\" name=\"{}\"{}>\n",
+        escape_html(name),
+        control.common_attributes()
+    )
+}
+
+/// Input type matching date time format: `date` or `time` when the format
+/// has only date or only time tokens, `datetime-local` otherwise.
+fn date_time_input_type(format: Option<&str>) -> &'static str {
+    let Some(format) = format else {
+        return "datetime-local";
+    };
+    let has = |tokens: &[&str]| tokens.iter().any(|token| format.contains(token));
+    match (has(&["YYYY", "MM", "DD"]), has(&["hh", "mm", "ss"])) {
+        (true, false) => "date",
+        (false, true) => "time",
+        _ => "datetime-local",
+    }
+}
+
+/// Element id of the field named `name`.
+fn field_id(name: &str) -> String {
+    let id = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    format!("oca-{id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oca_bundle_semantics::build::from_ast;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        from_ast(None, &ast).unwrap().oca_bundle
+    }
+
+    #[test]
+    fn render_form() {
+        let address = build(
+            r#"
+ADD ATTRIBUTE street=Text city=Text
+ADD LABEL en ATTRS street="Street"
+"#,
+        );
+        let person = build(&format!(
+            r#"
+ADD ATTRIBUTE name=Text age=Numeric sex=Text pregnant=Boolean phones=Array[Text] languages=Array[Text] addresses=Array[refs:{}] photo=Binary born=DateTime woke=DateTime seen=DateTime
+ADD META en PROPS name="Person"
+ADD LABEL en ATTRS name="Name" sex="Sex"
+ADD INFORMATION en ATTRS name="Full name"
+ADD CONFORMANCE ATTRS name="M" pregnant="M"
+ADD FORMAT ATTRS name="^[A-Z].*$" age="[0,120]" photo="image/png" born="YYYY-MM-DD" woke="hh:mm"
+ADD ENTRY_CODE ATTRS sex=["F", "M"] languages=["en", "pl"]
+ADD ENTRY en ATTRS sex={{"F": "Female", "M": "Male"}}
+ADD CONDITION ATTRS pregnant="${{sex}} == 'F'"
+ADD CARDINALITY ATTRS phones="2-"
+"#,
+            address.said.clone().unwrap()
+        ));
+
+        let html = FormRenderer::new()
+            .language(Language::Eng)
+            .with_dependencies(vec![address])
+            .render(&person);
+
+        assert!(html.starts_with("<form class=\"oca-form\""));
+        assert!(html.contains("<h2>Person</h2>"));
+        assert!(html
+            .contains("<label for=\"oca-name\">Name<span class=\"oca-required\">*</span></label>"));
+        assert!(html.contains("<input type=\"text\" placeholder=\"^[A-Z].*$\" pattern=\"^[A-Z].*$\" id=\"oca-name\" name=\"name\" required aria-describedby=\"oca-name-help\">"));
+        assert!(html.contains("<small class=\"oca-help\" id=\"oca-name-help\">Full name</small>"));
+        assert!(html.contains("min=\"0\" max=\"120\""));
+        assert!(html.contains(
+            "<input type=\"file\" accept=\"image/png\" id=\"oca-photo\" name=\"photo\">"
+        ));
+        assert!(html.contains("<input type=\"date\" placeholder=\"YYYY-MM-DD\" id=\"oca-born\""));
+        assert!(html.contains("<input type=\"time\" placeholder=\"hh:mm\" id=\"oca-woke\""));
+        assert!(html.contains("<input type=\"datetime-local\" id=\"oca-seen\""));
+        assert!(html.contains("<option value=\"F\">Female</option>"));
+        assert!(html.contains("<select id=\"oca-languages\" name=\"languages[]\" multiple>"));
+        assert!(html.contains(
+            "data-attribute=\"pregnant\" data-condition=\"${0} == &#39;F&#39;\" data-dependencies=\"sex\" data-required=\"true\""
+        ));
+        assert!(html.contains("name=\"phones[1]\""));
+        assert!(html.contains("name=\"phones[__index__]\""));
+        assert!(
+            html.contains("<fieldset class=\"oca-field oca-array\" data-attribute=\"addresses\">")
+        );
+        assert!(html.contains("<label for=\"oca-addresses-0--street\">Street</label>"));
+        assert!(html.contains("name=\"addresses[0].city\""));
+    }
+
+    #[test]
+    fn render_unresolved_reference() {
+        let person =
+            build("ADD ATTRIBUTE address=refs:ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI");
+        let html = FormRenderer::new().render(&person);
+        assert!(html.contains(
+            "<p class=\"oca-unresolved\" data-reference=\"refs:ENrf7niTCnz7HD-Ci88rlxHlxkpQ2NIZNNv08fQnXANI\">"
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+pub mod docs;
+pub mod form;
+
+use isolang::Language;
+use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+use oca_bundle_semantics::state::oca::OCABundle;
+use std::collections::HashMap;
+
+/// Languages used for rendering and the order of languages tried when a
+/// text is missing in requested language.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageSelection {
+    /// Rendered languages. When empty, all languages of the bundle are used.
+    pub languages: Vec<Language>,
+    pub fallback: Vec<Language>,
+}
+
+impl LanguageSelection {
+    /// Languages to render for the bundle.
+    pub fn resolve(&self, bundle: &OCABundle) -> Vec<Language> {
+        if self.languages.is_empty() {
+            bundle.languages()
+        } else {
+            self.languages.clone()
+        }
+    }
+
+    /// Text in given language, or in the first fallback language it exists in.
+    pub fn translate<'a>(
+        &self,
+        texts: Option<&'a HashMap<Language, String>>,
+        language: Language,
+    ) -> Option<&'a String> {
+        let texts = texts?;
+        texts.get(&language).or_else(|| {
+            self.fallback
+                .iter()
+                .find_map(|fallback| texts.get(fallback))
+        })
+    }
+}
+
+/// Human readable attribute type, as written in OCAfile.
+pub fn type_name(attr_type: &NestedAttrType) -> String {
+    match attr_type {
+        NestedAttrType::Value(attribute_type) => format!("{attribute_type:?}"),
+        NestedAttrType::Reference(reference) => reference.to_string(),
+        NestedAttrType::Array(element_type) => format!("Array[{}]", type_name(element_type)),
+        NestedAttrType::Null => "Null".to_string(),
+    }
+}
+
+/// Reference to other bundle held by the attribute type, directly or as
+/// array element.
+pub fn referenced_bundle(attr_type: &NestedAttrType) -> Option<&RefValue> {
+    match attr_type {
+        NestedAttrType::Reference(reference) => Some(reference),
+        NestedAttrType::Array(element_type) => referenced_bundle(element_type),
+        _ => None,
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
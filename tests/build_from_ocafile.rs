@@ -43,7 +43,7 @@ ADD ATTR_FRAMING \
             }
         }
 "#.to_string();
-        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config);
+        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config)?;
 
         let result = facade.build_from_ocafile(ocafile)?;
 
@@ -65,7 +65,9 @@ ADD ATTR_FRAMING \
         let oca_bundle_version = String::from_utf8(oca_bundle_encoded[6..23].to_vec()).unwrap();
         assert_eq!(oca_bundle_version, "OCAS11JSON0009ba_");
 
-        let search_result = facade.search_oca_bundle(None, "Ent".to_string(), 10, 1);
+        let search_result = facade
+            .search_oca_bundle(None, "Ent".to_string(), 10, 1)
+            .unwrap();
         assert_eq!(search_result.metadata.total, 1);
         Ok(())
     }
@@ -75,7 +77,7 @@ ADD ATTR_FRAMING \
         let db = InMemoryDataStorage::new();
         let db_cache = InMemoryDataStorage::new();
         let cache_storage_config = SQLiteConfig::build().unwrap();
-        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config);
+        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config)?;
         let other_ocafile = r#"
 ADD ATTRIBUTE d=Text i=Text passed=Boolean
 ADD META en PROPS name="Entrance credential" description="Entrance credential"
@@ -105,7 +107,7 @@ ADD ATTRIBUTE x=Text
         let db = InMemoryDataStorage::new();
         let db_cache = InMemoryDataStorage::new();
         let cache_storage_config = SQLiteConfig::build().unwrap();
-        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config);
+        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config)?;
         let second_ocafile = r#"
 -- name=first
 ADD ATTRIBUTE b=Text
@@ -161,7 +163,7 @@ ADD ATTRIBUTE x=Text
         let db = InMemoryDataStorage::new();
         let db_cache = InMemoryDataStorage::new();
         let cache_storage_config = SQLiteConfig::build().unwrap();
-        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config);
+        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config)?;
         let first_ocafile = r#"
 -- name=first
 ADD ATTRIBUTE a=Text
@@ -187,11 +189,11 @@ ADD LINK refn:first ATTRS b=a
     }
 
     #[test]
-    fn fail_while_building_from_unknown_reference() {
+    fn fail_while_building_from_unknown_reference() -> Result<(), Error> {
         let db = InMemoryDataStorage::new();
         let db_cache = InMemoryDataStorage::new();
         let cache_storage_config = SQLiteConfig::build().unwrap();
-        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config);
+        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config)?;
 
         let ocafile = r#"
 ADD ATTRIBUTE A=refs:EI_5ohTYptgOrXldUfZujgd7vcXK9zwa6aNqk4-UDWzq
@@ -207,5 +209,40 @@ ADD ATTRIBUTE C=Array[refn:third]
             let validation_error = validation_errors.first().unwrap();
             assert!(matches!(validation_error, ValidationError::UnknownRefn(_)));
         }
+        Ok(())
+    }
+
+    #[test]
+    fn fail_while_building_from_unknown_base() -> Result<(), Error> {
+        let db = InMemoryDataStorage::new();
+        let db_cache = InMemoryDataStorage::new();
+        let cache_storage_config = SQLiteConfig::build().unwrap();
+        let mut facade = Facade::new(Box::new(db), Box::new(db_cache), cache_storage_config)?;
+
+        let ocafile = "FROM EI_5ohTYptgOrXldUfZujgd7vcXK9zwa6aNqk4-UDWzq\nADD ATTRIBUTE a=Text\n";
+        let Err(Error::ValidationError(validation_errors)) =
+            facade.build_from_ocafile(ocafile.to_string())
+        else {
+            panic!("Built from unknown base");
+        };
+        assert!(
+            matches!(
+                validation_errors.as_slice(),
+                [ValidationError::InvalidCommand { line_number: 1, .. }]
+            ),
+            "{validation_errors:?}"
+        );
+
+        let ocafile = "FROM refn:first\nADD ATTRIBUTE a=Text\n";
+        let Err(Error::ValidationError(validation_errors)) =
+            facade.build_from_ocafile(ocafile.to_string())
+        else {
+            panic!("Built from base given by name");
+        };
+        assert!(matches!(
+            validation_errors.as_slice(),
+            [ValidationError::OCAFileParse(_)]
+        ));
+        Ok(())
     }
 }
@@ -0,0 +1,82 @@
+use oca_bundle_semantics::{
+    derive::{Oca, OcaType},
+    state::{entry_codes::EntryCodes, oca::OCABox},
+};
+
+#[allow(dead_code)]
+#[derive(Oca)]
+#[oca(name(en = "Address"))]
+struct Address {
+    #[oca(label(en = "Street"))]
+    street: String,
+}
+
+#[allow(dead_code)]
+#[derive(Oca)]
+#[oca(name(en = "Patient", pl = "Pacjent"), classification = "GICS:35102020")]
+struct Patient {
+    #[oca(
+        label(en = "Name", pl = "Imię"),
+        information(en = "Full name"),
+        flagged
+    )]
+    name: String,
+    #[oca(unit = "kg", conformance = "M")]
+    weight: Option<f64>,
+    #[oca(entry_codes = ["F", "M"])]
+    sex: String,
+    #[oca(rename = "dateOfBirth", attribute_type = "DateTime")]
+    date_of_birth: String,
+    #[oca(cardinality = "1-")]
+    addresses: Vec<Address>,
+    note: Option<String>,
+}
+
+#[test]
+fn derive_bundle() {
+    let bundle = Patient::oca_bundle();
+    assert_eq!(bundle.said, Some(Patient::oca_said()));
+    assert_eq!(bundle.capture_base.flagged_attributes, vec!["name"]);
+    assert_eq!(bundle.capture_base.classification, "GICS:35102020");
+
+    let oca_box = OCABox::from(bundle);
+    let attribute = |name: &str| oca_box.attributes.get(name).unwrap();
+    assert_eq!(
+        serde_json::to_value(&attribute("addresses").attribute_type).unwrap(),
+        serde_json::json!([format!("refs:{}", Address::oca_said())])
+    );
+    assert_eq!(
+        serde_json::to_value(&attribute("dateOfBirth").attribute_type).unwrap(),
+        serde_json::json!("DateTime")
+    );
+    assert_eq!(attribute("weight").conformance.as_deref(), Some("M"));
+    assert_eq!(attribute("weight").unit.as_deref(), Some("kg"));
+    assert_eq!(attribute("note").conformance.as_deref(), Some("O"));
+    assert_eq!(attribute("sex").conformance.as_deref(), Some("M"));
+    assert!(matches!(
+        &attribute("sex").entry_codes,
+        Some(EntryCodes::Array(codes)) if codes == &vec!["F".to_string(), "M".to_string()]
+    ));
+    assert_eq!(attribute("addresses").cardinality.as_deref(), Some("1-"));
+    assert_eq!(
+        attribute("name")
+            .labels
+            .as_ref()
+            .unwrap()
+            .get(&isolang::Language::Pol)
+            .map(String::as_str),
+        Some("Imię")
+    );
+
+    assert_eq!(
+        Patient::oca_dependencies()
+            .iter()
+            .map(|dependency| dependency.said.clone().unwrap())
+            .collect::<Vec<_>>(),
+        vec![Address::oca_said()]
+    );
+    assert_eq!(
+        serde_json::to_value(Patient::attribute_type()).unwrap(),
+        serde_json::json!(format!("refs:{}", Patient::oca_said()))
+    );
+}
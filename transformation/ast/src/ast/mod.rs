@@ -35,6 +35,13 @@ pub struct CommandMeta {
 pub enum CommandType {
     Rename,
     Link,
+    ConvertUnit,
+    ConvertDate,
+    MapEntryCodes,
+    Concat,
+    Split,
+    Default,
+    Drop,
 }
 
 #[derive(Debug, PartialEq, Clone, Eq)]
@@ -42,6 +49,13 @@ pub enum ObjectKind {
     // Transformation(TransformationType),
     Rename(RenameContent),
     Link(LinkContent),
+    ConvertUnit(ConvertContent),
+    ConvertDate(ConvertContent),
+    MapEntryCodes(MapEntryCodesContent),
+    Concat(ConcatContent),
+    Split(SplitContent),
+    Default(DefaultContent),
+    Drop(DropContent),
 }
 
 #[derive(Debug, PartialEq, Clone, Eq)]
@@ -62,6 +76,63 @@ pub struct LinkContent {
     pub attributes: Option<IndexMap<String, String>>,
 }
 
+/// Conversion of attribute value between units or date formats.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Eq, Hash, Default)]
+pub struct ConvertContent {
+    pub attribute: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Eq, Default)]
+pub struct MapEntryCodesContent {
+    pub attribute: String,
+    pub mapping: IndexMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Eq, Hash, Default)]
+pub struct ConcatContent {
+    pub attributes: Vec<String>,
+    pub target: String,
+    pub separator: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Eq, Hash, Default)]
+pub struct SplitContent {
+    pub attribute: String,
+    pub targets: Vec<String>,
+    pub separator: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Eq, Default)]
+pub struct DefaultContent {
+    pub attributes: IndexMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Eq, Hash, Default)]
+pub struct DropContent {
+    pub attributes: Vec<String>,
+}
+
+impl Hash for MapEntryCodesContent {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.attribute.hash(state);
+        for (key, value) in &self.mapping {
+            key.hash(state);
+            value.hash(state);
+        }
+    }
+}
+
+impl Hash for DefaultContent {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for (key, value) in &self.attributes {
+            key.hash(state);
+            value.to_string().hash(state);
+        }
+    }
+}
+
 impl Hash for ObjectKind {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
@@ -71,6 +142,24 @@ impl Hash for ObjectKind {
             ObjectKind::Link(content) => {
                 content.hash(state);
             }
+            ObjectKind::ConvertUnit(content) | ObjectKind::ConvertDate(content) => {
+                content.hash(state);
+            }
+            ObjectKind::MapEntryCodes(content) => {
+                content.hash(state);
+            }
+            ObjectKind::Concat(content) => {
+                content.hash(state);
+            }
+            ObjectKind::Split(content) => {
+                content.hash(state);
+            }
+            ObjectKind::Default(content) => {
+                content.hash(state);
+            }
+            ObjectKind::Drop(content) => {
+                content.hash(state);
+            }
         }
     }
 }
@@ -134,6 +223,34 @@ impl Serialize for ObjectKind {
                 state.serialize_field("object_kind", "Link")?;
                 state.serialize_field("content", content)?;
             }
+            ObjectKind::ConvertUnit(content) => {
+                state.serialize_field("object_kind", "ConvertUnit")?;
+                state.serialize_field("content", content)?;
+            }
+            ObjectKind::ConvertDate(content) => {
+                state.serialize_field("object_kind", "ConvertDate")?;
+                state.serialize_field("content", content)?;
+            }
+            ObjectKind::MapEntryCodes(content) => {
+                state.serialize_field("object_kind", "MapEntryCodes")?;
+                state.serialize_field("content", content)?;
+            }
+            ObjectKind::Concat(content) => {
+                state.serialize_field("object_kind", "Concat")?;
+                state.serialize_field("content", content)?;
+            }
+            ObjectKind::Split(content) => {
+                state.serialize_field("object_kind", "Split")?;
+                state.serialize_field("content", content)?;
+            }
+            ObjectKind::Default(content) => {
+                state.serialize_field("object_kind", "Default")?;
+                state.serialize_field("content", content)?;
+            }
+            ObjectKind::Drop(content) => {
+                state.serialize_field("object_kind", "Drop")?;
+                state.serialize_field("content", content)?;
+            }
         }
         state.end()
     }
@@ -251,6 +368,41 @@ impl<'de> Deserialize<'de> for Command {
                                     let content: LinkContent = map.next_value()?;
                                     object_kind = Some(ObjectKind::Link(content));
                                 }
+                                "ConvertUnit" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: ConvertContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::ConvertUnit(content));
+                                }
+                                "ConvertDate" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: ConvertContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::ConvertDate(content));
+                                }
+                                "MapEntryCodes" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: MapEntryCodesContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::MapEntryCodes(content));
+                                }
+                                "Concat" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: ConcatContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::Concat(content));
+                                }
+                                "Split" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: SplitContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::Split(content));
+                                }
+                                "Default" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: DefaultContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::Default(content));
+                                }
+                                "Drop" => {
+                                    let _content_key: Option<String> = map.next_key()?;
+                                    let content: DropContent = map.next_value()?;
+                                    object_kind = Some(ObjectKind::Drop(content));
+                                }
                                 _ => {}
                             }
                         }
@@ -310,6 +462,13 @@ impl From<u8> for ObjectKind {
         match val {
             0 => ObjectKind::Rename(RenameContent { attributes: None }),
             1 => ObjectKind::Link(LinkContent { attributes: None }),
+            2 => ObjectKind::ConvertUnit(ConvertContent::default()),
+            3 => ObjectKind::ConvertDate(ConvertContent::default()),
+            4 => ObjectKind::MapEntryCodes(MapEntryCodesContent::default()),
+            5 => ObjectKind::Concat(ConcatContent::default()),
+            6 => ObjectKind::Split(SplitContent::default()),
+            7 => ObjectKind::Default(DefaultContent::default()),
+            8 => ObjectKind::Drop(DropContent::default()),
             _ => panic!("Unknown object type"),
         }
     }
@@ -320,6 +479,13 @@ impl From<ObjectKind> for u8 {
         match val {
             ObjectKind::Rename(_) => 0,
             ObjectKind::Link(_) => 1,
+            ObjectKind::ConvertUnit(_) => 2,
+            ObjectKind::ConvertDate(_) => 3,
+            ObjectKind::MapEntryCodes(_) => 4,
+            ObjectKind::Concat(_) => 5,
+            ObjectKind::Split(_) => 6,
+            ObjectKind::Default(_) => 7,
+            ObjectKind::Drop(_) => 8,
         }
     }
 }
@@ -333,6 +499,13 @@ impl<'de> Deserialize<'de> for ObjectKind {
         match s.as_str() {
             "Rename" => Ok(ObjectKind::Rename(RenameContent { attributes: None })),
             "Link" => Ok(ObjectKind::Link(LinkContent { attributes: None })),
+            "ConvertUnit" => Ok(ObjectKind::ConvertUnit(ConvertContent::default())),
+            "ConvertDate" => Ok(ObjectKind::ConvertDate(ConvertContent::default())),
+            "MapEntryCodes" => Ok(ObjectKind::MapEntryCodes(MapEntryCodesContent::default())),
+            "Concat" => Ok(ObjectKind::Concat(ConcatContent::default())),
+            "Split" => Ok(ObjectKind::Split(SplitContent::default())),
+            "Default" => Ok(ObjectKind::Default(DefaultContent::default())),
+            "Drop" => Ok(ObjectKind::Drop(DropContent::default())),
             _ => Err(serde::de::Error::custom(format!(
                 "unknown object kind: {}",
                 s
@@ -369,4 +542,30 @@ mod tests {
         let deser: TransformationAST = serde_json::from_str(&serialized).unwrap();
         assert_eq!(ast, deser);
     }
+
+    #[test]
+    fn test_value_operations_serialize() {
+        let mut ast = TransformationAST::new();
+        ast.commands.push(Command {
+            kind: CommandType::ConvertUnit,
+            object_kind: ObjectKind::ConvertUnit(ConvertContent {
+                attribute: "weight".to_string(),
+                from: "kg".to_string(),
+                to: "g".to_string(),
+            }),
+        });
+        ast.commands.push(Command {
+            kind: CommandType::Default,
+            object_kind: ObjectKind::Default(DefaultContent {
+                attributes: IndexMap::from([("age".to_string(), serde_json::json!(0))]),
+            }),
+        });
+
+        let serialized = serde_json::to_string(&ast).unwrap();
+        assert!(serialized.contains(
+            r#"{"type":"ConvertUnit","object_kind":"ConvertUnit","content":{"attribute":"weight","from":"kg","to":"g"}}"#
+        ));
+        let deser: TransformationAST = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(ast, deser);
+    }
 }
@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 
 pub enum Error {
     #[error("{0}")]
@@ -15,17 +15,15 @@ pub enum Error {
     #[error("")]
     MissingVersion(),
 
-    #[error("Validation errors: {0:?}")]
+    #[error("{}", Errors(.0.to_vec()))]
     Validation(Vec<Error>),
 }
 
-#[allow(dead_code)]
 struct Errors(Vec<Error>);
 
 impl std::fmt::Display for Errors {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0
-            .iter()
-            .try_fold((), |_result, error| writeln!(f, "{}", error))
+        let messages = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+        write!(f, "{}", messages.join("; "))
     }
 }
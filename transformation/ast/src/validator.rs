@@ -1,8 +1,8 @@
 use crate::{
-    ast::{Command, TransformationAST},
+    ast::{Command, LinkContent, ObjectKind, RenameContent, TransformationAST},
     errors::Error,
 };
-// use indexmap::{indexmap, IndexMap};
+use indexmap::IndexMap;
 
 //type CaptureAttributes = IndexMap<String, String>;
 
@@ -49,32 +49,34 @@ impl Validator for OCAValidator {
     }
 }
 
-fn validate_1_0_2(_ast: &TransformationAST, _command: Command) -> Result<bool, Error> {
+fn validate_1_0_2(ast: &TransformationAST, command: Command) -> Result<bool, Error> {
     // Rules
-    // Cannot remove if does not exist on stack
-    // Cannot modify if does not exist on stack
-    // Cannot add if already exists on stack
-    // Attributes must have valid type
-    let valid = true;
-    let errors = Vec::new();
-    /* match (&command.kind, &command.object_kind) {
-        _ => {
-            // TODO: Add support for FROM, MODIFY with combination of different object kinds
+    // Renamed or linked attribute can not be mapped to other name
+    // Two attributes can not be mapped to the same name
+    // Instructions must not be empty
+    let mut valid = true;
+    let mut errors = Vec::new();
+    let rules = match &command.object_kind {
+        ObjectKind::Rename(RenameContent { attributes })
+        | ObjectKind::Link(LinkContent { attributes }) => match attributes {
+            Some(attributes) if !attributes.is_empty() => {
+                vec![rule_mapping_unambiguous(ast, attributes)]
+            }
+            _ => vec![Err(Error::InvalidOperation(
+                "No attributes to map".to_string(),
+            ))],
+        },
+        ObjectKind::Concat(content) => vec![rule_not_empty(&content.attributes, "concatenate")],
+        ObjectKind::Split(content) => vec![rule_not_empty(&content.targets, "split into")],
+        ObjectKind::Drop(content) => vec![rule_not_empty(&content.attributes, "drop")],
+        _ => vec![],
+    };
+    for rule in rules {
+        if let Err(error) = rule {
+            valid = false;
+            errors.push(error);
         }
-    } */
-    // CommandType::Modify => {
-    //     match rule_modify_if_exist(ast, command) {
-    //         Ok(result) => {
-    //             if !result {
-    //                 valid = result;
-    //             }
-    //         }
-    //         Err(error) => {
-    //             valid = false;
-    //             errors.push(error);
-    //         }
-    //     }
-    // }
+    }
 
     if valid {
         Ok(true)
@@ -83,6 +85,76 @@ fn validate_1_0_2(_ast: &TransformationAST, _command: Command) -> Result<bool, E
     }
 }
 
+/// Check rule for rename and link commands
+/// Rule would be valid if none of the attributes is already mapped to other
+/// name and none of the names is already a target of other attribute
+///
+/// # Arguments
+/// * `ast` - valid OCA AST
+/// * `attributes` - Mapping of attribute names to validate against AST
+///
+/// # Returns
+/// * `Result<bool, Error>` - Result of validation
+fn rule_mapping_unambiguous(
+    ast: &TransformationAST,
+    attributes: &IndexMap<String, String>,
+) -> Result<bool, Error> {
+    let mut errors = Vec::new();
+    let mut mapping = extract_mapping(ast);
+
+    for (source, target) in attributes {
+        match mapping.get(source) {
+            Some(mapped) if mapped != target => errors.push(Error::InvalidOperation(format!(
+                "Attribute {source} is already mapped to {mapped}"
+            ))),
+            _ => {
+                if let Some((other, _)) = mapping
+                    .iter()
+                    .find(|(other, mapped)| *other != source && *mapped == target)
+                {
+                    errors.push(Error::InvalidOperation(format!(
+                        "Attribute {other} is already mapped to {target}"
+                    )));
+                }
+            }
+        }
+        mapping.insert(source.clone(), target.clone());
+    }
+
+    if errors.is_empty() {
+        Ok(true)
+    } else {
+        Err(Error::Validation(errors))
+    }
+}
+
+fn rule_not_empty(attributes: &[String], operation: &str) -> Result<bool, Error> {
+    if attributes.is_empty() {
+        Err(Error::InvalidOperation(format!(
+            "No attributes to {operation}"
+        )))
+    } else {
+        Ok(true)
+    }
+}
+
+/// Mapping of attribute names done by rename and link commands
+fn extract_mapping(ast: &TransformationAST) -> IndexMap<String, String> {
+    let mut mapping = IndexMap::new();
+    for command in &ast.commands {
+        match &command.object_kind {
+            ObjectKind::Rename(RenameContent {
+                attributes: Some(attributes),
+            })
+            | ObjectKind::Link(LinkContent {
+                attributes: Some(attributes),
+            }) => mapping.extend(attributes.clone()),
+            _ => {}
+        }
+    }
+    mapping
+}
+
 /* fn extract_attributes(ast: &TransformationAST) -> CaptureAttributes {
     let default_attrs: IndexMap<String, String> = indexmap! {};
     let mut attributes: CaptureAttributes = indexmap! {};
@@ -107,29 +179,48 @@ fn extract_properties(ast: &TransformationAST) -> IndexMap<String, NestedValue>
 
 #[cfg(test)]
 mod tests {
-    /* #[test]
-    fn test_rule_remove_if_exist() {
-        let command = Command {
-            kind: CommandType::Add,
-            object_kind: ObjectKind::CaptureBase(CaptureContent {
-                attributes: Some(indexmap! {
-                    "name".to_string() => NestedAttrType::Value(AttributeType::Text),
-                    "documentType".to_string() => NestedAttrType::Value(AttributeType::Text),
-                    "photo".to_string() => NestedAttrType::Value(AttributeType::Binary),
-                }),
-                properties: Some(indexmap! {
-                    "classification".to_string() => NestedValue::Value("GICS:1234".to_string()),
-                }),
-                flagged_attributes: None,
+    use super::*;
+    use crate::ast::{CommandType, DropContent};
+
+    fn rename(pairs: &[(&str, &str)]) -> Command {
+        Command {
+            kind: CommandType::Rename,
+            object_kind: ObjectKind::Rename(RenameContent {
+                attributes: Some(
+                    pairs
+                        .iter()
+                        .map(|(from, to)| (from.to_string(), to.to_string()))
+                        .collect(),
+                ),
             }),
-        };
-
-        let mut ocaast = OCAAst::new();
-        ocaast.commands.push(command);
-        let mut result = rule_remove_attr_if_exist(&ocaast, valid_command.clone());
-        assert!(result.is_ok());
-        ocaast.commands.push(invalid_command.clone());
-        result = rule_remove_attr_if_exist(&ocaast, invalid_command);
+        }
+    }
+
+    #[test]
+    fn test_rule_mapping_unambiguous() {
+        let validator = OCAValidator {};
+        let mut ast = TransformationAST::new();
+        let command = rename(&[("surname", "last_name")]);
+        assert!(validator.validate(&ast, command.clone()).is_ok());
+        ast.commands.push(command);
+
+        assert!(validator
+            .validate(&ast, rename(&[("surname", "last_name")]))
+            .is_ok());
+        let result = validator.validate(&ast, rename(&[("surname", "family_name")]));
         assert!(result.is_err());
-    } */
+        let result = validator.validate(&ast, rename(&[("name", "last_name")]));
+        assert!(result.is_err());
+        let result = validator.validate(&ast, rename(&[("a", "x"), ("b", "x")]));
+        assert!(result.is_err());
+
+        let result = validator.validate(
+            &ast,
+            Command {
+                kind: CommandType::Drop,
+                object_kind: ObjectKind::Drop(DropContent { attributes: vec![] }),
+            },
+        );
+        assert!(result.is_err());
+    }
 }
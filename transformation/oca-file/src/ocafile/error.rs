@@ -17,6 +17,16 @@ pub enum ParseError {
     #[error("Error parsing meta: {0}")]
     MetaError(String),
 
+    #[error("Error at line {line_number} ({raw_line}): {message}")]
+    InvalidInstruction {
+        #[serde(rename = "ln")]
+        line_number: usize,
+        #[serde(rename = "c")]
+        raw_line: String,
+        #[serde(rename = "e")]
+        message: String,
+    },
+
     #[error("Error parsing instruction: {0}")]
     InstructionError(#[from] InstructionError),
 
@@ -0,0 +1,118 @@
+use crate::ocafile::{error::InstructionError, instructions::helpers, Pair, Rule};
+use log::debug;
+use oca_ast_transformation::ast::{Command, CommandType, ConcatContent, ObjectKind, SplitContent};
+
+pub struct ConcatInstruction {}
+
+impl ConcatInstruction {
+    pub(crate) fn from_record(record: Pair, _index: usize) -> Result<Command, InstructionError> {
+        debug!("Parsing concat instruction from the record: {:?}", record);
+        let mut attributes = vec![];
+        let mut target = None;
+        let mut separator = String::new();
+        for object in record.into_inner() {
+            match object.as_rule() {
+                Rule::attr_keys => attributes = helpers::extract_attr_keys(object),
+                Rule::attr_key => target = Some(object.as_str().to_string()),
+                Rule::separator => separator = extract_separator(object),
+                rule => {
+                    return Err(InstructionError::UnexpectedToken(format!(
+                        "Concat: unexpected token {:?}",
+                        rule
+                    )))
+                }
+            }
+        }
+        let target = target.ok_or_else(|| {
+            InstructionError::Parser("Concat: missing target attribute".to_string())
+        })?;
+
+        Ok(Command {
+            kind: CommandType::Concat,
+            object_kind: ObjectKind::Concat(ConcatContent {
+                attributes,
+                target,
+                separator,
+            }),
+        })
+    }
+}
+
+pub struct SplitInstruction {}
+
+impl SplitInstruction {
+    pub(crate) fn from_record(record: Pair, _index: usize) -> Result<Command, InstructionError> {
+        debug!("Parsing split instruction from the record: {:?}", record);
+        let mut attribute = None;
+        let mut targets = vec![];
+        let mut separator = String::new();
+        for object in record.into_inner() {
+            match object.as_rule() {
+                Rule::attr_key => attribute = Some(object.as_str().to_string()),
+                Rule::attr_keys => targets = helpers::extract_attr_keys(object),
+                Rule::separator => separator = extract_separator(object),
+                rule => {
+                    return Err(InstructionError::UnexpectedToken(format!(
+                        "Split: unexpected token {:?}",
+                        rule
+                    )))
+                }
+            }
+        }
+        let attribute = attribute
+            .ok_or_else(|| InstructionError::Parser("Split: missing attribute name".to_string()))?;
+
+        Ok(Command {
+            kind: CommandType::Split,
+            object_kind: ObjectKind::Split(SplitContent {
+                attribute,
+                targets,
+                separator,
+            }),
+        })
+    }
+}
+
+fn extract_separator(separator: Pair) -> String {
+    separator
+        .into_inner()
+        .find(|item| item.as_rule() == Rule::string)
+        .map(helpers::extract_string)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocafile::OCAfileParser;
+    use pest::Parser;
+
+    #[test]
+    fn test_concat_and_split_instructions() {
+        let instruction = "CONCAT ATTRIBUTE first_name last-name -> full_name SEPARATOR \" \"";
+        let mut parsed = OCAfileParser::parse(Rule::concat, instruction).unwrap();
+        let command = ConcatInstruction::from_record(parsed.next().unwrap(), 0).unwrap();
+        assert_eq!(
+            command.object_kind,
+            ObjectKind::Concat(ConcatContent {
+                attributes: vec!["first_name".to_string(), "last-name".to_string()],
+                target: "full_name".to_string(),
+                separator: " ".to_string(),
+            })
+        );
+
+        let instruction = "SPLIT ATTRIBUTE full_name -> first_name last_name SEPARATOR ', '";
+        let mut parsed = OCAfileParser::parse(Rule::split, instruction).unwrap();
+        let command = SplitInstruction::from_record(parsed.next().unwrap(), 0).unwrap();
+        assert_eq!(
+            command.object_kind,
+            ObjectKind::Split(SplitContent {
+                attribute: "full_name".to_string(),
+                targets: vec!["first_name".to_string(), "last_name".to_string()],
+                separator: ", ".to_string(),
+            })
+        );
+
+        assert!(OCAfileParser::parse(Rule::split, "SPLIT ATTRIBUTE full_name -> a b").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::ocafile::{error::InstructionError, instructions::helpers, Pair, Rule};
+use log::debug;
+use oca_ast_transformation::ast::{Command, CommandType, ConvertContent, ObjectKind};
+
+pub struct ConvertInstruction {}
+
+impl ConvertInstruction {
+    /// Parses `CONVERT UNIT` and `CONVERT DATE` instructions.
+    pub(crate) fn from_record(record: Pair, _index: usize) -> Result<Command, InstructionError> {
+        debug!("Parsing convert instruction from the record: {:?}", record);
+        let kind = match record.as_rule() {
+            Rule::convert_unit => CommandType::ConvertUnit,
+            Rule::convert_date => CommandType::ConvertDate,
+            rule => {
+                return Err(InstructionError::UnexpectedToken(format!(
+                    "Invalid convert instruction {:?}",
+                    rule
+                )))
+            }
+        };
+
+        let mut attribute = None;
+        let mut formats = vec![];
+        for object in record.into_inner() {
+            match object.as_rule() {
+                Rule::attr_key => attribute = Some(object.as_str().to_string()),
+                Rule::unit => formats.push(helpers::extract_text(object)),
+                Rule::string => formats.push(helpers::extract_string(object)),
+                rule => {
+                    return Err(InstructionError::UnexpectedToken(format!(
+                        "Convert: unexpected token {:?}",
+                        rule
+                    )))
+                }
+            }
+        }
+        let (Some(attribute), [from, to]) = (attribute, formats.as_slice()) else {
+            return Err(InstructionError::Parser(
+                "Convert: expected attribute name and two formats".to_string(),
+            ));
+        };
+        let content = ConvertContent {
+            attribute,
+            from: from.clone(),
+            to: to.clone(),
+        };
+        let object_kind = match kind {
+            CommandType::ConvertUnit => ObjectKind::ConvertUnit(content),
+            _ => ObjectKind::ConvertDate(content),
+        };
+
+        Ok(Command { kind, object_kind })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocafile::OCAfileParser;
+    use pest::Parser;
+
+    #[test]
+    fn test_convert_instruction() {
+        let instructions = vec![
+            (
+                Rule::convert_unit,
+                "CONVERT UNIT ATTRIBUTE weight kg -> g",
+                true,
+            ),
+            (
+                Rule::convert_unit,
+                "CONVERT UNIT ATTRIBUTE weight kg->g",
+                true,
+            ),
+            (
+                Rule::convert_date,
+                "CONVERT DATE ATTRIBUTE dob \"DD/MM/YYYY\" -> \"YYYY-MM-DD\"",
+                true,
+            ),
+            (
+                Rule::convert_date,
+                "CONVERT DATE ATTRIBUTE dob DD/MM/YYYY",
+                false,
+            ),
+            (
+                Rule::convert_unit,
+                "CONVERT UNIT ATTRIBUTE weight kg",
+                false,
+            ),
+        ];
+
+        for (rule, instruction, is_valid) in instructions {
+            match OCAfileParser::parse(rule, instruction) {
+                Ok(mut parsed_instruction) => {
+                    assert!(is_valid, "Instruction should be invalid: {instruction}");
+                    let command =
+                        ConvertInstruction::from_record(parsed_instruction.next().unwrap(), 0)
+                            .unwrap();
+                    match command.object_kind {
+                        ObjectKind::ConvertUnit(content) => {
+                            assert_eq!(command.kind, CommandType::ConvertUnit);
+                            assert_eq!(
+                                (
+                                    content.attribute.as_str(),
+                                    content.from.as_str(),
+                                    content.to.as_str()
+                                ),
+                                ("weight", "kg", "g")
+                            );
+                        }
+                        ObjectKind::ConvertDate(content) => {
+                            assert_eq!(content.from, "DD/MM/YYYY");
+                            assert_eq!(content.to, "YYYY-MM-DD");
+                        }
+                        _ => panic!("Unexpected object kind"),
+                    }
+                }
+                Err(_) => assert!(!is_valid, "Instruction should be valid: {instruction}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::ocafile::{error::InstructionError, instructions::helpers, Pair, Rule};
+use indexmap::IndexMap;
+use log::debug;
+use oca_ast_transformation::ast::{Command, CommandType, DefaultContent, DropContent, ObjectKind};
+
+pub struct DefaultInstruction {}
+
+impl DefaultInstruction {
+    pub(crate) fn from_record(record: Pair, _index: usize) -> Result<Command, InstructionError> {
+        debug!("Parsing default instruction from the record: {:?}", record);
+        let mut attributes = IndexMap::new();
+        for object in record.into_inner() {
+            match object.as_rule() {
+                Rule::default_pairs => {
+                    for pair in object.into_inner() {
+                        let (name, value) = helpers::extract_pair(pair)?;
+                        attributes.insert(name, helpers::extract_json_value(value));
+                    }
+                }
+                rule => {
+                    return Err(InstructionError::UnexpectedToken(format!(
+                        "Default: unexpected token {:?}",
+                        rule
+                    )))
+                }
+            }
+        }
+
+        Ok(Command {
+            kind: CommandType::Default,
+            object_kind: ObjectKind::Default(DefaultContent { attributes }),
+        })
+    }
+}
+
+pub struct DropInstruction {}
+
+impl DropInstruction {
+    pub(crate) fn from_record(record: Pair, _index: usize) -> Result<Command, InstructionError> {
+        debug!("Parsing drop instruction from the record: {:?}", record);
+        let mut attributes = vec![];
+        for object in record.into_inner() {
+            match object.as_rule() {
+                Rule::attr_keys => attributes = helpers::extract_attr_keys(object),
+                rule => {
+                    return Err(InstructionError::UnexpectedToken(format!(
+                        "Drop: unexpected token {:?}",
+                        rule
+                    )))
+                }
+            }
+        }
+
+        Ok(Command {
+            kind: CommandType::Drop,
+            object_kind: ObjectKind::Drop(DropContent { attributes }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocafile::OCAfileParser;
+    use pest::Parser;
+    use serde_json::json;
+
+    #[test]
+    fn test_default_and_drop_instructions() {
+        let instruction = "DEFAULT ATTRIBUTE country=\"CH\" age=0 active=true code=A1";
+        let mut parsed = OCAfileParser::parse(Rule::default, instruction).unwrap();
+        let command = DefaultInstruction::from_record(parsed.next().unwrap(), 0).unwrap();
+        assert_eq!(
+            command.object_kind,
+            ObjectKind::Default(DefaultContent {
+                attributes: IndexMap::from([
+                    ("country".to_string(), json!("CH")),
+                    ("age".to_string(), json!(0)),
+                    ("active".to_string(), json!(true)),
+                    ("code".to_string(), json!("A1")),
+                ])
+            })
+        );
+
+        let mut parsed = OCAfileParser::parse(Rule::drop, "DROP ATTRIBUTE nickname tmp").unwrap();
+        let command = DropInstruction::from_record(parsed.next().unwrap(), 0).unwrap();
+        assert_eq!(
+            command.object_kind,
+            ObjectKind::Drop(DropContent {
+                attributes: vec!["nickname".to_string(), "tmp".to_string()]
+            })
+        );
+    }
+}
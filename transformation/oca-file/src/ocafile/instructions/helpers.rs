@@ -47,3 +47,57 @@ pub fn extract_link_attribute(
 ) -> Result<(String, String), ExtractingAttributeError> {
     extract_rename_attribute(attr_pair)
 }
+
+/// Extract text of quoted string, resolving escape sequences
+pub fn extract_string(string: Pair) -> String {
+    match string.into_inner().next() {
+        Some(inner) if inner.as_rule() == Rule::inner => {
+            serde_json::from_str(&format!("\"{}\"", inner.as_str()))
+                .unwrap_or_else(|_| inner.as_str().to_string())
+        }
+        Some(inner) => inner.as_str().replace("\\'", "'"),
+        None => String::new(),
+    }
+}
+
+/// Extract value of `key_value` or `unit`, which is either quoted string or
+/// bare characters
+pub fn extract_text(value: Pair) -> String {
+    let text = value.as_str().to_string();
+    match value.into_inner().next() {
+        Some(string) if string.as_rule() == Rule::string => extract_string(string),
+        _ => text,
+    }
+}
+
+/// Extract JSON value of `key_value`. Quoted strings stay strings, bare
+/// values are read as JSON when possible, e.g. numbers and booleans
+pub fn extract_json_value(value: Pair) -> serde_json::Value {
+    let text = value.as_str().to_string();
+    match value.into_inner().next() {
+        Some(string) if string.as_rule() == Rule::string => {
+            serde_json::Value::String(extract_string(string))
+        }
+        _ => serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text)),
+    }
+}
+
+/// Extract attribute names of `attr_keys`
+pub fn extract_attr_keys(attr_keys: Pair) -> Vec<String> {
+    attr_keys
+        .into_inner()
+        .filter(|key| key.as_rule() == Rule::attr_key)
+        .map(|key| key.as_str().to_string())
+        .collect()
+}
+
+/// Extract pair of values separated by `=`
+pub fn extract_pair(pair: Pair) -> Result<(String, Pair), ExtractingAttributeError> {
+    let mut items = pair.into_inner();
+    match (items.next(), items.next()) {
+        (Some(key), Some(value)) => Ok((extract_text(key), value)),
+        _ => Err(ExtractingAttributeError::Unexpected(
+            "Missing key or value".to_string(),
+        )),
+    }
+}
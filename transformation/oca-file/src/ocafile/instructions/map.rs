@@ -0,0 +1,72 @@
+use crate::ocafile::{error::InstructionError, instructions::helpers, Pair, Rule};
+use indexmap::IndexMap;
+use log::debug;
+use oca_ast_transformation::ast::{Command, CommandType, MapEntryCodesContent, ObjectKind};
+
+pub struct MapEntryCodesInstruction {}
+
+impl MapEntryCodesInstruction {
+    pub(crate) fn from_record(record: Pair, _index: usize) -> Result<Command, InstructionError> {
+        debug!(
+            "Parsing map entry codes instruction from the record: {:?}",
+            record
+        );
+        let mut attribute = None;
+        let mut mapping = IndexMap::new();
+        for object in record.into_inner() {
+            match object.as_rule() {
+                Rule::attr_key => attribute = Some(object.as_str().to_string()),
+                Rule::code_pairs => {
+                    for pair in object.into_inner() {
+                        let (code, new_code) = helpers::extract_pair(pair)?;
+                        mapping.insert(code, helpers::extract_text(new_code));
+                    }
+                }
+                rule => {
+                    return Err(InstructionError::UnexpectedToken(format!(
+                        "Map entry codes: unexpected token {:?}",
+                        rule
+                    )))
+                }
+            }
+        }
+        let attribute = attribute.ok_or_else(|| {
+            InstructionError::Parser("Map entry codes: missing attribute name".to_string())
+        })?;
+
+        Ok(Command {
+            kind: CommandType::MapEntryCodes,
+            object_kind: ObjectKind::MapEntryCodes(MapEntryCodesContent { attribute, mapping }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocafile::OCAfileParser;
+    use pest::Parser;
+
+    #[test]
+    fn test_map_entry_codes_instruction() {
+        let instruction = "MAP ENTRY_CODE ATTRIBUTE sex F=female M=\"male person\"";
+        let mut parsed = OCAfileParser::parse(Rule::map_entry_codes, instruction).unwrap();
+        let command = MapEntryCodesInstruction::from_record(parsed.next().unwrap(), 0).unwrap();
+        match command.object_kind {
+            ObjectKind::MapEntryCodes(content) => {
+                assert_eq!(content.attribute, "sex");
+                assert_eq!(
+                    content.mapping,
+                    IndexMap::from([
+                        ("F".to_string(), "female".to_string()),
+                        ("M".to_string(), "male person".to_string())
+                    ])
+                );
+            }
+            _ => panic!("Unexpected object kind"),
+        }
+        assert!(
+            OCAfileParser::parse(Rule::map_entry_codes, "MAP ENTRY_CODE ATTRIBUTE sex").is_err()
+        );
+    }
+}
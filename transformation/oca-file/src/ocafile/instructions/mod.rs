@@ -1,3 +1,7 @@
+pub mod concat;
+pub mod convert;
+pub mod default;
 pub mod helpers;
 pub mod link;
+pub mod map;
 pub mod rename;
@@ -3,7 +3,14 @@ mod instructions;
 
 use self::{
     error::ParseError,
-    instructions::{link::LinkInstruction, rename::RenameInstruction},
+    instructions::{
+        concat::{ConcatInstruction, SplitInstruction},
+        convert::ConvertInstruction,
+        default::{DefaultInstruction, DropInstruction},
+        link::LinkInstruction,
+        map::MapEntryCodesInstruction,
+        rename::RenameInstruction,
+    },
 };
 use crate::ocafile::error::InstructionError;
 pub use oca_ast_transformation::ast::TransformationAST;
@@ -30,6 +37,12 @@ impl TryFromPair for Command {
         let instruction: Command = match record.as_rule() {
             Rule::rename => RenameInstruction::from_record(record, 0)?,
             Rule::link => LinkInstruction::from_record(record, 0)?,
+            Rule::convert_unit | Rule::convert_date => ConvertInstruction::from_record(record, 0)?,
+            Rule::map_entry_codes => MapEntryCodesInstruction::from_record(record, 0)?,
+            Rule::concat => ConcatInstruction::from_record(record, 0)?,
+            Rule::split => SplitInstruction::from_record(record, 0)?,
+            Rule::default => DefaultInstruction::from_record(record, 0)?,
+            Rule::drop => DropInstruction::from_record(record, 0)?,
             _ => return Err(InstructionError::UnexpectedToken(record.to_string())),
         };
         Ok(instruction)
@@ -57,7 +70,7 @@ pub fn parse_from_string(unparsed_file: String) -> Result<TransformationAST, Par
 
     let validator = OCAValidator {};
 
-    for (n, line) in file.into_inner().enumerate() {
+    for line in file.into_inner() {
         if let Rule::EOI = line.as_rule() {
             continue;
         }
@@ -93,6 +106,7 @@ pub fn parse_from_string(unparsed_file: String) -> Result<TransformationAST, Par
             continue;
         }
 
+        let line_number = line.as_span().start_pos().line_col().0;
         match Command::try_from_pair(line.clone()) {
             Ok(command) => match validator.validate(&oca_ast, command.clone()) {
                 Ok(_) => {
@@ -100,16 +114,17 @@ pub fn parse_from_string(unparsed_file: String) -> Result<TransformationAST, Par
                     oca_ast.commands_meta.insert(
                         oca_ast.commands.len() - 1,
                         CommandMeta {
-                            line_number: n + 1,
+                            line_number,
                             raw_line: line.as_str().to_string(),
                         },
                     );
                 }
                 Err(e) => {
-                    return Err(ParseError::Custom(format!(
-                        "Error validating instruction: {}",
-                        e
-                    )));
+                    return Err(ParseError::InvalidInstruction {
+                        line_number,
+                        raw_line: line.as_str().trim_end().to_string(),
+                        message: e.to_string(),
+                    });
                 }
             },
             Err(e) => {
@@ -120,23 +135,137 @@ pub fn parse_from_string(unparsed_file: String) -> Result<TransformationAST, Par
     Ok(oca_ast)
 }
 
+/// Generates OCAfile of transformation. Meta lines come first, starting with
+/// `-- precompiler=transformation`, followed by one line per command. Parsing
+/// the result gives back the same meta and commands.
 pub fn generate_from_ast(ast: &TransformationAST) -> String {
-    let ocafile = String::new();
+    let mut ocafile = String::new();
+
+    ocafile.push_str("-- precompiler=transformation\n");
+    for key in ["name", "version", "source", "target"] {
+        if let Some(value) = ast.meta.get(key) {
+            ocafile.push_str(format!("-- {}={}\n", key, value).as_str());
+        }
+    }
 
     ast.commands.iter().for_each(|command| {
-        let _line = String::new();
+        let mut line = String::new();
 
-        match command.kind {
-            ast::CommandType::Rename => todo!(),
-            ast::CommandType::Link => todo!(),
+        match &command.object_kind {
+            ast::ObjectKind::Rename(content) => {
+                line.push_str("RENAME ATTRIBUTE");
+                for (from, to) in content.attributes.iter().flatten() {
+                    line.push_str(format!(" {}={}", from, to).as_str());
+                }
+            }
+            ast::ObjectKind::Link(content) => {
+                line.push_str("LINK ATTRIBUTE");
+                for (from, to) in content.attributes.iter().flatten() {
+                    line.push_str(format!(" {} -> {}", from, to).as_str());
+                }
+            }
+            ast::ObjectKind::ConvertUnit(content) => {
+                line.push_str(
+                    format!(
+                        "CONVERT UNIT ATTRIBUTE {} {} -> {}",
+                        content.attribute,
+                        format_text(&content.from),
+                        format_text(&content.to)
+                    )
+                    .as_str(),
+                );
+            }
+            ast::ObjectKind::ConvertDate(content) => {
+                line.push_str(
+                    format!(
+                        "CONVERT DATE ATTRIBUTE {} {} -> {}",
+                        content.attribute,
+                        format_string(&content.from),
+                        format_string(&content.to)
+                    )
+                    .as_str(),
+                );
+            }
+            ast::ObjectKind::MapEntryCodes(content) => {
+                line.push_str(format!("MAP ENTRY_CODE ATTRIBUTE {}", content.attribute).as_str());
+                for (code, new_code) in &content.mapping {
+                    line.push_str(
+                        format!(" {}={}", format_text(code), format_text(new_code)).as_str(),
+                    );
+                }
+            }
+            ast::ObjectKind::Concat(content) => {
+                line.push_str(
+                    format!(
+                        "CONCAT ATTRIBUTE {} -> {}",
+                        content.attributes.join(" "),
+                        content.target
+                    )
+                    .as_str(),
+                );
+                if !content.separator.is_empty() {
+                    line.push_str(
+                        format!(" SEPARATOR {}", format_string(&content.separator)).as_str(),
+                    );
+                }
+            }
+            ast::ObjectKind::Split(content) => {
+                line.push_str(
+                    format!(
+                        "SPLIT ATTRIBUTE {} -> {} SEPARATOR {}",
+                        content.attribute,
+                        content.targets.join(" "),
+                        format_string(&content.separator)
+                    )
+                    .as_str(),
+                );
+            }
+            ast::ObjectKind::Default(content) => {
+                line.push_str("DEFAULT ATTRIBUTE");
+                for (name, value) in &content.attributes {
+                    line.push_str(format!(" {}={}", name, format_value(value)).as_str());
+                }
+            }
+            ast::ObjectKind::Drop(content) => {
+                line.push_str(format!("DROP ATTRIBUTE {}", content.attributes.join(" ")).as_str());
+            }
         }
 
-        // ocafile.push_str(format!("{}\n", line).as_str());
+        ocafile.push_str(format!("{}\n", line).as_str());
     });
 
     ocafile
 }
 
+/// Double quoted string, escaped the way the parser reads it back
+fn format_string(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+/// Bare text when it consists of characters allowed unquoted, quoted string
+/// otherwise
+fn format_text(text: &str) -> String {
+    let bare = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | '/' | ':'));
+    if bare {
+        text.to_string()
+    } else {
+        format_string(text)
+    }
+}
+
+/// Strings are always quoted, so they are not read back as numbers or
+/// booleans. Other values are written as JSON, which is kept bare when
+/// possible.
+fn format_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => format_string(text),
+        value => format_text(&value.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,4 +282,92 @@ LINK ATTRIBUTE surname -> last_name
         println!("{:#?}", oca_ast);
         assert_eq!(oca_ast.meta.get("version").unwrap(), "0.0.1");
     }
+
+    #[test]
+    fn parse_value_operations() {
+        let unparsed_file = r#"
+-- version=0.0.1
+RENAME ATTRIBUTE surname=last_name
+CONVERT UNIT ATTRIBUTE weight kg -> g
+CONVERT DATE ATTRIBUTE dob "DD/MM/YYYY" -> "YYYY-MM-DD"
+MAP ENTRY_CODE ATTRIBUTE sex F=female M=male
+CONCAT ATTRIBUTE first_name surname -> full_name SEPARATOR " "
+SPLIT ATTRIBUTE address -> street city SEPARATOR ","
+DEFAULT ATTRIBUTE country="CH"
+DROP ATTRIBUTE nickname
+"#;
+        let oca_ast = parse_from_string(unparsed_file.to_string()).unwrap();
+        assert_eq!(
+            oca_ast
+                .commands
+                .iter()
+                .map(|command| command.kind.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                ast::CommandType::Rename,
+                ast::CommandType::ConvertUnit,
+                ast::CommandType::ConvertDate,
+                ast::CommandType::MapEntryCodes,
+                ast::CommandType::Concat,
+                ast::CommandType::Split,
+                ast::CommandType::Default,
+                ast::CommandType::Drop,
+            ]
+        );
+        assert_eq!(oca_ast.commands_meta[&7].line_number, 10);
+    }
+
+    #[test]
+    fn parse_invalid_instruction() {
+        let unparsed_file = r#"
+RENAME ATTRIBUTE surname=last_name
+
+RENAME ATTRIBUTE surname=family_name
+"#;
+        match parse_from_string(unparsed_file.to_string()) {
+            Err(ParseError::InvalidInstruction {
+                line_number,
+                raw_line,
+                message,
+            }) => {
+                assert_eq!(line_number, 4);
+                assert_eq!(raw_line, "RENAME ATTRIBUTE surname=family_name");
+                assert_eq!(message, "Attribute surname is already mapped to last_name");
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn generate_round_trip() {
+        let unparsed_file = r#"
+-- version=0.0.1
+-- source=refs:EJVVlVSZJqVNnuAMLHLkeSQgwfxYLWTKBELi9e8j1PW0
+-- target=refs:EBQMQm_tXSC8tnNICl7paGUeGg0SyF1tceHhTUutn1PN
+RENAME ATTRIBUTE surname=last_name
+LINK ATTRIBUTE first_name -> given_name age -> years
+CONVERT UNIT ATTRIBUTE weight kg -> "fl oz"
+CONVERT DATE ATTRIBUTE dob "DD/MM/YYYY" -> "YYYY-MM-DD"
+MAP ENTRY_CODE ATTRIBUTE sex F=female M="male \"person\""
+CONCAT ATTRIBUTE first_name surname -> full_name SEPARATOR ", "
+CONCAT ATTRIBUTE a b -> ab
+SPLIT ATTRIBUTE address -> street city SEPARATOR "\t"
+DEFAULT ATTRIBUTE country="CH" age=0 ratio=0.5 active=true code="true" note=null
+DROP ATTRIBUTE nickname tmp
+"#;
+        let oca_ast = parse_from_string(unparsed_file.to_string()).unwrap();
+        let ocafile = generate_from_ast(&oca_ast);
+        assert!(ocafile.starts_with("-- precompiler=transformation\n-- version=0.0.1\n"));
+        let mut generated_ast = parse_from_string(ocafile.clone()).unwrap();
+        assert_eq!(generated_ast.commands, oca_ast.commands);
+        assert_eq!(
+            generated_ast.meta.remove("precompiler"),
+            Some("transformation".to_string())
+        );
+        assert_eq!(generated_ast.meta, oca_ast.meta);
+        assert_eq!(
+            generate_from_ast(&parse_from_string(ocafile.clone()).unwrap()),
+            ocafile
+        );
+    }
 }
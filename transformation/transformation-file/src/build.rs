@@ -1,4 +1,4 @@
-use crate::state::Transformation;
+use crate::state::{Operation, Transformation};
 use oca_ast_transformation::ast;
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -20,21 +20,40 @@ pub enum Error {
         #[serde(rename = "e")]
         message: String,
     },
+    #[error("Error at line {line_number}: missing {name} meta")]
+    MissingMeta {
+        #[serde(rename = "ln")]
+        line_number: usize,
+        #[serde(rename = "meta")]
+        name: String,
+    },
 }
 
 pub fn from_ast(ast: &ast::TransformationAST) -> Result<Transformation, Vec<Error>> {
     let mut errors = vec![];
 
-    let mut base: Option<Transformation> = None;
-    if !ast.meta.is_empty() {
-        let source = ast.meta.get("source").expect("missing source meta");
-        let source_said = source.replace("refs:", "");
-        let target = ast.meta.get("target").expect("missing target meta");
-        let target_said = target.replace("refs:", "");
-        let mut transformation = Transformation::new();
-        transformation.set_source(source_said);
-        transformation.set_target(target_said);
-        base = Some(transformation)
+    let source = ast.meta.get("source");
+    let target = ast.meta.get("target");
+    // Meta precedes commands, so missing one is reported at the first of them
+    let first_line = ast
+        .commands_meta
+        .get(&0)
+        .map(|meta| meta.line_number)
+        .unwrap_or(1);
+    for (name, value) in [("source", source), ("target", target)] {
+        if value.is_none() {
+            errors.push(Error::MissingMeta {
+                line_number: first_line,
+                name: name.to_string(),
+            });
+        }
+    }
+    let mut transformation = Transformation::new();
+    if let Some(source) = source {
+        transformation.set_source(source.replace("refs:", ""));
+    }
+    if let Some(target) = target {
+        transformation.set_target(target.replace("refs:", ""));
     }
 
     let default_command_meta = ast::CommandMeta {
@@ -48,9 +67,9 @@ pub fn from_ast(ast: &ast::TransformationAST) -> Result<Transformation, Vec<Erro
             .commands_meta
             .get(&command_index)
             .unwrap_or(&default_command_meta);
-        match apply_command(base.clone(), command.clone()) {
-            Ok(transformation) => {
-                base = Some(transformation);
+        match apply_command(Some(transformation.clone()), command.clone()) {
+            Ok(applied) => {
+                transformation = applied;
             }
             Err(mut err) => {
                 errors.extend(err.iter_mut().map(|e| Error::FromASTError {
@@ -62,7 +81,6 @@ pub fn from_ast(ast: &ast::TransformationAST) -> Result<Transformation, Vec<Erro
         }
     }
     if errors.is_empty() {
-        let mut transformation = base.unwrap().clone();
         transformation.fill_said();
         Ok(transformation)
     } else {
@@ -74,7 +92,7 @@ pub fn apply_command(
     base: Option<Transformation>,
     op: ast::Command,
 ) -> Result<Transformation, Vec<String>> {
-    let errors = vec![];
+    let mut errors = vec![];
     let mut transformation: Transformation = match base {
         Some(transformation) => transformation,
         None => Transformation::new(),
@@ -91,6 +109,78 @@ pub fn apply_command(
                 transformation.link(attributes);
             }
         }
+        (ast::CommandType::ConvertUnit, ast::ObjectKind::ConvertUnit(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::ConvertUnit {
+                    attribute: content.attribute,
+                    from: content.from,
+                    to: content.to,
+                },
+                &mut errors,
+            );
+        }
+        (ast::CommandType::ConvertDate, ast::ObjectKind::ConvertDate(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::ConvertDate {
+                    attribute: content.attribute,
+                    from: content.from,
+                    to: content.to,
+                },
+                &mut errors,
+            );
+        }
+        (ast::CommandType::MapEntryCodes, ast::ObjectKind::MapEntryCodes(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::MapEntryCodes {
+                    attribute: content.attribute,
+                    mapping: content.mapping,
+                },
+                &mut errors,
+            );
+        }
+        (ast::CommandType::Concat, ast::ObjectKind::Concat(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::Concat {
+                    attributes: content.attributes,
+                    target: content.target,
+                    separator: content.separator,
+                },
+                &mut errors,
+            );
+        }
+        (ast::CommandType::Split, ast::ObjectKind::Split(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::Split {
+                    attribute: content.attribute,
+                    targets: content.targets,
+                    separator: content.separator,
+                },
+                &mut errors,
+            );
+        }
+        (ast::CommandType::Default, ast::ObjectKind::Default(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::Default {
+                    attributes: content.attributes,
+                },
+                &mut errors,
+            );
+        }
+        (ast::CommandType::Drop, ast::ObjectKind::Drop(content)) => {
+            add_operation(
+                &mut transformation,
+                Operation::Drop {
+                    attributes: content.attributes,
+                },
+                &mut errors,
+            );
+        }
         _ => {}
     }
 
@@ -101,6 +191,19 @@ pub fn apply_command(
     }
 }
 
+fn add_operation(
+    transformation: &mut Transformation,
+    operation: Operation,
+    errors: &mut Vec<String>,
+) {
+    let operation_errors = operation.validate();
+    if operation_errors.is_empty() {
+        transformation.add_operation(operation);
+    } else {
+        errors.extend(operation_errors);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -127,21 +230,89 @@ mod tests {
             version: "1.0".to_string(),
             commands,
             commands_meta: IndexMap::new(),
-            meta: HashMap::new(),
+            meta: HashMap::from([
+                ("source".to_string(), "refs:source".to_string()),
+                ("target".to_string(), "refs:target".to_string()),
+            ]),
         };
 
         let build_result = from_ast(&ast);
-        match build_result {
-            Ok(transformation) => {
-                let code = HashFunctionCode::Blake3_256;
-                let format = SerializationFormats::JSON;
-                let transformation_encoded = transformation.encode(&code, &format).unwrap();
-                let transformation_json = String::from_utf8(transformation_encoded).unwrap();
-                println!("{}", transformation_json);
-            }
-            Err(e) => {
-                println!("{:?}", e);
-            }
-        }
+        assert!(build_result.is_ok(), "{build_result:?}");
+        let transformation = build_result.unwrap();
+        assert_eq!(transformation.source, Some("source".to_string()));
+        assert_eq!(transformation.target, Some("target".to_string()));
+        assert_eq!(
+            transformation.attributes,
+            IndexMap::from([("digest".to_string(), "d".to_string())])
+        );
+        let code = HashFunctionCode::Blake3_256;
+        let format = SerializationFormats::JSON;
+        let transformation_encoded = transformation.encode(&code, &format).unwrap();
+        let transformation_json = String::from_utf8(transformation_encoded).unwrap();
+        assert!(transformation_json.contains(r#""attributes":{"digest":"d"}"#));
+
+        let errors = from_ast(&ast::TransformationAST {
+            commands_meta: IndexMap::from([(
+                0,
+                ast::CommandMeta {
+                    line_number: 3,
+                    raw_line: "RENAME ATTRIBUTE digest=d".to_string(),
+                },
+            )]),
+            meta: HashMap::new(),
+            ..ast
+        })
+        .unwrap_err();
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "Error at line 3: missing source meta",
+                "Error at line 3: missing target meta"
+            ]
+        );
+    }
+
+    #[test]
+    fn build_with_operations() {
+        let mut commands = vec![ast::Command {
+            kind: ast::CommandType::Drop,
+            object_kind: ast::ObjectKind::Drop(ast::DropContent {
+                attributes: vec!["tmp".to_string()],
+            }),
+        }];
+        let ast = ast::TransformationAST {
+            version: "1.0".to_string(),
+            commands: commands.clone(),
+            commands_meta: IndexMap::new(),
+            meta: HashMap::from([
+                ("source".to_string(), "refs:source".to_string()),
+                ("target".to_string(), "refs:target".to_string()),
+            ]),
+        };
+        let transformation = from_ast(&ast).unwrap();
+        let code = HashFunctionCode::Blake3_256;
+        let format = SerializationFormats::JSON;
+        let encoded = String::from_utf8(transformation.encode(&code, &format).unwrap()).unwrap();
+        assert!(encoded.contains(r#""operations":[{"op":"drop","attributes":["tmp"]}]"#));
+        let without_operations = from_ast(&ast::TransformationAST {
+            commands: vec![],
+            ..ast.clone()
+        })
+        .unwrap();
+        assert_ne!(transformation.said, without_operations.said);
+        let encoded =
+            String::from_utf8(without_operations.encode(&code, &format).unwrap()).unwrap();
+        assert!(!encoded.contains("operations"));
+
+        commands.push(ast::Command {
+            kind: ast::CommandType::ConvertUnit,
+            object_kind: ast::ObjectKind::ConvertUnit(ast::ConvertContent {
+                attribute: "weight".to_string(),
+                from: "kg".to_string(),
+                to: "m".to_string(),
+            }),
+        });
+        let errors = from_ast(&ast::TransformationAST { commands, ..ast }).unwrap_err();
+        assert_eq!(errors.len(), 1);
     }
 }
@@ -0,0 +1,405 @@
+//! Applying transformations to data records.
+//!
+//! Record shaped by the source bundle is turned into record shaped by the
+//! target bundle. Attributes are carried over by name, unless renamed or
+//! linked by the transformation. Records of referenced bundles are
+//! transformed with the transformation between the referenced bundles, when
+//! one is provided, and by matching names otherwise. Value operations of the
+//! transformation run first, before attributes are renamed.
+use crate::state::Transformation;
+use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+use oca_bundle_semantics::state::{attribute::Attribute, oca::OCABundle, record::attributes_of};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Error {
+    #[error("Transformation source {expected:?} does not match bundle {actual}")]
+    SourceMismatch {
+        expected: Option<String>,
+        actual: String,
+    },
+    #[error("Transformation target {expected:?} does not match bundle {actual}")]
+    TargetMismatch {
+        expected: Option<String>,
+        actual: String,
+    },
+    #[error("Referenced bundle {0} not found in dependencies")]
+    MissingDependency(String),
+    #[error("Record at {0:?} is not an object")]
+    InvalidRecord(String),
+    #[error("Operation on record at {path:?} failed: {message}")]
+    Operation { path: String, message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transformed {
+    pub record: Value,
+    /// Paths of source values without counterpart in the target bundle,
+    /// e.g. `addresses[0].street`. They are left out of the record.
+    pub unmapped: Vec<String>,
+    /// Paths of mandatory target attributes left without value.
+    pub unfilled: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Transformer<'a> {
+    dependencies: &'a [OCABundle],
+    transformations: &'a [Transformation],
+}
+
+impl<'a> Transformer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bundles referenced by `refs:` attributes of source and target bundles.
+    pub fn with_dependencies(mut self, dependencies: &'a [OCABundle]) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Transformations between referenced bundles, applied to nested
+    /// records.
+    pub fn with_transformations(mut self, transformations: &'a [Transformation]) -> Self {
+        self.transformations = transformations;
+        self
+    }
+
+    pub fn transform(
+        &self,
+        transformation: &Transformation,
+        source: &OCABundle,
+        target: &OCABundle,
+        record: &Value,
+    ) -> Result<Transformed, Error> {
+        let source_said = said_of(source);
+        if transformation.source.as_deref() != Some(source_said.as_str()) {
+            return Err(Error::SourceMismatch {
+                expected: transformation.source.clone(),
+                actual: source_said,
+            });
+        }
+        let target_said = said_of(target);
+        if transformation.target.as_deref() != Some(target_said.as_str()) {
+            return Err(Error::TargetMismatch {
+                expected: transformation.target.clone(),
+                actual: target_said,
+            });
+        }
+
+        let mut transformed = Transformed {
+            record: Value::Null,
+            unmapped: vec![],
+            unfilled: vec![],
+        };
+        transformed.record =
+            self.transform_object(transformation, source, target, record, "", &mut transformed)?;
+        Ok(transformed)
+    }
+
+    fn transform_object(
+        &self,
+        transformation: &Transformation,
+        source: &OCABundle,
+        target: &OCABundle,
+        record: &Value,
+        path: &str,
+        report: &mut Transformed,
+    ) -> Result<Value, Error> {
+        let mut object = record
+            .as_object()
+            .ok_or_else(|| Error::InvalidRecord(path.to_string()))?
+            .clone();
+        for operation in &transformation.operations {
+            operation
+                .apply(&mut object)
+                .map_err(|message| Error::Operation {
+                    path: path.to_string(),
+                    message,
+                })?;
+        }
+        let source_attributes = attributes_of(source);
+        let target_attributes = attributes_of(target);
+
+        let mut output = Map::new();
+        for (name, value) in &object {
+            let value_path = join(path, name);
+            let target_name = transformation.attributes.get(name).unwrap_or(name);
+            let Some(target_attribute) = target_attributes.iter().find(|a| &a.name == target_name)
+            else {
+                report.unmapped.push(value_path);
+                continue;
+            };
+            let source_type = source_attributes
+                .iter()
+                .find(|a| &a.name == name)
+                .and_then(|a| a.attribute_type.as_ref());
+            let value = match (source_type, &target_attribute.attribute_type) {
+                (Some(source_type), Some(target_type)) => {
+                    self.transform_value(source_type, target_type, value, &value_path, report)?
+                }
+                _ => value.clone(),
+            };
+            output.insert(target_name.clone(), value);
+        }
+
+        report.unfilled.extend(
+            target_attributes
+                .iter()
+                .filter(|attribute| is_mandatory(attribute))
+                .filter(|attribute| {
+                    output
+                        .get(&attribute.name)
+                        .map(Value::is_null)
+                        .unwrap_or(true)
+                })
+                .map(|attribute| join(path, &attribute.name)),
+        );
+        Ok(Value::Object(output))
+    }
+
+    fn transform_value(
+        &self,
+        source_type: &NestedAttrType,
+        target_type: &NestedAttrType,
+        value: &Value,
+        path: &str,
+        report: &mut Transformed,
+    ) -> Result<Value, Error> {
+        match (source_type, target_type, value) {
+            (
+                NestedAttrType::Reference(RefValue::Said(source_said)),
+                NestedAttrType::Reference(RefValue::Said(target_said)),
+                Value::Object(_),
+            ) if source_said != target_said => {
+                let source_said = source_said.to_string();
+                let target_said = target_said.to_string();
+                let source = self.dependency(&source_said)?;
+                let target = self.dependency(&target_said)?;
+                let identity = Transformation::new();
+                let transformation = self
+                    .transformations
+                    .iter()
+                    .find(|t| {
+                        t.source.as_deref() == Some(source_said.as_str())
+                            && t.target.as_deref() == Some(target_said.as_str())
+                    })
+                    .unwrap_or(&identity);
+                self.transform_object(transformation, source, target, value, path, report)
+            }
+            (
+                NestedAttrType::Array(source_type),
+                NestedAttrType::Array(target_type),
+                Value::Array(elements),
+            ) => elements
+                .iter()
+                .enumerate()
+                .map(|(i, element)| {
+                    self.transform_value(
+                        source_type,
+                        target_type,
+                        element,
+                        &format!("{path}[{i}]"),
+                        report,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            _ => Ok(value.clone()),
+        }
+    }
+
+    fn dependency(&self, said: &str) -> Result<&'a OCABundle, Error> {
+        self.dependencies
+            .iter()
+            .find(|dependency| said_of(dependency) == said)
+            .ok_or_else(|| Error::MissingDependency(said.to_string()))
+    }
+}
+
+fn said_of(bundle: &OCABundle) -> String {
+    bundle
+        .said
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+fn is_mandatory(attribute: &Attribute) -> bool {
+    attribute.conformance.as_deref() == Some("M")
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+    use serde_json::json;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        oca_bundle_semantics::build::from_ast(None, &ast)
+            .unwrap()
+            .oca_bundle
+    }
+
+    fn transformation(
+        source: &OCABundle,
+        target: &OCABundle,
+        renames: &[(&str, &str)],
+    ) -> Transformation {
+        let mut transformation = Transformation::new();
+        transformation.set_source(said_of(source));
+        transformation.set_target(said_of(target));
+        transformation.rename(
+            renames
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect::<IndexMap<_, _>>(),
+        );
+        transformation
+    }
+
+    #[test]
+    fn transform_record() {
+        let source_address = build("ADD ATTRIBUTE street=Text city=Text\n");
+        let target_address = build(
+            r#"
+ADD ATTRIBUTE road=Text city=Text zip=Text
+ADD CONFORMANCE ATTRS road="M" zip="M"
+"#,
+        );
+        let source = build(&format!(
+            "ADD ATTRIBUTE first_name=Text nickname=Text addresses=Array[refs:{}]\n",
+            said_of(&source_address)
+        ));
+        let target = build(&format!(
+            r#"
+ADD ATTRIBUTE name=Text age=Numeric addresses=Array[refs:{}]
+ADD CONFORMANCE ATTRS name="M" age="M"
+"#,
+            said_of(&target_address)
+        ));
+
+        let person = transformation(&source, &target, &[("first_name", "name")]);
+        let address = transformation(&source_address, &target_address, &[("street", "road")]);
+        let dependencies = vec![source_address, target_address];
+        let transformations = vec![address];
+        let transformer = Transformer::new()
+            .with_dependencies(&dependencies)
+            .with_transformations(&transformations);
+
+        let transformed = transformer
+            .transform(
+                &person,
+                &source,
+                &target,
+                &json!({
+                    "first_name": "Alice",
+                    "nickname": "Al",
+                    "addresses": [{ "street": "Main", "city": "Bern" }]
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            transformed.record,
+            json!({
+                "name": "Alice",
+                "addresses": [{ "road": "Main", "city": "Bern" }]
+            })
+        );
+        assert_eq!(transformed.unmapped, vec!["nickname"]);
+        assert_eq!(transformed.unfilled, vec!["addresses[0].zip", "age"]);
+
+        assert_eq!(
+            transformer.transform(&person, &target, &source, &json!({})),
+            Err(Error::SourceMismatch {
+                expected: Some(said_of(&source)),
+                actual: said_of(&target),
+            })
+        );
+        assert_eq!(
+            Transformer::new().transform(
+                &person,
+                &source,
+                &target,
+                &json!({ "addresses": [{ "street": "Main" }] })
+            ),
+            Err(Error::MissingDependency(
+                dependencies[0].said.clone().unwrap().to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn transform_with_operations() {
+        let source = build("ADD ATTRIBUTE first=Text last=Text weight=Numeric sex=Text tmp=Text\n");
+        let target = build(
+            r#"
+ADD ATTRIBUTE name=Text mass=Numeric gender=Text country=Text
+ADD CONFORMANCE ATTRS country="M"
+"#,
+        );
+        let ast = oca_file_transformation::ocafile::parse_from_string(format!(
+            r#"
+-- source=refs:{}
+-- target=refs:{}
+RENAME ATTRIBUTE weight=mass sex=gender
+CONVERT UNIT ATTRIBUTE weight kg -> g
+MAP ENTRY_CODE ATTRIBUTE sex F=female M=male
+CONCAT ATTRIBUTE first last -> name SEPARATOR " "
+DEFAULT ATTRIBUTE country="CH"
+DROP ATTRIBUTE tmp
+"#,
+            said_of(&source),
+            said_of(&target)
+        ))
+        .unwrap();
+        let transformation = crate::build::from_ast(&ast).unwrap();
+        assert_eq!(transformation.operations.len(), 5);
+
+        let transformed = Transformer::new()
+            .transform(
+                &transformation,
+                &source,
+                &target,
+                &json!({
+                    "first": "Ada",
+                    "last": "Lovelace",
+                    "weight": 55.5,
+                    "sex": "F",
+                    "tmp": "x"
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            transformed.record,
+            json!({
+                "mass": 55500,
+                "gender": "female",
+                "name": "Ada Lovelace",
+                "country": "CH"
+            })
+        );
+        assert!(transformed.unmapped.is_empty());
+        assert!(transformed.unfilled.is_empty());
+
+        assert!(matches!(
+            Transformer::new().transform(
+                &transformation,
+                &source,
+                &target,
+                &json!({ "weight": "heavy" })
+            ),
+            Err(Error::Operation { .. })
+        ));
+    }
+}
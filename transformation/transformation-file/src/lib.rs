@@ -1,2 +1,5 @@
 pub mod build;
+pub mod engine;
+pub mod matcher;
 pub mod state;
+pub mod validator;
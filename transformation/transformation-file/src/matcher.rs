@@ -0,0 +1,432 @@
+//! Suggesting transformation between two bundles.
+//!
+//! Every attribute of the source bundle is compared with every attribute of
+//! the target bundle. Evidence of correspondence comes from names, shared
+//! attribute framing IRIs, labels in common languages and overlap of entry
+//! codes, and is discounted when types are not compatible. Pairs are then
+//! matched one to one, best first.
+use crate::state::Transformation;
+use oca_ast_semantics::ast::NestedAttrType;
+use oca_ast_transformation::ast;
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    oca::OCABundle,
+    record::{attributes_of, entry_codes_list},
+};
+use serde::Serialize;
+use std::{collections::HashSet, fmt};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Evidence {
+    ExactName,
+    NormalizedName,
+    /// Share of common words in names
+    SimilarName(f64),
+    /// IRI both attributes are framed with
+    SharedFraming(String),
+    /// Similarity of labels in the language, as ISO 639-3 code
+    SimilarLabel {
+        language: String,
+        similarity: f64,
+    },
+    /// Share of common entry codes
+    EntryCodeOverlap(f64),
+    TypeMismatch,
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Evidence::ExactName => write!(f, "exact name"),
+            Evidence::NormalizedName => write!(f, "normalized name"),
+            Evidence::SimilarName(similarity) => write!(f, "similar name {similarity:.2}"),
+            Evidence::SharedFraming(iri) => write!(f, "shared framing {iri}"),
+            Evidence::SimilarLabel {
+                language,
+                similarity,
+            } => write!(f, "similar {language} label {similarity:.2}"),
+            Evidence::EntryCodeOverlap(overlap) => write!(f, "entry code overlap {overlap:.2}"),
+            Evidence::TypeMismatch => write!(f, "type mismatch"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Match {
+    pub source: String,
+    pub target: String,
+    /// Between 0 and 1
+    pub confidence: f64,
+    pub evidence: Vec<Evidence>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub transformation: Transformation,
+    /// Matched attributes, by descending confidence
+    pub matches: Vec<Match>,
+}
+
+impl Suggestion {
+    /// Transformation OCAfile with one `LINK` command per match, each
+    /// preceded by comment with its confidence and evidence.
+    pub fn to_ocafile(&self) -> String {
+        let mut ast = self.transformation.to_ast();
+        ast.commands = self
+            .matches
+            .iter()
+            .map(|m| ast::Command {
+                kind: ast::CommandType::Link,
+                object_kind: ast::ObjectKind::Link(ast::LinkContent {
+                    attributes: Some([(m.source.clone(), m.target.clone())].into()),
+                }),
+            })
+            .collect();
+        let generated = oca_file_transformation::ocafile::generate_from_ast(&ast);
+
+        let mut ocafile = String::new();
+        let mut matches = self.matches.iter();
+        for line in generated.lines() {
+            if !line.starts_with("--") {
+                if let Some(m) = matches.next() {
+                    let evidence = m
+                        .evidence
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    ocafile.push_str(&format!("# confidence {:.2}: {}\n", m.confidence, evidence));
+                }
+            }
+            ocafile.push_str(line);
+            ocafile.push('\n');
+        }
+        ocafile
+    }
+}
+
+pub struct Matcher {
+    threshold: f64,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Matcher {
+    pub fn new() -> Self {
+        Self { threshold: 0.5 }
+    }
+
+    /// Minimal confidence of proposed matches, 0.5 by default.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn suggest(&self, source: &OCABundle, target: &OCABundle) -> Suggestion {
+        let source_attributes = attributes_of(source);
+        let target_attributes = attributes_of(target);
+
+        let mut candidates = vec![];
+        for source_attribute in &source_attributes {
+            for target_attribute in &target_attributes {
+                let candidate = compare(source_attribute, target_attribute);
+                if candidate.confidence >= self.threshold {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        // Attributes are sorted by name, so the order of equally confident
+        // candidates is stable
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        let mut matched_sources = HashSet::new();
+        let mut matched_targets = HashSet::new();
+        let mut matches = vec![];
+        for candidate in candidates {
+            if !matched_sources.contains(&candidate.source)
+                && !matched_targets.contains(&candidate.target)
+            {
+                matched_sources.insert(candidate.source.clone());
+                matched_targets.insert(candidate.target.clone());
+                matches.push(candidate);
+            }
+        }
+
+        let mut transformation = Transformation::new();
+        if let Some(said) = &source.said {
+            transformation.set_source(said.to_string());
+        }
+        if let Some(said) = &target.said {
+            transformation.set_target(said.to_string());
+        }
+        transformation.link(
+            matches
+                .iter()
+                .map(|m| (m.source.clone(), m.target.clone()))
+                .collect(),
+        );
+        transformation.fill_said();
+
+        Suggestion {
+            transformation,
+            matches,
+        }
+    }
+}
+
+/// Weighs evidence found for the pair of attributes. Each piece of evidence
+/// independently lowers the chance of pair being unrelated.
+fn compare(source: &Attribute, target: &Attribute) -> Match {
+    let mut evidence = vec![];
+    let mut weights = vec![];
+
+    let source_words = words(&source.name);
+    let target_words = words(&target.name);
+    if source.name == target.name {
+        evidence.push(Evidence::ExactName);
+        weights.push(0.95);
+    } else if source_words == target_words {
+        evidence.push(Evidence::NormalizedName);
+        weights.push(0.9);
+    } else {
+        let similarity = jaccard(&source_words, &target_words);
+        if similarity > 0.0 {
+            evidence.push(Evidence::SimilarName(similarity));
+            weights.push(0.6 * similarity);
+        }
+    }
+
+    let iris = |attribute: &Attribute| -> HashSet<String> {
+        attribute
+            .framings
+            .iter()
+            .flat_map(|framings| framings.values())
+            .flat_map(|framing| framing.keys().cloned())
+            .collect()
+    };
+    let source_iris = iris(source);
+    let mut shared_iris = iris(target)
+        .intersection(&source_iris)
+        .cloned()
+        .collect::<Vec<_>>();
+    shared_iris.sort();
+    if let Some(iri) = shared_iris.into_iter().next() {
+        evidence.push(Evidence::SharedFraming(iri));
+        weights.push(0.95);
+    }
+
+    if let (Some(source_labels), Some(target_labels)) = (&source.labels, &target.labels) {
+        let best = source_labels
+            .iter()
+            .filter_map(|(language, source_label)| {
+                let target_label = target_labels.get(language)?;
+                Some((
+                    language.to_639_3().to_string(),
+                    dice(&normalize(source_label), &normalize(target_label)),
+                ))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)));
+        if let Some((language, similarity)) = best.filter(|(_, similarity)| *similarity > 0.0) {
+            evidence.push(Evidence::SimilarLabel {
+                language,
+                similarity,
+            });
+            weights.push(0.8 * similarity);
+        }
+    }
+
+    let codes = |attribute: &Attribute| -> Option<HashSet<String>> {
+        let codes = attribute.entry_codes.as_ref().and_then(entry_codes_list)?;
+        Some(codes.into_iter().map(|code| normalize(&code)).collect())
+    };
+    if let (Some(source_codes), Some(target_codes)) = (codes(source), codes(target)) {
+        let overlap = jaccard(&source_codes, &target_codes);
+        if overlap > 0.0 {
+            evidence.push(Evidence::EntryCodeOverlap(overlap));
+            weights.push(0.7 * overlap);
+        }
+    }
+
+    let mut confidence = 1.0 - weights.iter().map(|w| 1.0 - w).product::<f64>();
+    if !compatible(
+        source.attribute_type.as_ref(),
+        target.attribute_type.as_ref(),
+    ) {
+        evidence.push(Evidence::TypeMismatch);
+        confidence *= 0.3;
+    }
+
+    Match {
+        source: source.name.clone(),
+        target: target.name.clone(),
+        confidence,
+        evidence,
+    }
+}
+
+/// Types are compatible when equal, or when both are references.
+fn compatible(source: Option<&NestedAttrType>, target: Option<&NestedAttrType>) -> bool {
+    match (source, target) {
+        (Some(NestedAttrType::Array(source)), Some(NestedAttrType::Array(target))) => {
+            compatible(Some(source), Some(target))
+        }
+        (Some(NestedAttrType::Reference(_)), Some(NestedAttrType::Reference(_))) => true,
+        (source, target) => source == target,
+    }
+}
+
+/// Lowercase words of name, split at `_`, `-`, spaces and camel case humps,
+/// e.g. `firstName` and `first_name` give `first` and `name`.
+fn words(name: &str) -> HashSet<String> {
+    let mut words = HashSet::new();
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        let boundary = !c.is_alphanumeric() || (c.is_uppercase() && previous_lowercase);
+        if boundary && !word.is_empty() {
+            words.insert(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        }
+        previous_lowercase = c.is_lowercase() || c.is_numeric();
+    }
+    if !word.is_empty() {
+        words.insert(word);
+    }
+    words
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Dice coefficient of character bigrams
+fn dice(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let bigrams = |text: &str| -> Vec<(char, char)> {
+        let chars = text.chars().collect::<Vec<_>>();
+        chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    };
+    let a = bigrams(a);
+    let mut b = bigrams(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let total = a.len() + b.len();
+    let mut common = 0;
+    for bigram in a {
+        if let Some(i) = b.iter().position(|other| *other == bigram) {
+            b.swap_remove(i);
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        oca_bundle_semantics::build::from_ast(None, &ast)
+            .unwrap()
+            .oca_bundle
+    }
+
+    #[test]
+    fn suggest_transformation() {
+        let ours = build(
+            r#"
+ADD ATTRIBUTE first_name=Text age=Numeric sex=Text dob=DateTime notes=Text
+ADD LABEL en ATTRS dob="Date of birth"
+ADD ENTRY_CODE ATTRS sex=["F", "M"]
+ADD ATTR_FRAMING id="SNOMEDCT" label="SNOMED" location="https://snomed.org" version="1" ATTRS age={"http://snomed.info/id/397669002": {"predicate_id": "skos:exactMatch", "framing_justification": "semapv:ManualMappingCuration"}}
+"#,
+        );
+        let theirs = build(
+            r#"
+ADD ATTRIBUTE firstName=Text years=Numeric gender=Text birth=DateTime notes=Numeric
+ADD LABEL en ATTRS birth="Date of Birth"
+ADD ENTRY_CODE ATTRS gender=["F", "M"]
+ADD ATTR_FRAMING id="SNOMEDCT" label="SNOMED" location="https://snomed.org" version="1" ATTRS years={"http://snomed.info/id/397669002": {"predicate_id": "skos:exactMatch", "framing_justification": "semapv:ManualMappingCuration"}}
+"#,
+        );
+
+        let suggestion = Matcher::new().suggest(&ours, &theirs);
+        let pairs = suggestion
+            .matches
+            .iter()
+            .map(|m| (m.source.as_str(), m.target.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![
+                ("age", "years"),
+                ("first_name", "firstName"),
+                ("dob", "birth"),
+                ("sex", "gender"),
+            ]
+        );
+        assert_eq!(
+            suggestion.matches[1].evidence,
+            vec![Evidence::NormalizedName]
+        );
+        assert_eq!(
+            suggestion.transformation.source,
+            Some(ours.said.clone().unwrap().to_string())
+        );
+        assert_eq!(suggestion.transformation.attributes["sex"], "gender");
+
+        // Names alone do not outweigh incompatible types
+        let notes = |bundle: &OCABundle| {
+            attributes_of(bundle)
+                .into_iter()
+                .find(|attribute| attribute.name == "notes")
+                .unwrap()
+        };
+        let notes = compare(&notes(&ours), &notes(&theirs));
+        assert_eq!(
+            notes.evidence,
+            vec![Evidence::ExactName, Evidence::TypeMismatch]
+        );
+        assert!(notes.confidence < 0.5);
+        assert_eq!(
+            Matcher::new()
+                .with_threshold(0.2)
+                .suggest(&ours, &theirs)
+                .matches
+                .len(),
+            5
+        );
+
+        let ocafile = suggestion.to_ocafile();
+        assert!(ocafile.contains(
+            "# confidence 0.90: normalized name\nLINK ATTRIBUTE first_name -> firstName\n"
+        ));
+        let ast = oca_file_transformation::ocafile::parse_from_string(ocafile).unwrap();
+        let transformation = crate::build::from_ast(&ast).unwrap();
+        assert_eq!(
+            transformation.attributes,
+            suggestion.transformation.attributes
+        );
+    }
+}
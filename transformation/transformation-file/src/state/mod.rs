@@ -1,9 +1,25 @@
 use indexmap::IndexMap;
+use oca_ast_transformation::ast;
 use said::derivation::HashFunctionCode;
 use said::version::SerializationInfo;
 use said::{sad::SerializationFormats, sad::SAD};
 use serde::{Deserialize, Serialize};
 
+pub mod operation;
+
+pub use operation::Operation;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Error {
+    #[error("Transformation source {actual:?} does not match target {expected:?}")]
+    NotComposable {
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+    #[error("Transformation is not invertible: {0}")]
+    NotInvertible(String),
+}
+
 #[derive(SAD, Serialize, Debug, Deserialize, Clone)]
 #[version(protocol = "OCAT", major = 1, minor = 0)]
 // #[said(format = "JSON")]
@@ -14,6 +30,8 @@ pub struct Transformation {
     pub source: Option<String>,
     pub target: Option<String>,
     pub attributes: IndexMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operations: Vec<Operation>,
 }
 
 impl Default for Transformation {
@@ -29,6 +47,7 @@ impl Transformation {
             source: None,
             target: None,
             attributes: IndexMap::new(),
+            operations: vec![],
         }
     }
 
@@ -51,9 +70,210 @@ impl Transformation {
         });
     }
 
+    pub fn add_operation(&mut self, operation: Operation) {
+        self.operations.push(operation);
+    }
+
     pub fn fill_said(&mut self) {
         let code = HashFunctionCode::Blake3_256;
         let format = SerializationFormats::JSON;
         self.compute_digest(&code, &format);
     }
+
+    /// Commands reproducing the transformation, with source and target
+    /// bundles in meta. Renamed and linked attributes are given by a single
+    /// `RENAME` command.
+    pub fn to_ast(&self) -> ast::TransformationAST {
+        let mut ast = ast::TransformationAST::new();
+        if let Some(source) = &self.source {
+            ast.meta
+                .insert("source".to_string(), format!("refs:{}", source));
+        }
+        if let Some(target) = &self.target {
+            ast.meta
+                .insert("target".to_string(), format!("refs:{}", target));
+        }
+        if !self.attributes.is_empty() {
+            ast.commands.push(ast::Command {
+                kind: ast::CommandType::Rename,
+                object_kind: ast::ObjectKind::Rename(ast::RenameContent {
+                    attributes: Some(self.attributes.clone()),
+                }),
+            });
+        }
+        for operation in &self.operations {
+            ast.commands.push(operation.to_command());
+        }
+        ast
+    }
+
+    /// Composes transformation with the `next` one, whose source is the
+    /// target of this one, into a single transformation from the source of
+    /// this one to the target of `next`. Operations of `next` are rewritten to
+    /// the attribute names of this source and run after operations of this
+    /// transformation.
+    pub fn compose(&self, next: &Transformation) -> Result<Transformation, Error> {
+        if self.target.is_none() || self.target != next.source {
+            return Err(Error::NotComposable {
+                expected: self.target.clone(),
+                actual: next.source.clone(),
+            });
+        }
+        // Names in the intermediate bundle of attributes renamed by this
+        // transformation, mapped back to names in this source.
+        let inverse = self
+            .attributes
+            .iter()
+            .map(|(from, to)| (to.clone(), from.clone()))
+            .collect::<IndexMap<_, _>>();
+
+        let mut composed = Transformation::new();
+        composed.source = self.source.clone();
+        composed.target = next.target.clone();
+        for (from, to) in &self.attributes {
+            let to = next.attributes.get(to).unwrap_or(to);
+            composed.attributes.insert(from.clone(), to.clone());
+        }
+        for (from, to) in &next.attributes {
+            // Attribute renamed by this transformation is no longer there
+            // under its source name
+            if !inverse.contains_key(from) && !self.attributes.contains_key(from) {
+                composed.attributes.insert(from.clone(), to.clone());
+            }
+        }
+        composed.operations = self.operations.clone();
+        composed.operations.extend(
+            next.operations
+                .iter()
+                .map(|operation| operation.rename_inputs(&inverse)),
+        );
+        composed.fill_said();
+        Ok(composed)
+    }
+
+    /// Inverts transformation which only renames or links attributes.
+    pub fn invert(&self) -> Result<Transformation, Error> {
+        if !self.operations.is_empty() {
+            return Err(Error::NotInvertible(
+                "value operations can not be reverted".to_string(),
+            ));
+        }
+        let mut inverted = Transformation::new();
+        inverted.source = self.target.clone();
+        inverted.target = self.source.clone();
+        for (from, to) in &self.attributes {
+            if let Some(other) = inverted.attributes.insert(to.clone(), from.clone()) {
+                return Err(Error::NotInvertible(format!(
+                    "attributes {other} and {from} are both mapped to {to}"
+                )));
+            }
+        }
+        inverted.fill_said();
+        Ok(inverted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transformation(source: &str, target: &str, renames: &[(&str, &str)]) -> Transformation {
+        let mut transformation = Transformation::new();
+        transformation.set_source(source.to_string());
+        transformation.set_target(target.to_string());
+        transformation.rename(
+            renames
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        );
+        transformation.fill_said();
+        transformation
+    }
+
+    #[test]
+    fn compose_transformations() {
+        let mut v1_v2 = transformation("v1", "v2", &[("surname", "last_name"), ("kg", "weight")]);
+        v1_v2.add_operation(Operation::Drop {
+            attributes: vec!["tmp".to_string()],
+        });
+        let mut v2_v3 = transformation(
+            "v2",
+            "v3",
+            &[("last_name", "family_name"), ("age", "years")],
+        );
+        v2_v3.add_operation(Operation::ConvertUnit {
+            attribute: "weight".to_string(),
+            from: "kg".to_string(),
+            to: "g".to_string(),
+        });
+
+        let v1_v3 = v1_v2.compose(&v2_v3).unwrap();
+        assert_eq!(v1_v3.source.as_deref(), Some("v1"));
+        assert_eq!(v1_v3.target.as_deref(), Some("v3"));
+        assert_eq!(
+            v1_v3.attributes.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("surname".to_string(), "family_name".to_string()),
+                ("kg".to_string(), "weight".to_string()),
+                ("age".to_string(), "years".to_string()),
+            ]
+        );
+        assert_eq!(
+            v1_v3.operations,
+            vec![
+                Operation::Drop {
+                    attributes: vec!["tmp".to_string()],
+                },
+                Operation::ConvertUnit {
+                    attribute: "kg".to_string(),
+                    from: "kg".to_string(),
+                    to: "g".to_string(),
+                },
+            ]
+        );
+        assert!(v1_v3.said.is_some());
+        assert_ne!(v1_v3.said, v1_v2.said);
+
+        assert_eq!(
+            v2_v3.compose(&v1_v2).unwrap_err(),
+            Error::NotComposable {
+                expected: Some("v3".to_string()),
+                actual: Some("v1".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn invert_transformation() {
+        let v1_v2 = transformation("v1", "v2", &[("surname", "last_name")]);
+        let v2_v1 = v1_v2.invert().unwrap();
+        assert_eq!(v2_v1.source.as_deref(), Some("v2"));
+        assert_eq!(v2_v1.target.as_deref(), Some("v1"));
+        assert_eq!(v2_v1.attributes["last_name"], "surname");
+        assert_eq!(v2_v1.invert().unwrap().said, v1_v2.said);
+
+        let merged = transformation("v1", "v2", &[("a", "x"), ("b", "x")]);
+        assert!(matches!(merged.invert(), Err(Error::NotInvertible(_))));
+        let mut converted = v1_v2.clone();
+        converted.add_operation(Operation::Drop {
+            attributes: vec!["tmp".to_string()],
+        });
+        assert!(matches!(converted.invert(), Err(Error::NotInvertible(_))));
+    }
+
+    #[test]
+    fn transformation_to_ast() {
+        let mut transformation = transformation("v1", "v2", &[("surname", "last_name")]);
+        transformation.add_operation(Operation::Drop {
+            attributes: vec!["tmp".to_string()],
+        });
+        transformation.fill_said();
+        let ast = transformation.to_ast();
+        assert_eq!(ast.meta["source"], "refs:v1");
+        let rebuilt = crate::build::from_ast(&ast).unwrap();
+        assert_eq!(rebuilt.attributes, transformation.attributes);
+        assert_eq!(rebuilt.operations, transformation.operations);
+        assert_eq!(rebuilt.said, transformation.said);
+    }
 }
@@ -0,0 +1,528 @@
+use indexmap::IndexMap;
+use oca_ast_transformation::ast;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+/// Operation on attribute values, run on the source record before
+/// attributes are renamed. Attributes created by operations are named as in
+/// the target bundle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Converts numbers between units of the same dimension, e.g. `kg` to `g`.
+    ConvertUnit {
+        attribute: String,
+        from: String,
+        to: String,
+    },
+    /// Converts dates between formats built of `YYYY`, `MM`, `DD`, `hh`, `mm`
+    /// and `ss` tokens.
+    ConvertDate {
+        attribute: String,
+        from: String,
+        to: String,
+    },
+    /// Replaces entry codes. Codes missing in the mapping are kept.
+    MapEntryCodes {
+        attribute: String,
+        mapping: IndexMap<String, String>,
+    },
+    /// Joins values of attributes into the target attribute and removes them.
+    Concat {
+        attributes: Vec<String>,
+        target: String,
+        separator: String,
+    },
+    /// Splits value of attribute into the target attributes and removes it.
+    /// The last target gets the rest of the value.
+    Split {
+        attribute: String,
+        targets: Vec<String>,
+        separator: String,
+    },
+    /// Sets values of attributes which are missing or null.
+    Default {
+        attributes: IndexMap<String, Value>,
+    },
+    Drop {
+        attributes: Vec<String>,
+    },
+}
+
+/// Linear conversion of unit to the base unit of its dimension:
+/// `base = value * factor + offset`.
+struct Unit {
+    dimension: &'static str,
+    factor: f64,
+    offset: f64,
+}
+
+const UNITS: &[(&str, &str, f64, f64)] = &[
+    ("mg", "mass", 1e-6, 0.0),
+    ("g", "mass", 1e-3, 0.0),
+    ("kg", "mass", 1.0, 0.0),
+    ("t", "mass", 1e3, 0.0),
+    ("oz", "mass", 0.028_349_523_125, 0.0),
+    ("lb", "mass", 0.453_592_37, 0.0),
+    ("mm", "length", 1e-3, 0.0),
+    ("cm", "length", 1e-2, 0.0),
+    ("m", "length", 1.0, 0.0),
+    ("km", "length", 1e3, 0.0),
+    ("in", "length", 0.0254, 0.0),
+    ("ft", "length", 0.3048, 0.0),
+    ("yd", "length", 0.9144, 0.0),
+    ("mi", "length", 1_609.344, 0.0),
+    ("ml", "volume", 1e-3, 0.0),
+    ("l", "volume", 1.0, 0.0),
+    ("ms", "time", 1e-3, 0.0),
+    ("s", "time", 1.0, 0.0),
+    ("min", "time", 60.0, 0.0),
+    ("h", "time", 3_600.0, 0.0),
+    ("d", "time", 86_400.0, 0.0),
+    ("K", "temperature", 1.0, 0.0),
+    ("C", "temperature", 1.0, 273.15),
+    ("F", "temperature", 5.0 / 9.0, 273.15 - 32.0 * 5.0 / 9.0),
+];
+
+fn unit(name: &str) -> Option<Unit> {
+    UNITS
+        .iter()
+        .find(|(unit, ..)| *unit == name)
+        .map(|(_, dimension, factor, offset)| Unit {
+            dimension,
+            factor: *factor,
+            offset: *offset,
+        })
+}
+
+const DATE_TOKENS: &[(&str, usize)] = &[
+    ("YYYY", 4),
+    ("MM", 2),
+    ("DD", 2),
+    ("hh", 2),
+    ("mm", 2),
+    ("ss", 2),
+];
+
+/// Splits date format into tokens and literal characters.
+fn date_format(format: &str) -> Vec<Result<(&'static str, usize), char>> {
+    let mut parts = vec![];
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        match DATE_TOKENS
+            .iter()
+            .find(|(token, _)| rest.starts_with(token))
+        {
+            Some((token, digits)) => {
+                parts.push(Ok((*token, *digits)));
+                rest = &rest[token.len()..];
+            }
+            None => {
+                parts.push(Err(c));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    parts
+}
+
+impl Operation {
+    /// Checks operation arguments, returning description of each problem.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        match self {
+            Operation::ConvertUnit { from, to, .. } => match (unit(from), unit(to)) {
+                (Some(from_unit), Some(to_unit)) if from_unit.dimension != to_unit.dimension => {
+                    errors.push(format!("Can not convert {from} to {to}"))
+                }
+                (from_unit, to_unit) => {
+                    for (name, unit) in [(from, from_unit), (to, to_unit)] {
+                        if unit.is_none() {
+                            errors.push(format!("Unknown unit: {name}"));
+                        }
+                    }
+                }
+            },
+            Operation::ConvertDate { from, to, .. } => {
+                let from_tokens = date_format(from)
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .map(|(token, _)| token)
+                    .collect::<Vec<_>>();
+                for (token, _) in date_format(to).into_iter().filter_map(Result::ok) {
+                    if !from_tokens.contains(&token) {
+                        errors.push(format!("Date format {from} has no {token}"));
+                    }
+                }
+            }
+            Operation::Concat { attributes, .. } if attributes.is_empty() => {
+                errors.push("Nothing to concatenate".to_string())
+            }
+            Operation::Split {
+                targets, separator, ..
+            } => {
+                if targets.is_empty() {
+                    errors.push("Missing split targets".to_string());
+                }
+                if separator.is_empty() {
+                    errors.push("Missing split separator".to_string());
+                }
+            }
+            _ => (),
+        }
+        errors
+    }
+
+    /// Returns operation reading attributes under other names, given by
+    /// `names`. Names of attributes created by the operation are kept.
+    pub fn rename_inputs(&self, names: &IndexMap<String, String>) -> Operation {
+        let rename = |attribute: &String| names.get(attribute).unwrap_or(attribute).clone();
+        let mut operation = self.clone();
+        match &mut operation {
+            Operation::ConvertUnit { attribute, .. }
+            | Operation::ConvertDate { attribute, .. }
+            | Operation::MapEntryCodes { attribute, .. }
+            | Operation::Split { attribute, .. } => *attribute = rename(attribute),
+            Operation::Concat { attributes, .. } | Operation::Drop { attributes } => attributes
+                .iter_mut()
+                .for_each(|attribute| *attribute = rename(attribute)),
+            Operation::Default { .. } => (),
+        }
+        operation
+    }
+
+    /// Transformation command adding this operation.
+    pub fn to_command(&self) -> ast::Command {
+        let (kind, object_kind) = match self.clone() {
+            Operation::ConvertUnit {
+                attribute,
+                from,
+                to,
+            } => (
+                ast::CommandType::ConvertUnit,
+                ast::ObjectKind::ConvertUnit(ast::ConvertContent {
+                    attribute,
+                    from,
+                    to,
+                }),
+            ),
+            Operation::ConvertDate {
+                attribute,
+                from,
+                to,
+            } => (
+                ast::CommandType::ConvertDate,
+                ast::ObjectKind::ConvertDate(ast::ConvertContent {
+                    attribute,
+                    from,
+                    to,
+                }),
+            ),
+            Operation::MapEntryCodes { attribute, mapping } => (
+                ast::CommandType::MapEntryCodes,
+                ast::ObjectKind::MapEntryCodes(ast::MapEntryCodesContent { attribute, mapping }),
+            ),
+            Operation::Concat {
+                attributes,
+                target,
+                separator,
+            } => (
+                ast::CommandType::Concat,
+                ast::ObjectKind::Concat(ast::ConcatContent {
+                    attributes,
+                    target,
+                    separator,
+                }),
+            ),
+            Operation::Split {
+                attribute,
+                targets,
+                separator,
+            } => (
+                ast::CommandType::Split,
+                ast::ObjectKind::Split(ast::SplitContent {
+                    attribute,
+                    targets,
+                    separator,
+                }),
+            ),
+            Operation::Default { attributes } => (
+                ast::CommandType::Default,
+                ast::ObjectKind::Default(ast::DefaultContent { attributes }),
+            ),
+            Operation::Drop { attributes } => (
+                ast::CommandType::Drop,
+                ast::ObjectKind::Drop(ast::DropContent { attributes }),
+            ),
+        };
+        ast::Command { kind, object_kind }
+    }
+
+    pub fn apply(&self, record: &mut Map<String, Value>) -> Result<(), String> {
+        match self {
+            Operation::ConvertUnit {
+                attribute,
+                from,
+                to,
+            } => {
+                let (Some(from_unit), Some(to_unit)) = (unit(from), unit(to)) else {
+                    return Err(format!("Can not convert {from} to {to}"));
+                };
+                map_values(record, attribute, |value| {
+                    let number = value
+                        .as_f64()
+                        .ok_or_else(|| format!("{attribute}: {value} is not a number"))?;
+                    let base = number * from_unit.factor + from_unit.offset;
+                    Ok(number_value((base - to_unit.offset) / to_unit.factor))
+                })
+            }
+            Operation::ConvertDate {
+                attribute,
+                from,
+                to,
+            } => map_values(record, attribute, |value| {
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| format!("{attribute}: {value} is not a date"))?;
+                convert_date(text, from, to)
+                    .map(Value::String)
+                    .ok_or_else(|| format!("{attribute}: {text} does not match {from}"))
+            }),
+            Operation::MapEntryCodes { attribute, mapping } => {
+                map_values(record, attribute, |value| {
+                    Ok(match value.as_str().and_then(|code| mapping.get(code)) {
+                        Some(code) => Value::String(code.clone()),
+                        None => value.clone(),
+                    })
+                })
+            }
+            Operation::Concat {
+                attributes,
+                target,
+                separator,
+            } => {
+                let parts = attributes
+                    .iter()
+                    .filter_map(|attribute| record.remove(attribute))
+                    .filter(|value| !value.is_null())
+                    .map(|value| match value {
+                        Value::String(text) => text,
+                        value => value.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                if !parts.is_empty() {
+                    record.insert(target.clone(), Value::String(parts.join(separator)));
+                }
+                Ok(())
+            }
+            Operation::Split {
+                attribute,
+                targets,
+                separator,
+            } => {
+                let Some(value) = record.remove(attribute).filter(|value| !value.is_null()) else {
+                    return Ok(());
+                };
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| format!("{attribute}: {value} is not a text"))?;
+                for (target, part) in targets.iter().zip(text.splitn(targets.len(), separator)) {
+                    record.insert(target.clone(), Value::String(part.to_string()));
+                }
+                Ok(())
+            }
+            Operation::Default { attributes } => {
+                for (attribute, default) in attributes {
+                    let value = record.entry(attribute.clone()).or_insert(Value::Null);
+                    if value.is_null() {
+                        *value = default.clone();
+                    }
+                }
+                Ok(())
+            }
+            Operation::Drop { attributes } => {
+                for attribute in attributes {
+                    record.remove(attribute);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Replaces value of attribute, or each element of array value. Missing and
+/// null values are skipped.
+fn map_values(
+    record: &mut Map<String, Value>,
+    attribute: &str,
+    f: impl Fn(&Value) -> Result<Value, String>,
+) -> Result<(), String> {
+    match record.get_mut(attribute) {
+        Some(Value::Null) | None => Ok(()),
+        Some(Value::Array(elements)) => {
+            for element in elements.iter_mut().filter(|element| !element.is_null()) {
+                *element = f(element)?;
+            }
+            Ok(())
+        }
+        Some(value) => {
+            *value = f(value)?;
+            Ok(())
+        }
+    }
+}
+
+/// Number rounded to 12 significant digits, to hide floating point noise.
+/// Integral numbers are kept as integers.
+fn number_value(number: f64) -> Value {
+    let number = format!("{number:.11e}").parse::<f64>().unwrap_or(number);
+    if number.fract() == 0.0 && number.abs() < 9_007_199_254_740_992.0 {
+        Value::from(number as i64)
+    } else {
+        Number::from_f64(number)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+fn convert_date(text: &str, from: &str, to: &str) -> Option<String> {
+    let mut components = IndexMap::new();
+    let mut rest = text;
+    for part in date_format(from) {
+        match part {
+            Ok((token, digits)) => {
+                let value = rest.get(..digits)?;
+                if !value.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                components.insert(token, value);
+                rest = &rest[digits..];
+            }
+            Err(c) => rest = rest.strip_prefix(c)?,
+        }
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+    date_format(to)
+        .into_iter()
+        .map(|part| match part {
+            Ok((token, _)) => components.get(token).map(|value| value.to_string()),
+            Err(c) => Some(c.to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn apply(operation: Operation, record: Value) -> Result<Value, String> {
+        let mut record = record.as_object().unwrap().clone();
+        operation.apply(&mut record)?;
+        Ok(Value::Object(record))
+    }
+
+    #[test]
+    fn convert_values() {
+        let convert_unit = |from: &str, to: &str| Operation::ConvertUnit {
+            attribute: "v".to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        assert_eq!(
+            apply(convert_unit("kg", "g"), json!({ "v": 1.2 })),
+            Ok(json!({ "v": 1200 }))
+        );
+        assert_eq!(
+            apply(convert_unit("C", "F"), json!({ "v": [100, null] })),
+            Ok(json!({ "v": [212, null] }))
+        );
+        assert_eq!(
+            apply(convert_unit("mm", "m"), json!({ "v": 5 })),
+            Ok(json!({ "v": 0.005 }))
+        );
+        assert!(apply(convert_unit("kg", "g"), json!({ "v": "heavy" })).is_err());
+        assert_eq!(
+            convert_unit("kg", "m").validate(),
+            vec!["Can not convert kg to m"]
+        );
+        assert_eq!(
+            convert_unit("kg", "stone").validate(),
+            vec!["Unknown unit: stone"]
+        );
+
+        let convert_date = Operation::ConvertDate {
+            attribute: "v".to_string(),
+            from: "DD/MM/YYYY".to_string(),
+            to: "YYYY-MM-DD".to_string(),
+        };
+        assert_eq!(
+            apply(convert_date.clone(), json!({ "v": "31/12/1999" })),
+            Ok(json!({ "v": "1999-12-31" }))
+        );
+        assert!(apply(convert_date.clone(), json!({ "v": "1999-12-31" })).is_err());
+        assert!(Operation::ConvertDate {
+            attribute: "v".to_string(),
+            from: "YYYY".to_string(),
+            to: "YYYY-MM".to_string(),
+        }
+        .validate()
+        .contains(&"Date format YYYY has no MM".to_string()));
+
+        let map = Operation::MapEntryCodes {
+            attribute: "v".to_string(),
+            mapping: IndexMap::from([("F".to_string(), "female".to_string())]),
+        };
+        assert_eq!(
+            apply(map, json!({ "v": ["F", "X"] })),
+            Ok(json!({ "v": ["female", "X"] }))
+        );
+    }
+
+    #[test]
+    fn restructure_values() {
+        let concat = Operation::Concat {
+            attributes: vec!["first".to_string(), "last".to_string()],
+            target: "name".to_string(),
+            separator: " ".to_string(),
+        };
+        assert_eq!(
+            apply(
+                concat,
+                json!({ "first": "Ada", "last": "Lovelace", "age": 36 })
+            ),
+            Ok(json!({ "age": 36, "name": "Ada Lovelace" }))
+        );
+
+        let split = Operation::Split {
+            attribute: "name".to_string(),
+            targets: vec!["first".to_string(), "last".to_string()],
+            separator: " ".to_string(),
+        };
+        assert_eq!(
+            apply(split, json!({ "name": "Jean Le Rond" })),
+            Ok(json!({ "first": "Jean", "last": "Le Rond" }))
+        );
+
+        let default = Operation::Default {
+            attributes: IndexMap::from([
+                ("country".to_string(), json!("CH")),
+                ("age".to_string(), json!(0)),
+            ]),
+        };
+        assert_eq!(
+            apply(default, json!({ "country": null, "age": 3 })),
+            Ok(json!({ "country": "CH", "age": 3 }))
+        );
+
+        let drop = Operation::Drop {
+            attributes: vec!["tmp".to_string()],
+        };
+        assert_eq!(
+            apply(drop, json!({ "tmp": 1, "a": 2 })),
+            Ok(json!({ "a": 2 }))
+        );
+    }
+}
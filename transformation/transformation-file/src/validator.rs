@@ -0,0 +1,245 @@
+//! Validation of transformation against its source and target bundles.
+use crate::build::Error;
+use oca_ast_semantics::ast::{AttributeType, NestedAttrType};
+use oca_ast_transformation::ast::{self, ObjectKind};
+use oca_bundle_semantics::state::{
+    attribute::Attribute,
+    oca::OCABundle,
+    record::{attributes_of, entry_codes_list},
+};
+
+/// Checks that every attribute used by commands of the transformation
+/// exists in the capture base of the source or target bundle, and that
+/// mapped attributes have compatible types. Errors point to lines of the
+/// commands.
+pub fn validate(
+    ast: &ast::TransformationAST,
+    source: &OCABundle,
+    target: &OCABundle,
+) -> Result<(), Vec<Error>> {
+    let source_attributes = attributes_of(source);
+    let target_attributes = attributes_of(target);
+    let source_attribute = |name: &str| -> Result<&Attribute, String> {
+        source_attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .ok_or_else(|| format!("Attribute {name} not found in source bundle"))
+    };
+    let target_attribute = |name: &str| -> Result<&Attribute, String> {
+        target_attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .ok_or_else(|| format!("Attribute {name} not found in target bundle"))
+    };
+
+    let mut errors = vec![];
+    for (i, command) in ast.commands.iter().enumerate() {
+        let mut messages = vec![];
+        match &command.object_kind {
+            ObjectKind::Rename(ast::RenameContent {
+                attributes: Some(attributes),
+            })
+            | ObjectKind::Link(ast::LinkContent {
+                attributes: Some(attributes),
+            }) => {
+                for (from, to) in attributes {
+                    match (source_attribute(from), target_attribute(to)) {
+                        (Ok(from_attribute), Ok(to_attribute)) => {
+                            if !compatible(
+                                from_attribute.attribute_type.as_ref(),
+                                to_attribute.attribute_type.as_ref(),
+                            ) {
+                                messages.push(format!(
+                                    "Attribute {from} of type {} can not be mapped to {to} of type {}",
+                                    type_name(from_attribute.attribute_type.as_ref()),
+                                    type_name(to_attribute.attribute_type.as_ref())
+                                ));
+                            }
+                        }
+                        (from_attribute, to_attribute) => {
+                            messages.extend(from_attribute.err());
+                            messages.extend(to_attribute.err());
+                        }
+                    }
+                }
+            }
+            ObjectKind::ConvertUnit(content) => match source_attribute(&content.attribute) {
+                Ok(attribute) if !has_value_type(attribute, AttributeType::Numeric) => {
+                    messages.push(format!("Attribute {} is not numeric", content.attribute))
+                }
+                Ok(_) => (),
+                Err(message) => messages.push(message),
+            },
+            ObjectKind::ConvertDate(content) => match source_attribute(&content.attribute) {
+                Ok(attribute)
+                    if !has_value_type(attribute, AttributeType::DateTime)
+                        && !has_value_type(attribute, AttributeType::Text) =>
+                {
+                    messages.push(format!("Attribute {} is not a date", content.attribute))
+                }
+                Ok(_) => (),
+                Err(message) => messages.push(message),
+            },
+            ObjectKind::MapEntryCodes(content) => match source_attribute(&content.attribute) {
+                Ok(attribute) => {
+                    let codes = attribute.entry_codes.as_ref().and_then(entry_codes_list);
+                    if let Some(codes) = codes {
+                        messages.extend(
+                            content
+                                .mapping
+                                .keys()
+                                .filter(|code| !codes.contains(code))
+                                .map(|code| {
+                                    format!(
+                                        "Entry code {code} not found in attribute {}",
+                                        content.attribute
+                                    )
+                                }),
+                        );
+                    }
+                }
+                Err(message) => messages.push(message),
+            },
+            ObjectKind::Concat(content) => {
+                for attribute in &content.attributes {
+                    messages.extend(source_attribute(attribute).err());
+                }
+                messages.extend(target_attribute(&content.target).err());
+            }
+            ObjectKind::Split(content) => {
+                messages.extend(source_attribute(&content.attribute).err());
+                for attribute in &content.targets {
+                    messages.extend(target_attribute(attribute).err());
+                }
+            }
+            ObjectKind::Default(content) => {
+                for attribute in content.attributes.keys() {
+                    messages.extend(target_attribute(attribute).err());
+                }
+            }
+            ObjectKind::Drop(content) => {
+                for attribute in &content.attributes {
+                    messages.extend(source_attribute(attribute).err());
+                }
+            }
+            _ => (),
+        }
+
+        let command_meta = ast.commands_meta.get(&i);
+        errors.extend(messages.into_iter().map(|message| {
+            Error::FromASTError {
+                line_number: command_meta.map(|meta| meta.line_number).unwrap_or(0),
+                raw_line: command_meta
+                    .map(|meta| meta.raw_line.clone())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                message,
+            }
+        }));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Types are compatible when equal. References are compatible with each
+/// other, as nested records are transformed on their own.
+fn compatible(from: Option<&NestedAttrType>, to: Option<&NestedAttrType>) -> bool {
+    match (from, to) {
+        (Some(NestedAttrType::Array(from)), Some(NestedAttrType::Array(to))) => {
+            compatible(Some(from), Some(to))
+        }
+        (Some(NestedAttrType::Reference(_)), Some(NestedAttrType::Reference(_))) => true,
+        (from, to) => from == to,
+    }
+}
+
+fn has_value_type(attribute: &Attribute, attribute_type: AttributeType) -> bool {
+    let mut nested = attribute.attribute_type.as_ref();
+    while let Some(NestedAttrType::Array(element)) = nested {
+        nested = Some(element);
+    }
+    nested == Some(&NestedAttrType::Value(attribute_type))
+}
+
+fn type_name(attribute_type: Option<&NestedAttrType>) -> String {
+    attribute_type
+        .and_then(|attribute_type| serde_json::to_value(attribute_type).ok())
+        .map(|value| match value {
+            serde_json::Value::String(name) => name,
+            value => value.to_string(),
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(ocafile: &str) -> OCABundle {
+        let ast = oca_file_semantics::ocafile::parse_from_string(ocafile.to_string()).unwrap();
+        oca_bundle_semantics::build::from_ast(None, &ast)
+            .unwrap()
+            .oca_bundle
+    }
+
+    #[test]
+    fn validate_against_bundles() {
+        let source = build(
+            r#"
+ADD ATTRIBUTE surname=Text age=Text weight=Numeric sex=Text
+ADD ENTRY_CODE ATTRS sex=["F", "M"]
+"#,
+        );
+        let target = build("ADD ATTRIBUTE last_name=Text age=Numeric mass=Numeric\n");
+        let parse = |commands: &str| {
+            oca_file_transformation::ocafile::parse_from_string(commands.to_string()).unwrap()
+        };
+
+        let ast = parse(
+            r#"
+RENAME ATTRIBUTE surname=last_name weight=mass
+CONVERT UNIT ATTRIBUTE weight kg -> g
+"#,
+        );
+        assert!(validate(&ast, &source, &target).is_ok());
+        let missing_meta = crate::build::from_ast(&ast).unwrap_err();
+        assert_eq!(
+            missing_meta
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "Error at line 2: missing source meta",
+                "Error at line 2: missing target meta"
+            ]
+        );
+
+        let ast = parse(
+            r#"
+RENAME ATTRIBUTE surname=name
+LINK ATTRIBUTE age -> age
+CONVERT UNIT ATTRIBUTE sex kg -> g
+MAP ENTRY_CODE ATTRIBUTE sex X=x
+DROP ATTRIBUTE nickname
+"#,
+        );
+        let errors = validate(&ast, &source, &target).unwrap_err();
+        let messages = errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "Error at line 2 (RENAME ATTRIBUTE surname=name): Attribute name not found in target bundle",
+                "Error at line 3 (LINK ATTRIBUTE age -> age): Attribute age of type Text can not be mapped to age of type Numeric",
+                "Error at line 4 (CONVERT UNIT ATTRIBUTE sex kg -> g): Attribute sex is not numeric",
+                "Error at line 5 (MAP ENTRY_CODE ATTRIBUTE sex X=x): Entry code X not found in attribute sex",
+                "Error at line 6 (DROP ATTRIBUTE nickname): Attribute nickname not found in source bundle",
+            ]
+        );
+    }
+}